@@ -1,6 +1,18 @@
+pub mod audit;
 pub mod scan;
 pub mod files;
 pub mod login;
 pub mod user;
 pub mod settings;
-pub mod trash;
\ No newline at end of file
+pub mod trash;
+pub mod api_token;
+pub mod totp;
+pub mod oidc;
+pub mod cli;
+pub mod export;
+pub mod report;
+pub mod stats;
+pub mod duplicate_dir;
+pub mod dir_tree;
+pub mod image_hash;
+pub mod audio_fingerprint;
\ No newline at end of file
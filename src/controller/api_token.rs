@@ -0,0 +1,149 @@
+use actix_web::{delete, get, post, web, HttpResponse};
+use chrono::{Duration, Local};
+use log::{info, warn};
+
+use crate::{
+    controller::{
+        audit::{record_audit_log, AuditContext, AuditTarget},
+        user::Authentication,
+    },
+    database::{
+        api_token::{ApiToken, ApiTokenList},
+        audit_log::AuditAction,
+        sqlite::PoolDatabaseManager,
+        user_info::UserInfo,
+    },
+    model::{
+        api_token::{CreateApiTokenRequest, CreateApiTokenResult, RevokeApiTokenRequest},
+        common::{ErrorCode, RestResponse},
+    },
+    utils::{
+        error::DfrError,
+        token::{generate_api_token, hash_api_token},
+    },
+};
+
+/// Api tokens can only be managed with login session, so that a leaked token can not be used to
+/// create more tokens
+pub fn check_session(authentication: &Authentication) -> Result<(), DfrError> {
+    if let Authentication::ApiToken(api_token) = authentication {
+        warn!(
+            "Api token {} of user {} tried to manage api tokens",
+            api_token.name, api_token.user_name
+        );
+        return DfrError::custom_error(
+            ErrorCode::PERMISSION_DENIED,
+            String::from("Api tokens can only be managed after login"),
+        );
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    summary = "List api tokens of current user",
+    responses(
+        (status = 200, description = "The list of api tokens", body = ApiTokenList),
+    ),
+)]
+#[get("/token/list")]
+pub async fn list_api_tokens(
+    db: web::Data<PoolDatabaseManager>,
+    current_user: UserInfo,
+) -> Result<HttpResponse, DfrError> {
+    let api_token_list = db.list_api_tokens(current_user.user_name.as_str())?;
+    Ok(HttpResponse::Ok().json(api_token_list))
+}
+
+#[utoipa::path(
+    summary = "Create api token for current user",
+    request_body(content = CreateApiTokenRequest),
+    responses(
+        (status = 200, description = "Create api token successfully, the token is only returned once", body = RestResponse<CreateApiTokenResult>),
+    ),
+)]
+#[post("/token")]
+pub async fn create_api_token(
+    requst_json: web::Json<CreateApiTokenRequest>,
+    db: web::Data<PoolDatabaseManager>,
+    current_user: UserInfo,
+    authentication: Authentication,
+    audit_context: AuditContext,
+) -> Result<HttpResponse, DfrError> {
+    check_session(&authentication)?;
+    let create_api_token_request = requst_json.into_inner();
+    let token = generate_api_token();
+    let now = Local::now();
+    let api_token = ApiToken {
+        id: 0,
+        user_name: current_user.user_name.clone(),
+        name: create_api_token_request.name,
+        token_hash: hash_api_token(token.as_str()),
+        scopes: create_api_token_request.scopes,
+        created_time: now,
+        expire_time: create_api_token_request
+            .expire_days
+            .map(|expire_days| now + Duration::days(expire_days as i64)),
+        last_used_time: None,
+        revoked: false,
+    };
+    let result = db.insert_api_token(&api_token);
+    record_audit_log(
+        db.get_ref(),
+        &audit_context,
+        AuditAction::CreateApiToken,
+        AuditTarget::name(api_token.name.as_str()),
+        &result,
+    );
+    let id = result?;
+    info!(
+        "Create api token {} for user {} with scopes {:?}",
+        api_token.name, api_token.user_name, api_token.scopes
+    );
+    Ok(
+        HttpResponse::Ok().json(RestResponse::succeed_with_data(CreateApiTokenResult {
+            id,
+            token,
+        })),
+    )
+}
+
+#[utoipa::path(
+    summary = "Revoke api token of current user",
+    request_body(content = RevokeApiTokenRequest),
+    responses(
+        (status = 200, description = "Revoke api token successfully"),
+    ),
+)]
+#[delete("/token")]
+pub async fn revoke_api_token(
+    requst_json: web::Json<RevokeApiTokenRequest>,
+    db: web::Data<PoolDatabaseManager>,
+    current_user: UserInfo,
+    authentication: Authentication,
+    audit_context: AuditContext,
+) -> Result<HttpResponse, DfrError> {
+    check_session(&authentication)?;
+    let revoke_api_token_request = requst_json.into_inner();
+    let result =
+        match db.revoke_api_token(current_user.user_name.as_str(), revoke_api_token_request.id) {
+            Ok(0) => DfrError::custom_error(
+                ErrorCode::API_TOKEN_NOT_FOUND,
+                format!("Api token {} not found", revoke_api_token_request.id),
+            ),
+            Ok(count) => Ok(count),
+            Err(e) => Err(e),
+        };
+    record_audit_log(
+        db.get_ref(),
+        &audit_context,
+        AuditAction::RevokeApiToken,
+        AuditTarget::name(revoke_api_token_request.id.to_string().as_str()),
+        &result,
+    );
+    result?;
+    info!(
+        "Revoke api token {} of user {}",
+        revoke_api_token_request.id, current_user.user_name
+    );
+    Ok(HttpResponse::Ok().finish())
+}
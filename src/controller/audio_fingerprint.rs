@@ -0,0 +1,51 @@
+use actix_web::{get, middleware::from_fn, web, HttpResponse};
+
+use crate::{
+    controller::user::require_read,
+    database::{sqlite::PoolDatabaseManager, user_info::UserInfo},
+    model::audio_fingerprint::{
+        SimilarAudioGroupList, SimilarAudioListParams, DEFAULT_MIN_AUDIO_SIMILARITY,
+    },
+    utils::{audio_fingerprint::find_similar_audio, error::DfrError},
+};
+
+#[utoipa::path(
+    summary = "List groups of similar audio tracks by fingerprint, with a suggested keeper",
+    params(SimilarAudioListParams),
+    responses(
+        (status = 200, description = "The list of similar audio groups", body = SimilarAudioGroupList)
+    ),
+)]
+#[get("/audio/similar", wrap = "from_fn(require_read)")]
+pub async fn list_similar_audio(
+    query_list: web::Query<SimilarAudioListParams>,
+    db: web::Data<PoolDatabaseManager>,
+    current_user: UserInfo,
+) -> Result<HttpResponse, DfrError> {
+    let files = db.list_audio_fingerprint_files(&current_user.allowed_paths)?;
+    let mut similar_audio_groups = find_similar_audio(
+        &files,
+        query_list
+            .min_similarity
+            .unwrap_or(DEFAULT_MIN_AUDIO_SIMILARITY),
+    );
+    if let Some(dir_path) = query_list.dir_path.as_deref() {
+        similar_audio_groups.retain(|group| {
+            group
+                .tracks
+                .iter()
+                .any(|track| track.files.iter().any(|file| file.contains(dir_path)))
+        });
+    }
+    let total_count = similar_audio_groups.len() as u64;
+    let offset = ((query_list.page_no.max(1) - 1) * query_list.page_count.max(0)) as usize;
+    let similar_audio_group_list = similar_audio_groups
+        .into_iter()
+        .skip(offset)
+        .take(query_list.page_count.max(0) as usize)
+        .collect();
+    Ok(HttpResponse::Ok().json(SimilarAudioGroupList {
+        similar_audio_group_list,
+        total_count,
+    }))
+}
@@ -0,0 +1,195 @@
+use std::{
+    future::{ready, Ready},
+    path::PathBuf,
+};
+
+use actix_session::SessionExt as _;
+use actix_web::{
+    dev::Payload, get, middleware::from_fn, web, Error as AWError, FromRequest, HttpMessage,
+    HttpRequest, HttpResponse,
+};
+use chrono::Local;
+use log::{error, info};
+
+use crate::{
+    controller::user::{require_admin, SessionExt},
+    database::{
+        audit_log::{AuditAction, AuditLog, AuditLogList},
+        file_info::{FileInfo, TrashFileInfo},
+        sqlite::PoolDatabaseManager,
+        user_info::UserInfo,
+    },
+    model::audit::AuditLogListParams,
+    utils::error::DfrError,
+};
+
+/// Page count used to read all audit logs when exporting
+const EXPORT_PAGE_COUNT: i64 = 1000;
+
+/// User and client of a request, used to record audit logs
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    /// Logged in user name
+    pub user_name: Option<String>,
+    /// Client ip of the request
+    pub client_ip: Option<String>,
+}
+
+impl FromRequest for AuditContext {
+    type Error = AWError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        // users authenticated with api token have no session
+        // the extensions must be released before the session is read from them
+        let user_name = req
+            .extensions()
+            .get::<UserInfo>()
+            .map(|user_info| user_info.user_name.clone());
+        let user_name = user_name.or_else(|| req.get_session().get_current_user().ok().flatten());
+        let client_ip = req.peer_addr().map(|addr| addr.ip().to_string());
+        ready(Ok(AuditContext {
+            user_name,
+            client_ip,
+        }))
+    }
+}
+
+/// File or user an audited action is performed on
+#[derive(Debug, Clone, Default)]
+pub struct AuditTarget {
+    pub path: Option<String>,
+    pub md5: Option<String>,
+    pub size: Option<u64>,
+}
+
+impl AuditTarget {
+    pub fn path(dir_path: &str, file_name: &str) -> Self {
+        let mut path = PathBuf::from(dir_path);
+        path.push(file_name);
+        AuditTarget {
+            path: Some(path.to_string_lossy().to_string()),
+            md5: None,
+            size: None,
+        }
+    }
+
+    /// Target which is not a file, e.g. a user or an api token
+    pub fn name(name: &str) -> Self {
+        AuditTarget {
+            path: Some(name.to_string()),
+            md5: None,
+            size: None,
+        }
+    }
+}
+
+impl From<&FileInfo> for AuditTarget {
+    fn from(file_info: &FileInfo) -> Self {
+        AuditTarget {
+            path: Some(file_info.file_path.clone()),
+            md5: file_info.inode_info.md5.clone(),
+            size: Some(file_info.inode_info.size),
+        }
+    }
+}
+
+impl From<&TrashFileInfo> for AuditTarget {
+    fn from(trash_file_info: &TrashFileInfo) -> Self {
+        AuditTarget {
+            path: Some(trash_file_info.get_file_path()),
+            md5: Some(trash_file_info.md5.clone()),
+            size: Some(trash_file_info.size),
+        }
+    }
+}
+
+/// Record audit log of an action, a failure to record is only logged so that it does not change the action result
+pub fn record_audit_log<T>(
+    db: &PoolDatabaseManager,
+    context: &AuditContext,
+    action: AuditAction,
+    target: AuditTarget,
+    result: &Result<T, DfrError>,
+) {
+    let audit_log = AuditLog {
+        time: Local::now(),
+        user_name: context.user_name.clone(),
+        client_ip: context.client_ip.clone(),
+        action,
+        path: target.path,
+        md5: target.md5,
+        size: target.size,
+        success: result.is_ok(),
+        message: result.as_ref().err().map(|e| e.to_string()),
+    };
+    if let Err(e) = db.insert_audit_log(&audit_log) {
+        error!("Failed to record audit log {:?}: {:?}", audit_log, e);
+    }
+}
+
+#[utoipa::path(
+    summary = "List audit logs",
+    params(AuditLogListParams),
+    responses(
+        (status = 200, description = "The list of audit log", body = AuditLogList)
+    ),
+)]
+#[get("/audit/list", wrap = "from_fn(require_admin)")]
+pub async fn list_audit_logs(
+    query_list: web::Query<AuditLogListParams>,
+    db: web::Data<PoolDatabaseManager>,
+) -> Result<HttpResponse, DfrError> {
+    let audit_log_list = db.list_audit_logs(&query_list)?;
+    Ok(HttpResponse::Ok().json(audit_log_list))
+}
+
+/// Write the audit logs matching the filters as csv, page by page. Return the csv and the count of
+/// exported audit logs.
+pub fn export_audit_logs_csv(
+    db: &PoolDatabaseManager,
+    mut query_list_params: AuditLogListParams,
+) -> Result<(Vec<u8>, usize), DfrError> {
+    query_list_params.page_no = 1;
+    query_list_params.page_count = EXPORT_PAGE_COUNT;
+    let mut writer = csv::Writer::from_writer(vec![]);
+    let mut export_count = 0;
+    loop {
+        let audit_log_list = db.list_audit_logs(&query_list_params)?;
+        for audit_log in audit_log_list.audit_log_list.iter() {
+            writer.serialize(audit_log).map_err(std::io::Error::from)?;
+        }
+        export_count += audit_log_list.audit_log_list.len();
+        if audit_log_list.audit_log_list.len() < query_list_params.page_count as usize {
+            break;
+        }
+        query_list_params.page_no += 1;
+    }
+    let data = writer
+        .into_inner()
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    Ok((data, export_count))
+}
+
+#[utoipa::path(
+    summary = "Export audit logs as csv, page parameters are ignored",
+    params(AuditLogListParams),
+    responses(
+        (status = 200, description = "Audit logs in csv format", content_type = "text/csv")
+    ),
+)]
+#[get("/audit/export", wrap = "from_fn(require_admin)")]
+pub async fn export_audit_logs(
+    query_list: web::Query<AuditLogListParams>,
+    db: web::Data<PoolDatabaseManager>,
+) -> Result<HttpResponse, DfrError> {
+    let (data, export_count) = export_audit_logs_csv(db.get_ref(), query_list.into_inner())?;
+    info!("Export {} audit logs", export_count);
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv")
+        .insert_header((
+            "Content-Disposition",
+            "attachment; filename=\"audit_log.csv\"",
+        ))
+        .body(data))
+}
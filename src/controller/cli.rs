@@ -0,0 +1,529 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, BufRead, Write},
+    path::{self, Path},
+};
+
+use chrono::{Local, TimeDelta};
+use log::info;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    controller::{
+        audit::{record_audit_log, AuditContext, AuditTarget},
+        export::DuplicateExporter,
+        files::_delete_file,
+        report::build_duplicate_report,
+        scan::scan_all_files,
+        trash::{_restore_trash_file, clear_trash_file},
+        user::_add_user,
+    },
+    database::{audit_log::AuditAction, file_info::FileInfo, sqlite::PoolDatabaseManager},
+    model::{
+        cli::{
+            AddedUser, Command, DedupeArgs, DedupeSummary, ExportArgs, ListArgs, PurgeSummary,
+            ReportArgs, RestoreSummary, ScanArgs, ScanSummary, TrashCommand, TrashListArgs,
+            UserCommand,
+        },
+        common::{ErrorCode, FailedFile},
+        files::DeleteFileRequest,
+        scan::SharedScanStatus,
+        settings::{ListSettings, ScanSettings, Settings, TrashListSettings},
+        user::AddUserRequest,
+    },
+    utils::{error::DfrError, report::render_html_report},
+    SharedSettings,
+};
+
+/// Database, settings and output format of a subcommand, the same as used by the web server
+struct CliContext {
+    db: PoolDatabaseManager,
+    settings: SharedSettings,
+    audit_context: AuditContext,
+    json: bool,
+}
+
+impl CliContext {
+    /// Print the result as JSON, or as text with the formatter
+    fn print<T: Serialize>(
+        &self,
+        result: &T,
+        format_text: impl FnOnce(&T),
+    ) -> Result<(), DfrError> {
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(result)?);
+        } else {
+            format_text(result);
+        }
+        Ok(())
+    }
+}
+
+/// Fail with a non zero exit code if some files could not be processed, so that cron notices
+fn check_failed_files(failed_files: &[FailedFile]) -> Result<(), DfrError> {
+    if failed_files.is_empty() {
+        return Ok(());
+    }
+    DfrError::custom_error(
+        ErrorCode::SYSTEM_ERROR,
+        format!("{} files failed", failed_files.len()),
+    )
+}
+
+/// Split a file path into dir path and file name, relative paths are resolved against the
+/// current directory
+fn split_file_path(file_path: &str) -> Result<(String, String), DfrError> {
+    let file_path = path::absolute(file_path)?;
+    match (file_path.parent(), file_path.file_name()) {
+        (Some(dir_path), Some(file_name)) => Ok((
+            dir_path.to_string_lossy().to_string(),
+            file_name.to_string_lossy().to_string(),
+        )),
+        _ => DfrError::custom_error(
+            ErrorCode::FILE_PATH_NOT_FOUND,
+            format!("Invalid file path {}", file_path.display()),
+        ),
+    }
+}
+
+/// Run a subcommand against the database and trash of the configured server
+pub async fn run_command(settings: Settings, command: Command, json: bool) -> Result<(), DfrError> {
+    let trash_path = Path::new(settings.system.trash_path.as_str());
+    if !trash_path.exists() {
+        fs::create_dir_all(trash_path)?;
+    }
+    let db = PoolDatabaseManager::new(&settings.system.db_path)?;
+    db.create_tables()?;
+    // the os user is recorded in audit logs, as there is no logged in user
+    let user_name = match std::env::var("USER") {
+        Ok(os_user_name) if !os_user_name.is_empty() => format!("cli:{}", os_user_name),
+        _ => String::from("cli"),
+    };
+    let context = CliContext {
+        db,
+        settings: SharedSettings::from(settings),
+        audit_context: AuditContext {
+            user_name: Some(user_name),
+            client_ip: None,
+        },
+        json,
+    };
+    match command {
+        Command::Serve => DfrError::custom_error(
+            ErrorCode::SYSTEM_ERROR,
+            String::from("Serve is not a headless command"),
+        ),
+        Command::Scan(args) => scan(&context, args).await,
+        Command::List(args) => list(&context, args),
+        Command::Export(args) => export(&context, args),
+        Command::Report(args) => report(&context, args),
+        Command::Dedupe(args) => dedupe(&context, args).await,
+        Command::Trash(TrashCommand::List(args)) => list_trash(&context, args),
+        Command::Trash(TrashCommand::Restore { paths }) => restore_trash(&context, paths).await,
+        Command::Trash(TrashCommand::Purge {
+            older_than_days,
+            all: _,
+        }) => purge_trash(&context, older_than_days).await,
+        Command::User(UserCommand::Add {
+            user_name,
+            role,
+            allowed_paths,
+            password_stdin,
+        }) => {
+            let password = if password_stdin {
+                let mut password = String::new();
+                io::stdin().lock().read_line(&mut password)?;
+                password.trim_end_matches(['\r', '\n']).to_string()
+            } else {
+                Uuid::new_v4().simple().to_string()[..16].to_string()
+            };
+            let add_user_request = AddUserRequest {
+                user_name,
+                password: password.clone(),
+                role,
+                allowed_paths: Some(allowed_paths),
+            };
+            add_user(&context, add_user_request, !password_stdin).await
+        }
+    }
+}
+
+async fn scan(context: &CliContext, args: ScanArgs) -> Result<(), DfrError> {
+    let trash_path = {
+        let settings = context.settings.lock().await;
+        fs::canonicalize(settings.system.trash_path.as_str())?
+    };
+    let mut scan_summaries = Vec::new();
+    for scan_path in args.paths {
+        let scan_request = ScanSettings {
+            scan_path: scan_path.clone(),
+            include_file_extensions: args
+                .include_file_extensions
+                .clone()
+                .map(|extensions| extensions.into_iter().collect()),
+            min_file_size: args.min_file_size,
+            max_file_size: args.max_file_size,
+            ignore_paths: args
+                .ignore_paths
+                .clone()
+                .map(|paths| paths.into_iter().collect()),
+            image_hash: Some(args.image_hash),
+            audio_fingerprint: Some(args.audio_fingerprint),
+            payload_hash: Some(args.payload_hash),
+            archive_members: Some(args.archive_members),
+        };
+        let scan_status = SharedScanStatus::new();
+        scan_all_files(&scan_request, &context.db, &scan_status, trash_path.clone()).await?;
+        let scanned_file_count = scan_status.lock().await.scanned_file_count;
+        scan_summaries.push(ScanSummary {
+            scan_path,
+            scanned_file_count,
+        });
+    }
+    context.print(&scan_summaries, |scan_summaries| {
+        for scan_summary in scan_summaries {
+            println!(
+                "Scanned {} files in {}",
+                scan_summary.scanned_file_count, scan_summary.scan_path
+            );
+        }
+    })
+}
+
+fn list(context: &CliContext, args: ListArgs) -> Result<(), DfrError> {
+    let query_list_params = ListSettings {
+        page_no: args.page_no,
+        page_count: args.page_count,
+        dir_path: args.dir_path,
+        file_name: args.file_name,
+        min_file_size: args.min_file_size,
+        min_md5_count: Some(args.min_md5_count),
+        ..Default::default()
+    };
+    let file_info_list = context.db.list_files(&query_list_params, &[])?;
+    context.print(&file_info_list, |file_info_list| {
+        for item in file_info_list.file_info_list.iter() {
+            println!(
+                "{}  {:>4}  {:>12}  {}",
+                item.file_info.inode_info.md5.as_deref().unwrap_or_default(),
+                item.md5_count,
+                item.file_info.inode_info.size,
+                item.file_info.file_path
+            );
+        }
+        println!(
+            "{} of {} files",
+            file_info_list.file_info_list.len(),
+            file_info_list.total_count
+        );
+    })
+}
+
+fn export(context: &CliContext, args: ExportArgs) -> Result<(), DfrError> {
+    let mut writer: Box<dyn Write> = match args.output.as_ref() {
+        Some(output_path) => Box::new(io::BufWriter::new(fs::File::create(output_path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut exporter = DuplicateExporter::new(
+        context.db.clone(),
+        ListSettings::from(args.filter),
+        Vec::new(),
+        args.format,
+    );
+    while let Some(chunk) = exporter.next_chunk()? {
+        writer.write_all(&chunk)?;
+    }
+    writer.flush()?;
+    // the summary is not mixed into the exported data written to stdout
+    if let Some(output_path) = args.output {
+        println!(
+            "Exported {} duplicate groups of {} files to {}",
+            exporter.exported_group_count(),
+            exporter.exported_file_count(),
+            output_path.display()
+        );
+    }
+    Ok(())
+}
+
+fn report(context: &CliContext, args: ReportArgs) -> Result<(), DfrError> {
+    let report = build_duplicate_report(
+        &context.db,
+        ListSettings::from(args.filter),
+        Vec::new(),
+        args.top_count,
+    )?;
+    fs::write(&args.output, render_html_report(&report))?;
+    context.print(&report.summary, |summary| {
+        println!(
+            "Wrote report of {} duplicate groups, {} bytes reclaimable, to {}",
+            summary.group_count,
+            summary.reclaimable_size,
+            args.output.display()
+        );
+    })
+}
+
+async fn dedupe(context: &CliContext, args: DedupeArgs) -> Result<(), DfrError> {
+    let query_list_params = ListSettings {
+        page_no: 1,
+        page_count: i64::MAX,
+        dir_path: args.dir_path,
+        min_md5_count: Some(2),
+        ..Default::default()
+    };
+    let file_info_list = context.db.list_files(&query_list_params, &[])?;
+    let mut duplicate_groups: BTreeMap<String, Vec<FileInfo>> = BTreeMap::new();
+    // archive members are never removed, they are not kept in place of a file either
+    for item in file_info_list.file_info_list {
+        if item.file_info.archive_path.is_some() {
+            continue;
+        }
+        if let Some(md5) = item.file_info.inode_info.md5.clone() {
+            duplicate_groups
+                .entry(md5)
+                .or_default()
+                .push(item.file_info);
+        }
+    }
+
+    let action = if args.delete_permanently {
+        AuditAction::DeleteFile
+    } else {
+        AuditAction::MoveToTrash
+    };
+    let mut dedupe_summary = DedupeSummary {
+        dry_run: args.dry_run,
+        ..Default::default()
+    };
+    for files in duplicate_groups.values() {
+        // files outside of the dir path are not listed, they are not removed either
+        if files.len() < 2 {
+            continue;
+        }
+        let Some(keep_index) = args.policy.select_file_to_keep(files) else {
+            continue;
+        };
+        dedupe_summary
+            .kept_files
+            .push(files[keep_index].file_path.clone());
+        for (index, file_info) in files.iter().enumerate() {
+            if index == keep_index {
+                continue;
+            }
+            if args.dry_run {
+                dedupe_summary
+                    .removed_files
+                    .push(file_info.file_path.clone());
+                dedupe_summary.removed_size += file_info.inode_info.size;
+                continue;
+            }
+            let delete_file_request = DeleteFileRequest {
+                dir_path: file_info.dir_path.clone(),
+                file_name: file_info.file_name.clone(),
+                delete_permanently: Some(args.delete_permanently),
+                force_delete: Some(false),
+            };
+            let result = _delete_file(delete_file_request, &context.db, &context.settings).await;
+            record_audit_log(
+                &context.db,
+                &context.audit_context,
+                action,
+                AuditTarget::from(file_info),
+                &result,
+            );
+            match result {
+                Ok(_) => {
+                    dedupe_summary
+                        .removed_files
+                        .push(file_info.file_path.clone());
+                    dedupe_summary.removed_size += file_info.inode_info.size;
+                }
+                Err(e) => dedupe_summary.failed_files.push(FailedFile {
+                    file_path: file_info.file_path.clone(),
+                    error: e.to_string(),
+                }),
+            }
+        }
+    }
+    info!(
+        "Dedupe with policy {:?} removed {} files",
+        args.policy,
+        dedupe_summary.removed_files.len()
+    );
+
+    context.print(&dedupe_summary, |dedupe_summary| {
+        let removed = match (dedupe_summary.dry_run, args.delete_permanently) {
+            (true, _) => "Would remove",
+            (false, true) => "Deleted",
+            (false, false) => "Moved to trash",
+        };
+        for file_path in dedupe_summary.kept_files.iter() {
+            println!("Kept {}", file_path);
+        }
+        for file_path in dedupe_summary.removed_files.iter() {
+            println!("{} {}", removed, file_path);
+        }
+        for failed_file in dedupe_summary.failed_files.iter() {
+            println!("Failed {}: {}", failed_file.file_path, failed_file.error);
+        }
+        println!(
+            "{} {} files of {} bytes, kept {} files",
+            removed,
+            dedupe_summary.removed_files.len(),
+            dedupe_summary.removed_size,
+            dedupe_summary.kept_files.len()
+        );
+    })?;
+    check_failed_files(&dedupe_summary.failed_files)
+}
+
+fn list_trash(context: &CliContext, args: TrashListArgs) -> Result<(), DfrError> {
+    let query_list_params = TrashListSettings {
+        page_no: args.page_no,
+        page_count: args.page_count,
+        dir_path: args.dir_path,
+        ..Default::default()
+    };
+    let trash_file_info_list = context.db.list_trash_files(&query_list_params, &[])?;
+    context.print(&trash_file_info_list, |trash_file_info_list| {
+        for trash_file_info in trash_file_info_list.trash_file_info_list.iter() {
+            println!(
+                "{}  {:>12}  {}",
+                trash_file_info.remove_time.format("%Y-%m-%d %H:%M:%S"),
+                trash_file_info.size,
+                trash_file_info.get_file_path()
+            );
+        }
+        println!(
+            "{} of {} trash files",
+            trash_file_info_list.trash_file_info_list.len(),
+            trash_file_info_list.total_count
+        );
+    })
+}
+
+async fn restore_trash(context: &CliContext, paths: Vec<String>) -> Result<(), DfrError> {
+    let mut restore_summary = RestoreSummary::default();
+    for file_path in paths {
+        let (dir_path, file_name) = split_file_path(file_path.as_str())?;
+        let trash_file_info = match context
+            .db
+            .get_trash_file_by_path(dir_path.as_str(), file_name.as_str())
+        {
+            Ok(trash_file_info) => trash_file_info,
+            Err(e) => {
+                restore_summary.failed_files.push(FailedFile {
+                    file_path,
+                    error: e.to_string(),
+                });
+                continue;
+            }
+        };
+        let result = _restore_trash_file(&trash_file_info, &context.db, &context.settings).await;
+        record_audit_log(
+            &context.db,
+            &context.audit_context,
+            AuditAction::RestoreTrashFile,
+            AuditTarget::from(&trash_file_info),
+            &result,
+        );
+        match result {
+            Ok(_) => restore_summary
+                .restored_files
+                .push(trash_file_info.get_file_path()),
+            Err(e) => restore_summary.failed_files.push(FailedFile {
+                file_path: trash_file_info.get_file_path(),
+                error: e.to_string(),
+            }),
+        }
+    }
+    context.print(&restore_summary, |restore_summary| {
+        for file_path in restore_summary.restored_files.iter() {
+            println!("Restored {}", file_path);
+        }
+        for failed_file in restore_summary.failed_files.iter() {
+            println!("Failed {}: {}", failed_file.file_path, failed_file.error);
+        }
+    })?;
+    check_failed_files(&restore_summary.failed_files)
+}
+
+async fn purge_trash(context: &CliContext, older_than_days: Option<u32>) -> Result<(), DfrError> {
+    let query_list_params = TrashListSettings {
+        page_no: 1,
+        page_count: i64::MAX,
+        end_removed_time: older_than_days.map(|days| Local::now() - TimeDelta::days(days as i64)),
+        ..Default::default()
+    };
+    let trash_file_info_list = context.db.list_trash_files(&query_list_params, &[])?;
+    let mut purge_summary = PurgeSummary::default();
+    for trash_file_info in trash_file_info_list.trash_file_info_list.iter() {
+        let result = clear_trash_file(&context.settings, &context.db, trash_file_info).await;
+        record_audit_log(
+            &context.db,
+            &context.audit_context,
+            AuditAction::PurgeTrashFile,
+            AuditTarget::from(trash_file_info),
+            &result,
+        );
+        match result {
+            Ok(_) => {
+                purge_summary
+                    .purged_files
+                    .push(trash_file_info.get_file_path());
+                purge_summary.purged_size += trash_file_info.size;
+            }
+            Err(e) => purge_summary.failed_files.push(FailedFile {
+                file_path: trash_file_info.get_file_path(),
+                error: e.to_string(),
+            }),
+        }
+    }
+    context.print(&purge_summary, |purge_summary| {
+        for file_path in purge_summary.purged_files.iter() {
+            println!("Purged {}", file_path);
+        }
+        for failed_file in purge_summary.failed_files.iter() {
+            println!("Failed {}: {}", failed_file.file_path, failed_file.error);
+        }
+        println!(
+            "Purged {} trash files of {} bytes",
+            purge_summary.purged_files.len(),
+            purge_summary.purged_size
+        );
+    })?;
+    check_failed_files(&purge_summary.failed_files)
+}
+
+async fn add_user(
+    context: &CliContext,
+    add_user_request: AddUserRequest,
+    show_password: bool,
+) -> Result<(), DfrError> {
+    let result = _add_user(&add_user_request, &context.db).await;
+    record_audit_log(
+        &context.db,
+        &context.audit_context,
+        AuditAction::AddUser,
+        AuditTarget::name(add_user_request.user_name.as_str()),
+        &result,
+    );
+    result?;
+    let added_user = AddedUser {
+        user_name: add_user_request.user_name,
+        role: add_user_request.role,
+        password: show_password.then_some(add_user_request.password),
+    };
+    context.print(&added_user, |added_user| {
+        println!(
+            "Added user {} with role {}",
+            added_user.user_name, added_user.role
+        );
+        if let Some(password) = added_user.password.as_ref() {
+            println!("Password: {}", password);
+        }
+    })
+}
@@ -0,0 +1,26 @@
+use actix_web::{get, middleware::from_fn, web, HttpResponse};
+
+use crate::{
+    controller::user::require_read,
+    database::{dir_tree::DirTree, sqlite::PoolDatabaseManager, user_info::UserInfo},
+    model::dir_tree::DirTreeParams,
+    utils::error::DfrError,
+};
+
+#[utoipa::path(
+    summary = "Get file and duplicate counts of a directory and each of its subdirectories",
+    params(DirTreeParams),
+    responses(
+        (status = 200, description = "One level of the directory tree", body = DirTree)
+    ),
+)]
+#[get("/dirs/tree", wrap = "from_fn(require_read)")]
+pub async fn query_dir_tree(
+    query_params: web::Query<DirTreeParams>,
+    db: web::Data<PoolDatabaseManager>,
+    current_user: UserInfo,
+) -> Result<HttpResponse, DfrError> {
+    let dir_path = query_params.dir_path.as_deref().unwrap_or("/");
+    let dir_tree = db.get_dir_tree(dir_path, &current_user.allowed_paths)?;
+    Ok(HttpResponse::Ok().json(dir_tree))
+}
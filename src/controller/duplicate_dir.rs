@@ -0,0 +1,166 @@
+use std::path::Path;
+
+use actix_web::{delete, get, middleware::from_fn, web, HttpResponse};
+use log::info;
+
+use crate::{
+    controller::{
+        audit::{record_audit_log, AuditContext, AuditTarget},
+        files::{_delete_file, delete_action},
+        user::{check_path_allowed, require_read, require_trash, Authentication},
+    },
+    database::{sqlite::PoolDatabaseManager, user_info::UserInfo},
+    model::{
+        common::{ErrorCode, FailedFile, RestResponse},
+        duplicate_dir::{
+            DeleteDirRequest, DeleteDirResult, DuplicateDirList, DuplicateDirListParams,
+            DEFAULT_MIN_OVERLAP_PERCENT,
+        },
+        files::DeleteFileRequest,
+    },
+    utils::{
+        duplicate_dir::{find_duplicate_dirs, is_in_dir, join_path},
+        error::DfrError,
+    },
+    SharedSettings,
+};
+
+#[utoipa::path(
+    summary = "List identical and near-identical directory pairs",
+    params(DuplicateDirListParams),
+    responses(
+        (status = 200, description = "The list of duplicate directory pairs", body = DuplicateDirList)
+    ),
+)]
+#[get("/dirs/duplicate", wrap = "from_fn(require_read)")]
+pub async fn list_duplicate_dirs(
+    query_list: web::Query<DuplicateDirListParams>,
+    db: web::Data<PoolDatabaseManager>,
+    current_user: UserInfo,
+) -> Result<HttpResponse, DfrError> {
+    let files = db.list_file_hashes(&current_user.allowed_paths)?;
+    let min_overlap_percent = query_list
+        .min_overlap_percent
+        .unwrap_or(DEFAULT_MIN_OVERLAP_PERCENT);
+    let mut duplicate_dirs = find_duplicate_dirs(&files, min_overlap_percent);
+    if let Some(dir_path) = query_list.dir_path.as_deref() {
+        duplicate_dirs.retain(|pair| {
+            pair.dir_path.contains(dir_path) || pair.other_dir_path.contains(dir_path)
+        });
+    }
+    let total_count = duplicate_dirs.len() as u64;
+    let offset = ((query_list.page_no.max(1) - 1) * query_list.page_count.max(0)) as usize;
+    let duplicate_dir_list = duplicate_dirs
+        .into_iter()
+        .skip(offset)
+        .take(query_list.page_count.max(0) as usize)
+        .collect();
+    Ok(HttpResponse::Ok().json(DuplicateDirList {
+        duplicate_dir_list,
+        total_count,
+    }))
+}
+
+#[utoipa::path(
+    summary = "Delete all files of a directory and its subdirectories",
+    request_body(content = DeleteDirRequest),
+    responses(
+        (status = 200, description = "Removed and failed files", body = RestResponse<DeleteDirResult>),
+    ),
+)]
+#[delete("/dir", wrap = "from_fn(require_trash)")]
+pub async fn delete_dir(
+    request_json: web::Json<DeleteDirRequest>,
+    db: web::Data<PoolDatabaseManager>,
+    settings: web::Data<SharedSettings>,
+    current_user: UserInfo,
+    authentication: Authentication,
+    audit_context: AuditContext,
+) -> Result<HttpResponse, DfrError> {
+    let delete_dir_request = request_json.into_inner();
+    let dir_path = delete_dir_request.dir_path.trim_end_matches('/');
+    let dir_path = if dir_path.is_empty() { "/" } else { dir_path };
+    check_path_allowed(&current_user, Path::new(dir_path))?;
+    let delete_permanently = delete_dir_request.delete_permanently.unwrap_or(false);
+    let force_delete = delete_dir_request.force_delete.unwrap_or(false);
+    let action = delete_action(
+        delete_permanently,
+        &current_user,
+        &authentication,
+        &settings,
+    )
+    .await?;
+
+    let files = db.list_file_hashes(&[dir_path.to_string()])?;
+    if files.is_empty() {
+        return DfrError::custom_error(
+            ErrorCode::FILE_PATH_NOT_FOUND,
+            format!("No file is found in {}", dir_path),
+        );
+    }
+    // files are only removed if all of them have a copy outside of the directory, so that the
+    // directory is never removed partly
+    if !force_delete {
+        let mut unique_file_count = 0;
+        for file in files.iter() {
+            let has_copy = db
+                .get_file_list_by_md5(file.md5.as_str())?
+                .iter()
+                .any(|other_file| !is_in_dir(other_file.dir_path.as_str(), dir_path));
+            if !has_copy {
+                unique_file_count += 1;
+            }
+        }
+        if unique_file_count > 0 {
+            return DfrError::custom_error(
+                ErrorCode::NOT_ALLOW_DELETE_FILE,
+                format!(
+                    "Not allow to delete {}, {} files have no copy outside of it",
+                    dir_path, unique_file_count
+                ),
+            );
+        }
+    }
+
+    // archive members are never deleted, they are removed with their archive
+    let files = files
+        .into_iter()
+        .filter(|file| {
+            !matches!(db.get_file_by_path(&file.dir_path, &file.file_name),
+                Ok(db_file_info) if db_file_info.archive_path.is_some())
+        })
+        .collect::<Vec<_>>();
+    let mut delete_dir_result = DeleteDirResult::default();
+    for file in files {
+        let file_path = join_path(&file.dir_path, &file.file_name);
+        let target = match db.get_file_by_path(&file.dir_path, &file.file_name) {
+            Ok(db_file_info) => AuditTarget::from(&db_file_info),
+            Err(_) => AuditTarget::path(&file.dir_path, &file.file_name),
+        };
+        let delete_file_request = DeleteFileRequest {
+            dir_path: file.dir_path,
+            file_name: file.file_name,
+            delete_permanently: Some(delete_permanently),
+            force_delete: Some(force_delete),
+        };
+        let result = _delete_file(delete_file_request, &db, &settings).await;
+        record_audit_log(&db, &audit_context, action, target, &result);
+        match result {
+            Ok(_) => {
+                delete_dir_result.removed_files.push(file_path);
+                delete_dir_result.removed_size += file.size;
+            }
+            Err(e) => delete_dir_result.failed_files.push(FailedFile {
+                file_path,
+                error: e.to_string(),
+            }),
+        }
+    }
+    info!(
+        "Delete directory {}, removed {} files, {} files failed",
+        dir_path,
+        delete_dir_result.removed_files.len(),
+        delete_dir_result.failed_files.len()
+    );
+    Ok(HttpResponse::Ok().json(RestResponse::succeed_with_data(delete_dir_result)))
+}
@@ -0,0 +1,219 @@
+use actix_web::{get, middleware::from_fn, web, HttpResponse};
+use futures::stream;
+use log::info;
+
+use crate::{
+    controller::user::require_read,
+    database::{sqlite::PoolDatabaseManager, user_info::UserInfo},
+    model::{
+        export::{DuplicateFileRow, DuplicateGroup, ExportFormat, ExportParams},
+        settings::ListSettings,
+    },
+    utils::error::DfrError,
+};
+
+/// Page count used to read all duplicate files when exporting
+const EXPORT_PAGE_COUNT: i64 = 1000;
+
+/// Export of the duplicate files matching the list filters, read page by page and grouped by
+/// md5, so that large results are not held in memory
+pub struct DuplicateExporter {
+    db: PoolDatabaseManager,
+    query_list_params: ListSettings,
+    allowed_paths: Vec<String>,
+    format: ExportFormat,
+    /// Last group read, it may continue on the next page
+    pending_group: Option<DuplicateGroup>,
+    exported_group_count: usize,
+    exported_file_count: usize,
+    started: bool,
+    finished: bool,
+}
+
+impl DuplicateExporter {
+    /// Page and order parameters of the list settings are ignored, the files of a group must be
+    /// read one after another. Files grouped by payload md5 may differ in size, so ordering by size
+    /// would split their group.
+    pub fn new(
+        db: PoolDatabaseManager,
+        mut query_list_params: ListSettings,
+        allowed_paths: Vec<String>,
+        format: ExportFormat,
+    ) -> Self {
+        query_list_params.page_no = 1;
+        query_list_params.page_count = EXPORT_PAGE_COUNT;
+        query_list_params.order_by = None;
+        query_list_params.order_asc = None;
+        DuplicateExporter {
+            db,
+            query_list_params,
+            allowed_paths,
+            format,
+            pending_group: None,
+            exported_group_count: 0,
+            exported_file_count: 0,
+            started: false,
+            finished: false,
+        }
+    }
+
+    pub fn exported_group_count(&self) -> usize {
+        self.exported_group_count
+    }
+
+    pub fn exported_file_count(&self) -> usize {
+        self.exported_file_count
+    }
+
+    /// Read the next page and encode the groups completed by it, none if all are exported
+    pub fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, DfrError> {
+        let Some(groups) = self.next_groups()? else {
+            return Ok(None);
+        };
+        let chunk = self.encode(&groups, self.finished);
+        if chunk.is_err() {
+            self.finished = true;
+        }
+        chunk.map(Some)
+    }
+
+    /// Read the next page and return the groups completed by it, none if all are read
+    pub fn next_groups(&mut self) -> Result<Option<Vec<DuplicateGroup>>, DfrError> {
+        if self.finished {
+            return Ok(None);
+        }
+        let file_info_list = match self
+            .db
+            .list_files(&self.query_list_params, &self.allowed_paths)
+        {
+            Ok(file_info_list) => file_info_list,
+            Err(e) => {
+                self.finished = true;
+                return Err(e);
+            }
+        };
+        let last_page =
+            file_info_list.file_info_list.len() < self.query_list_params.page_count as usize;
+        self.query_list_params.page_no += 1;
+
+        let group_by = self.query_list_params.group_by.unwrap_or_default();
+        let mut groups = Vec::new();
+        for item in file_info_list.file_info_list {
+            let Some(md5) = item
+                .file_info
+                .inode_info
+                .group_md5(group_by)
+                .map(String::from)
+            else {
+                continue;
+            };
+            // files are ordered by md5 count and md5, so a group is never split by another one
+            match self.pending_group.as_mut() {
+                Some(group) if group.md5 == md5 => group.push(item),
+                _ => {
+                    let mut group = DuplicateGroup::new(md5, &item);
+                    group.push(item);
+                    groups.extend(self.pending_group.replace(group));
+                }
+            }
+        }
+        if last_page {
+            groups.extend(self.pending_group.take());
+            self.finished = true;
+        }
+        Ok(Some(groups))
+    }
+
+    fn encode(&mut self, groups: &[DuplicateGroup], last_page: bool) -> Result<Vec<u8>, DfrError> {
+        let mut chunk = Vec::new();
+        match self.format {
+            ExportFormat::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(!self.started)
+                    .from_writer(&mut chunk);
+                for group in groups {
+                    for file in group.files.iter() {
+                        writer
+                            .serialize(DuplicateFileRow::new(group, file))
+                            .map_err(std::io::Error::from)?;
+                        // the header is written with the first row only
+                        self.started = true;
+                    }
+                }
+                writer.flush()?;
+            }
+            ExportFormat::Json => {
+                if !self.started {
+                    chunk.push(b'[');
+                    self.started = true;
+                }
+                for (index, group) in groups.iter().enumerate() {
+                    if self.exported_group_count + index > 0 {
+                        chunk.push(b',');
+                    }
+                    chunk.push(b'\n');
+                    serde_json::to_writer(&mut chunk, group)?;
+                }
+                if last_page {
+                    chunk.extend_from_slice(b"\n]\n");
+                }
+            }
+            ExportFormat::Ndjson => {
+                for group in groups {
+                    serde_json::to_writer(&mut chunk, group)?;
+                    chunk.push(b'\n');
+                }
+            }
+        }
+        self.exported_group_count += groups.len();
+        self.exported_file_count += groups.iter().map(|group| group.files.len()).sum::<usize>();
+        Ok(chunk)
+    }
+}
+
+#[utoipa::path(
+    summary = "Export duplicate files grouped by md5, page parameters are ignored",
+    params(ListSettings, ExportParams),
+    responses(
+        (status = 200, description = "Duplicate files in csv format, one row per file", content_type = "text/csv"),
+        (status = 200, description = "Duplicate groups in json format", content_type = "application/json", body = Vec<DuplicateGroup>),
+        (status = 200, description = "Duplicate groups in ndjson format, one group per line", content_type = "application/x-ndjson"),
+    ),
+)]
+#[get("/list/export", wrap = "from_fn(require_read)")]
+pub async fn export_duplicate_files(
+    query_list: web::Query<ListSettings>,
+    export_params: web::Query<ExportParams>,
+    db: web::Data<PoolDatabaseManager>,
+    current_user: UserInfo,
+) -> Result<HttpResponse, DfrError> {
+    let format = export_params.format.unwrap_or_default();
+    info!(
+        "Export duplicate files as {}, user: {}",
+        format.file_extension(),
+        current_user.user_name
+    );
+    let exporter = DuplicateExporter::new(
+        db.get_ref().clone(),
+        query_list.into_inner(),
+        current_user.allowed_paths,
+        format,
+    );
+    let body = stream::unfold(exporter, |mut exporter| async move {
+        match exporter.next_chunk() {
+            Ok(Some(chunk)) => Some((Ok(web::Bytes::from(chunk)), exporter)),
+            Ok(None) => None,
+            Err(e) => Some((Err(actix_web::Error::from(e)), exporter)),
+        }
+    });
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header((
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"duplicate_files.{}\"",
+                format.file_extension()
+            ),
+        ))
+        .streaming(body))
+}
@@ -1,14 +1,22 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use actix_web::{delete, get, web, Error as AWError, HttpResponse};
+use actix_web::{delete, get, middleware::from_fn, web, Error as AWError, HttpResponse};
 use chrono::Local;
 use log::{info, warn};
 use tokio::fs;
 
 use crate::{
+    controller::{
+        audit::{record_audit_log, AuditContext, AuditTarget},
+        trash::move_file_to_trash,
+        user::{check_path_allowed, require_read, require_trash, Authentication},
+    },
     database::{
-        file_info::{FileInfo, FileInfoList},
+        api_token::TokenScope,
+        audit_log::AuditAction,
+        file_info::{FileAttributes, FileInfo, FileInfoList},
         sqlite::PoolDatabaseManager,
+        user_info::{UserInfo, UserRole},
     },
     model::{
         common::{ErrorCode, RestResponse},
@@ -25,7 +33,7 @@ use crate::{
         (status = 200, description = "List settings", body = RestResponse<ListSettings>),
     ),
 )]
-#[get("/list/settings")]
+#[get("/list/settings", wrap = "from_fn(require_read)")]
 pub async fn query_list_settings(
     settings: web::Data<SharedSettings>,
 ) -> Result<HttpResponse, AWError> {
@@ -40,14 +48,17 @@ pub async fn query_list_settings(
         (status = 200, description = "The list of file info with md5 count", body=FileInfoList)
     ),
 )]
-#[get("/list")]
+#[get("/list", wrap = "from_fn(require_read)")]
 pub async fn list_files(
     query_list: web::Query<ListSettings>,
     db: web::Data<PoolDatabaseManager>,
     settings: web::Data<SharedSettings>,
+    current_user: UserInfo,
 ) -> Result<HttpResponse, AWError> {
     let conn = db.get_ref();
-    let file_info_list = conn.0.list_files(&query_list)?;
+    let file_info_list = conn
+        .0
+        .list_files(&query_list, &current_user.allowed_paths)?;
     {
         let mut settings = settings.lock().await;
         settings.list = query_list.into_inner().clone();
@@ -56,6 +67,31 @@ pub async fn list_files(
     Ok(HttpResponse::Ok().json(file_info_list))
 }
 
+/// Audit action of deleting files, only admins are allowed to delete files permanently
+pub async fn delete_action(
+    delete_permanently: bool,
+    current_user: &UserInfo,
+    authentication: &Authentication,
+    settings: &SharedSettings,
+) -> Result<AuditAction, DfrError> {
+    if !delete_permanently {
+        return Ok(AuditAction::MoveToTrash);
+    }
+    if current_user.role < UserRole::Admin || !authentication.has_scope(TokenScope::Delete) {
+        return DfrError::custom_error(
+            ErrorCode::PERMISSION_DENIED,
+            String::from("Only admin is allowed to delete files permanently"),
+        );
+    }
+    if settings.lock().await.system.totp_required_for_delete && !authentication.is_totp_verified() {
+        return DfrError::custom_error(
+            ErrorCode::PERMISSION_DENIED,
+            String::from("Two-factor authentication is required to delete files permanently"),
+        );
+    }
+    Ok(AuditAction::DeleteFile)
+}
+
 #[utoipa::path(
     summary = "Delete a file",
     request_body(content = DeleteFileRequest),
@@ -65,18 +101,60 @@ pub async fn list_files(
         (status = 501, description = "Not implemented"),
     ),
 )]
-#[delete("/file")]
+#[delete("/file", wrap = "from_fn(require_trash)")]
 pub async fn delete_file(
     requst_json: web::Json<DeleteFileRequest>,
     db: web::Data<PoolDatabaseManager>,
     settings: web::Data<SharedSettings>,
+    current_user: UserInfo,
+    authentication: Authentication,
+    audit_context: AuditContext,
 ) -> Result<HttpResponse, DfrError> {
     let delete_file_request = requst_json.into_inner();
+    check_path_allowed(
+        &current_user,
+        Path::new(delete_file_request.dir_path.as_str()),
+    )?;
+    let action = delete_action(
+        delete_file_request.delete_permanently.unwrap_or(false),
+        &current_user,
+        &authentication,
+        &settings,
+    )
+    .await?;
+    let target = match db.get_file_by_path(
+        delete_file_request.dir_path.as_str(),
+        delete_file_request.file_name.as_str(),
+    ) {
+        Ok(db_file_info) => AuditTarget::from(&db_file_info),
+        Err(_) => AuditTarget::path(
+            delete_file_request.dir_path.as_str(),
+            delete_file_request.file_name.as_str(),
+        ),
+    };
+    let result = _delete_file(delete_file_request, db.get_ref(), settings.get_ref()).await;
+    record_audit_log(db.get_ref(), &audit_context, action, target, &result);
+    result
+}
 
+pub async fn _delete_file(
+    delete_file_request: DeleteFileRequest,
+    db: &PoolDatabaseManager,
+    settings: &SharedSettings,
+) -> Result<HttpResponse, DfrError> {
     let db_file_info = db.get_file_by_path(
         delete_file_request.dir_path.as_str(),
         delete_file_request.file_name.as_str(),
     )?;
+    if let Some(archive_path) = db_file_info.archive_path.as_deref() {
+        return DfrError::custom_error(
+            ErrorCode::NOT_ALLOW_DELETE_FILE,
+            format!(
+                "Not allow to delete file {}, it is a member of archive {}",
+                db_file_info.file_path, archive_path
+            ),
+        );
+    }
 
     let file = PathBuf::from(db_file_info.file_path.as_str());
     if !file.exists() {
@@ -113,6 +191,14 @@ pub async fn delete_file(
                 continue;
             }
 
+            // an archive member is a copy as long as its archive is unchanged
+            if let Some(archive_path) = item.archive_path.as_deref() {
+                if !is_archive_unchanged(db, archive_path) {
+                    dup_len -= 1;
+                }
+                continue;
+            }
+
             // check if file is exist
             let file = PathBuf::from(db_file_info.file_path.as_str());
             if !file.exists() {
@@ -161,31 +247,11 @@ pub async fn delete_file(
     } else {
         // move to trash dir
         info!("Move file {} to trash dir", db_file_info.file_path);
-        let trash_file_path = {
-            let settings = settings.lock().await;
-            let mut trash_path = PathBuf::from(settings.system.trash_path.as_str());
-            trash_path.push(db_file_info.inode_info.md5.clone().unwrap());
-            trash_path
-        };
-        // try to rename file
-        if !trash_file_path.exists() {
-            let result =
-                fs::rename(db_file_info.file_path.as_str(), trash_file_path.as_path()).await;
-            if let Err(error) = result {
-                warn!("Failed to rename file: {:?}, try to copy and delete", error);
-                // if rename failed, try to copy and delete
-                fs::copy(db_file_info.file_path.as_str(), trash_file_path.as_path()).await?;
-                fs::remove_file(db_file_info.file_path.as_str()).await?;
-            }
-        } else {
-            info!(
-                "Found file in trash dir: {:?}, remove file directly",
-                trash_file_path
-            );
-            fs::remove_file(db_file_info.file_path.as_str()).await?;
-        }
+        // keep attributes which would be lost by moving the file to the shared trash file
+        let attributes = FileAttributes::read(db_file_info.file_path.as_str())?;
+        let storage = move_file_to_trash(settings, &db_file_info).await?;
 
-        db.move_file_to_trash(&db_file_info)?;
+        db.move_file_to_trash(&db_file_info, &attributes, &storage)?;
     }
 
     info!(
@@ -195,6 +261,21 @@ pub async fn delete_file(
     Ok(HttpResponse::Ok().finish())
 }
 
+/// Check if the archive exists and is the same as in the database
+fn is_archive_unchanged(db: &PoolDatabaseManager, archive_path: &str) -> bool {
+    let Some((dir_path, file_name)) = archive_path.rsplit_once('/') else {
+        return false;
+    };
+    let dir_path = if dir_path.is_empty() { "/" } else { dir_path };
+    match (
+        db.get_file_by_path(dir_path, file_name),
+        FileInfo::new(archive_path, 0, Local::now()),
+    ) {
+        (Ok(db_file_info), Ok(file_info)) => db_file_info.inode_info == file_info.inode_info,
+        _ => false,
+    }
+}
+
 #[utoipa::path(
     summary = "Delete files",
     request_body(content = DeleteFilesRequest),
@@ -204,7 +285,7 @@ pub async fn delete_file(
         (status = 501, description = "Not implemented"),
     ),
 )]
-#[delete("/files")]
+#[delete("/files", wrap = "from_fn(require_trash)")]
 pub async fn delete_files(
     requst_json: web::Json<DeleteFilesRequest>,
 ) -> Result<HttpResponse, AWError> {
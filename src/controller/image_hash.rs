@@ -0,0 +1,52 @@
+use actix_web::{get, middleware::from_fn, web, HttpResponse};
+
+use crate::{
+    controller::user::require_read,
+    database::{sqlite::PoolDatabaseManager, user_info::UserInfo},
+    model::image_hash::{
+        SimilarImageGroupList, SimilarImageListParams, DEFAULT_MAX_IMAGE_DISTANCE,
+    },
+    utils::{error::DfrError, image_hash::find_similar_images},
+};
+
+#[utoipa::path(
+    summary = "List groups of similar images by perceptual hash",
+    params(SimilarImageListParams),
+    responses(
+        (status = 200, description = "The list of similar image groups", body = SimilarImageGroupList)
+    ),
+)]
+#[get("/images/similar", wrap = "from_fn(require_read)")]
+pub async fn list_similar_images(
+    query_list: web::Query<SimilarImageListParams>,
+    db: web::Data<PoolDatabaseManager>,
+    current_user: UserInfo,
+) -> Result<HttpResponse, DfrError> {
+    let files = db.list_image_hash_files(&current_user.allowed_paths)?;
+    let mut similar_image_groups = find_similar_images(
+        &files,
+        query_list
+            .max_distance
+            .unwrap_or(DEFAULT_MAX_IMAGE_DISTANCE),
+        query_list.algorithm.unwrap_or_default(),
+    );
+    if let Some(dir_path) = query_list.dir_path.as_deref() {
+        similar_image_groups.retain(|group| {
+            group
+                .images
+                .iter()
+                .any(|image| image.files.iter().any(|file| file.contains(dir_path)))
+        });
+    }
+    let total_count = similar_image_groups.len() as u64;
+    let offset = ((query_list.page_no.max(1) - 1) * query_list.page_count.max(0)) as usize;
+    let similar_image_group_list = similar_image_groups
+        .into_iter()
+        .skip(offset)
+        .take(query_list.page_count.max(0) as usize)
+        .collect();
+    Ok(HttpResponse::Ok().json(SimilarImageGroupList {
+        similar_image_group_list,
+        total_count,
+    }))
+}
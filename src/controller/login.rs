@@ -1,69 +1,352 @@
 use actix_session::Session;
-use actix_web::{post, web, Error as AWError, HttpResponse};
-use log::{error, info};
+use actix_web::{
+    get, http::header, middleware::from_fn, post, web, Error as AWError, HttpResponse,
+};
+use chrono::Local;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    controller::user::SESSION_KEY_USERNAME,
-    model::login::{FakeCaptcha, FakeCaptchaParams, LoginParams, LoginResult, PasswordParams},
+    controller::{
+        audit::{record_audit_log, AuditContext, AuditTarget},
+        totp::verify_second_factor,
+        user::{require_admin, SessionExt},
+    },
+    database::{audit_log::AuditAction, sqlite::PoolDatabaseManager, user_info::UserInfo},
+    model::{
+        common::ErrorCode,
+        login::{
+            LockedLoginList, LoginParams, LoginResult, LoginTotpParams, PasswordParams,
+            SharedLoginLimiter,
+        },
+    },
+    utils::{
+        error::DfrError,
+        password::{hash_password, verify_password},
+    },
     SharedSettings,
 };
 
+/// Verify password against the password hash in a blocking thread, as Argon2id is expensive
+pub async fn check_password(password: String, password_hash: String) -> Result<bool, DfrError> {
+    let matched = tokio::task::spawn_blocking(move || {
+        verify_password(password.as_str(), password_hash.as_str())
+    })
+    .await
+    .map_err(std::io::Error::from)?;
+    Ok(matched)
+}
+
+/// Argon2id hash with default parameters, unknown users and users without local password are
+/// verified against it, so that the response time does not tell which user names exist
+const DUMMY_PASSWORD_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$lq28hbLZ9UnqiQfnte93uQ$/FY2Zr6QQSp2uc1CFZhvvwl4nLOMn3UiIovBKs87aeY";
+
+/// Hash new password in a blocking thread, as Argon2id is expensive
+pub async fn new_password_hash(password: String) -> Result<String, DfrError> {
+    tokio::task::spawn_blocking(move || hash_password(password.as_str()))
+        .await
+        .map_err(std::io::Error::from)?
+}
+
+/// Session key of a login whose password is verified, waiting for the second factor
+const SESSION_KEY_PENDING_LOGIN: &str = "pending_login";
+/// Seconds to enter the second factor after the password is verified
+const PENDING_LOGIN_TIMEOUT_S: i64 = 300;
+
+/// Login whose password is verified, waiting for the second factor
+#[derive(Serialize, Deserialize)]
+struct PendingLogin {
+    user_name: String,
+    remember_me: bool,
+    login_type: String,
+    time: i64,
+}
+
 #[utoipa::path(
     summary = "Login user account",
     request_body(content = LoginParams),
     responses(
-        (status = 200, description = "Login result", body=LoginResult),
+        (status = 200, description = "Login result, status is totp if the second factor is required", body=LoginResult),
         (status = 403, description = "Illegal username or password"),
+        (status = 429, description = "Too many failed login attempts"),
     ),
 )]
 #[post("/api/login/account")]
 pub async fn login_account(
     requst_json: web::Json<LoginParams>,
+    db: web::Data<PoolDatabaseManager>,
     settings: web::Data<SharedSettings>,
+    login_limiter: web::Data<SharedLoginLimiter>,
     session: Session,
+    mut audit_context: AuditContext,
 ) -> Result<HttpResponse, AWError> {
     let params = requst_json.into_inner();
+    if !settings.lock().await.system.proxy_auth_header.is_empty() {
+        warn!(
+            "Login of user {} is rejected, login is managed by reverse proxy",
+            params.username
+        );
+        return Ok(HttpResponse::Forbidden().body("Login is managed by the reverse proxy"));
+    }
+    if settings.lock().await.oidc.disable_password_login {
+        warn!(
+            "Login of user {} is rejected, login is managed by identity provider",
+            params.username
+        );
+        return Ok(HttpResponse::Forbidden().body("Login is managed by the identity provider"));
+    }
+    // the session may still belong to another user
+    audit_context.user_name = None;
+    if let Some(response) =
+        check_login_limit(&login_limiter, &audit_context, params.username.as_str()).await
     {
-        let settings = settings.lock().await;
-        if settings.user.login_user_name != params.username {
-            error!("Username does not match");
-            return Ok(HttpResponse::Forbidden().body("Illegal username or password"));
-        }
-        if settings.user.login_password != params.password {
+        return Ok(response);
+    }
+
+    let user_info = db.get_user_by_name(params.username.as_str())?;
+    let password_hash = user_info
+        .as_ref()
+        .map(|user_info| user_info.password_hash.clone())
+        .filter(|password_hash| !password_hash.is_empty())
+        .unwrap_or_else(|| DUMMY_PASSWORD_HASH.to_string());
+    let matched = check_password(params.password.clone(), password_hash).await?;
+    let user_info = match user_info {
+        Some(user_info) if matched && !user_info.password_hash.is_empty() => user_info,
+        Some(_) => {
             error!("Password does not match");
-            return Ok(HttpResponse::Forbidden().body("Illegal username or password"));
+            return reject_login(
+                &db,
+                &settings,
+                &login_limiter,
+                &audit_context,
+                params.username.as_str(),
+            )
+            .await;
         }
+        None => {
+            error!("User does not exist");
+            return reject_login(
+                &db,
+                &settings,
+                &login_limiter,
+                &audit_context,
+                params.username.as_str(),
+            )
+            .await;
+        }
+    };
+
+    if db.get_user_totp(user_info.user_name.as_str())?.is_some() {
+        session.clear();
+        session.renew();
+        session.insert(
+            SESSION_KEY_PENDING_LOGIN,
+            PendingLogin {
+                user_name: user_info.user_name.clone(),
+                remember_me: params.auto_login,
+                login_type: params.login_type.clone(),
+                time: Local::now().timestamp(),
+            },
+        )?;
+        info!(
+            "Password of user {} is verified, waiting for TOTP code",
+            params.username
+        );
+        return Ok(HttpResponse::Ok().json(LoginResult {
+            status: String::from("totp"),
+            login_type: params.login_type,
+            current_authority: String::from("guest"),
+        }));
     }
-    let result = LoginResult {
+
+    // Store user information in session, auto login maps to a remembered session
+    session.login(user_info.user_name.as_str(), params.auto_login, false)?;
+    complete_login(&db, &login_limiter, audit_context, &user_info).await;
+    info!(
+        "Login successful, username: {}, remember me: {}",
+        params.username, params.auto_login
+    );
+    Ok(HttpResponse::Ok().json(LoginResult {
         status: String::from("ok"),
         login_type: params.login_type,
-        current_authority: String::from("admin"),
-    };
-    session
-        .insert(SESSION_KEY_USERNAME, &params.username)
-        .unwrap(); // Store user information in session
-    info!("Login successful, username: {}", params.username);
-    Ok(HttpResponse::Ok().json(result))
+        current_authority: user_info.role.to_string(),
+    }))
 }
 
 #[utoipa::path(
-    summary = "Get captcha for login",
-    request_body(content = FakeCaptchaParams),
+    summary = "Verify the second factor of a login",
+    request_body(content = LoginTotpParams),
     responses(
-        (status = 200, description = "Get captcha successfully", body=FakeCaptcha),
-        (status = 400, description = "Bad request"),
-        (status = 501, description = "Not implemented"),
+        (status = 200, description = "Login result", body=LoginResult),
+        (status = 401, description = "No login is waiting for the second factor"),
+        (status = 403, description = "Illegal code"),
+        (status = 429, description = "Too many failed login attempts"),
     ),
 )]
-#[post("/api/login/captcha")]
-pub async fn get_captcha(
-    requst_json: web::Json<FakeCaptchaParams>,
+#[post("/api/login/totp")]
+pub async fn login_totp(
+    requst_json: web::Json<LoginTotpParams>,
+    db: web::Data<PoolDatabaseManager>,
+    settings: web::Data<SharedSettings>,
+    login_limiter: web::Data<SharedLoginLimiter>,
+    session: Session,
+    mut audit_context: AuditContext,
 ) -> Result<HttpResponse, AWError> {
     let params = requst_json.into_inner();
-    if params.phone.is_none() {
-        return Ok(HttpResponse::BadRequest().body("Phone is null"));
+    audit_context.user_name = None;
+    let now = Local::now().timestamp();
+    let pending_login = session
+        .get::<PendingLogin>(SESSION_KEY_PENDING_LOGIN)?
+        .filter(|pending_login| now - pending_login.time <= PENDING_LOGIN_TIMEOUT_S);
+    let Some(pending_login) = pending_login else {
+        warn!("No login is waiting for TOTP code");
+        return Ok(HttpResponse::Unauthorized().body("Login is expired, please login again"));
+    };
+    if let Some(response) = check_login_limit(
+        &login_limiter,
+        &audit_context,
+        pending_login.user_name.as_str(),
+    )
+    .await
+    {
+        return Ok(response);
     }
-    return Ok(HttpResponse::NotImplemented().body("Not implemented"));
+
+    let user_info = match db.get_user_by_name(pending_login.user_name.as_str())? {
+        Some(user_info) if verify_second_factor(&db, &user_info.user_name, &params.code)? => {
+            user_info
+        }
+        _ => {
+            error!("TOTP code does not match");
+            return reject_login(
+                &db,
+                &settings,
+                &login_limiter,
+                &audit_context,
+                pending_login.user_name.as_str(),
+            )
+            .await;
+        }
+    };
+
+    session.login(
+        user_info.user_name.as_str(),
+        pending_login.remember_me,
+        true,
+    )?;
+    complete_login(&db, &login_limiter, audit_context, &user_info).await;
+    info!(
+        "Login with TOTP successful, username: {}, remember me: {}",
+        user_info.user_name, pending_login.remember_me
+    );
+    Ok(HttpResponse::Ok().json(LoginResult {
+        status: String::from("ok"),
+        login_type: pending_login.login_type,
+        current_authority: user_info.role.to_string(),
+    }))
+}
+
+/// Reject the login attempt if the client ip or user is locked out after failed attempts
+async fn check_login_limit(
+    login_limiter: &SharedLoginLimiter,
+    audit_context: &AuditContext,
+    user_name: &str,
+) -> Option<HttpResponse> {
+    let client_ip = audit_context.client_ip.as_deref().unwrap_or_default();
+    let now = Local::now();
+    let retry_after = login_limiter
+        .lock()
+        .await
+        .retry_after(client_ip, user_name, &now)?;
+    let retry_after_s = retry_after.num_seconds() + 1;
+    warn!(
+        "Login of user {} from {} is rejected for {} seconds",
+        user_name, client_ip, retry_after_s
+    );
+    Some(
+        HttpResponse::TooManyRequests()
+            .insert_header((header::RETRY_AFTER, retry_after_s.to_string()))
+            .body("Too many failed login attempts, please retry later"),
+    )
+}
+
+/// Forget failed attempts and record the login in audit log
+pub async fn complete_login(
+    db: &PoolDatabaseManager,
+    login_limiter: &SharedLoginLimiter,
+    mut audit_context: AuditContext,
+    user_info: &UserInfo,
+) {
+    login_limiter.lock().await.record_success(
+        audit_context.client_ip.as_deref().unwrap_or_default(),
+        user_info.user_name.as_str(),
+    );
+    audit_context.user_name = Some(user_info.user_name.clone());
+    record_audit_log(
+        db,
+        &audit_context,
+        AuditAction::Login,
+        AuditTarget::name(user_info.user_name.as_str()),
+        &Ok::<(), DfrError>(()),
+    );
+}
+
+/// Record a failed login attempt for rate limiting and in audit log
+async fn reject_login(
+    db: &PoolDatabaseManager,
+    settings: &SharedSettings,
+    login_limiter: &SharedLoginLimiter,
+    audit_context: &AuditContext,
+    user_name: &str,
+) -> Result<HttpResponse, AWError> {
+    let system_settings = settings.lock().await.system.clone();
+    let locked = login_limiter.lock().await.record_failure(
+        audit_context.client_ip.as_deref().unwrap_or_default(),
+        user_name,
+        &Local::now(),
+        &system_settings,
+    );
+    let message = if locked {
+        warn!(
+            "Too many failed login attempts of user {} from {:?}, login is locked for {} seconds",
+            user_name, audit_context.client_ip, system_settings.login_lockout_s
+        );
+        format!(
+            "Illegal username or password, login is locked for {} seconds",
+            system_settings.login_lockout_s
+        )
+    } else {
+        String::from("Illegal username or password")
+    };
+    let result: Result<(), DfrError> = DfrError::custom_error(ErrorCode::LOGIN_FAILED, message);
+    record_audit_log(
+        db,
+        audit_context,
+        AuditAction::Login,
+        AuditTarget::name(user_name),
+        &result,
+    );
+    Ok(HttpResponse::Forbidden().body("Illegal username or password"))
+}
+
+#[utoipa::path(
+    summary = "List client ips and users locked out after too many failed login attempts",
+    responses(
+        (status = 200, description = "The list of locked logins", body = LockedLoginList)
+    ),
+)]
+#[get("/login/locked", wrap = "from_fn(require_admin)")]
+pub async fn list_locked_logins(
+    settings: web::Data<SharedSettings>,
+    login_limiter: web::Data<SharedLoginLimiter>,
+) -> Result<HttpResponse, AWError> {
+    let system_settings = settings.lock().await.system.clone();
+    let locked_login_list = login_limiter
+        .lock()
+        .await
+        .list_locked(&Local::now(), &system_settings);
+    Ok(HttpResponse::Ok().json(locked_login_list))
 }
 
 #[utoipa::path(
@@ -74,7 +357,7 @@ pub async fn get_captcha(
 )]
 #[post("/api/login/outLogin")]
 pub async fn logout_account(session: Session) -> Result<HttpResponse, AWError> {
-    session.remove(SESSION_KEY_USERNAME);
+    session.purge();
     info!("Logout successful");
     Ok(HttpResponse::Ok().finish())
 }
@@ -91,10 +374,12 @@ pub async fn logout_account(session: Session) -> Result<HttpResponse, AWError> {
 pub async fn change_password(
     requst_json: web::Json<PasswordParams>,
     settings: web::Data<SharedSettings>,
+    db: web::Data<PoolDatabaseManager>,
     session: Session,
+    current_user: UserInfo,
+    audit_context: AuditContext,
 ) -> Result<HttpResponse, AWError> {
     let params = requst_json.into_inner();
-    let mut settings = settings.lock().await;
     if params.password.is_empty() || params.username.is_empty() {
         error!("Username or password is empty");
         return Ok(HttpResponse::Forbidden().body("Illegal username or password"));
@@ -104,40 +389,65 @@ pub async fn change_password(
         return Ok(HttpResponse::Forbidden().body("Illegal new username or new password"));
     }
 
-    if settings.user.login_user_name != params.username {
+    if current_user.user_name != params.username {
         error!("Username does not match");
         return Ok(HttpResponse::Forbidden().body("Illegal username or password"));
     }
-    if settings.user.login_password != params.password {
+    if !check_password(params.password, current_user.password_hash.clone()).await? {
         error!("Password does not match");
         return Ok(HttpResponse::Forbidden().body("Illegal username or password"));
     }
 
+    let mut user_info = current_user.clone();
     if let Some(new_username) = params.new_username {
-        if !new_username.is_empty() {
+        if !new_username.is_empty() && new_username != user_info.user_name {
+            if db.get_user_by_name(new_username.as_str())?.is_some() {
+                error!("User {} already exists", new_username);
+                return Ok(HttpResponse::Forbidden().body("Illegal new username or new password"));
+            }
             info!(
                 "Change username from {} to {}",
-                settings.user.login_user_name, new_username
+                user_info.user_name, new_username
             );
-            settings.user.login_user_name = new_username;
+            user_info.user_name = new_username;
         }
     }
 
     if let Some(new_password) = params.new_password {
         if !new_password.is_empty() {
-            info!(
-                "Change password from {} to {}",
-                settings.user.login_password, new_password
-            );
-            settings.user.login_password = new_password;
+            info!("Change password of {}", user_info.user_name);
+            user_info.password_hash = new_password_hash(new_password).await?;
         }
     }
 
-    // save new settings to file
-    settings.save()?;
+    user_info.modified_time = Local::now();
+    let result = db.update_user(current_user.user_name.as_str(), &user_info);
+    record_audit_log(
+        db.get_ref(),
+        &audit_context,
+        AuditAction::ChangePassword,
+        AuditTarget::default(),
+        &result,
+    );
+    result?;
     info!("Username / password changed successfully");
 
+    // the random password generated on first start is not valid any more
+    let initial_password_file_path = {
+        let settings = settings.lock().await;
+        settings.initial_password_file_path()
+    };
+    if initial_password_file_path.exists() {
+        if let Err(e) = std::fs::remove_file(&initial_password_file_path) {
+            warn!(
+                "Failed to remove initial password file {}: {}",
+                initial_password_file_path.display(),
+                e
+            );
+        }
+    }
+
     // logout
-    session.remove(SESSION_KEY_USERNAME);
+    session.purge();
     Ok(HttpResponse::Ok().finish())
 }
@@ -0,0 +1,262 @@
+use actix_session::Session;
+use actix_web::{get, http::header, web, HttpResponse};
+use chrono::Local;
+use log::{info, warn};
+use openidconnect::{
+    core::{CoreAuthenticationFlow, CoreClient, CoreProviderMetadata},
+    reqwest, AuthorizationCode, ClientId, ClientSecret, CsrfToken, EndpointMaybeSet,
+    EndpointNotSet, EndpointSet, IssuerUrl, Nonce, PkceCodeChallenge, PkceCodeVerifier,
+    RedirectUrl, Scope, TokenResponse,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controller::{
+        audit::{record_audit_log, AuditContext, AuditTarget},
+        login::complete_login,
+        user::{provision_user, sync_user_role, SessionExt},
+    },
+    database::{audit_log::AuditAction, sqlite::PoolDatabaseManager, user_info::UserInfo},
+    model::{
+        common::ErrorCode,
+        login::{OidcCallbackParams, OidcLoginParams, SharedLoginLimiter},
+        settings::OidcSettings,
+    },
+    utils::{
+        error::DfrError,
+        oidc::{decode_jwt_claims, map_oidc_claims},
+    },
+    SharedSettings,
+};
+
+/// Session key of a login started at the identity provider, until it redirects back
+const SESSION_KEY_PENDING_OIDC_LOGIN: &str = "pending_oidc_login";
+/// Seconds to authenticate at the identity provider
+const PENDING_OIDC_LOGIN_TIMEOUT_S: i64 = 600;
+
+/// Client with the endpoints of the provider metadata
+type OidcClient = CoreClient<
+    EndpointSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointMaybeSet,
+    EndpointMaybeSet,
+>;
+
+/// Login started at the identity provider, with the values to verify the callback
+#[derive(Serialize, Deserialize)]
+struct PendingOidcLogin {
+    state: String,
+    nonce: String,
+    pkce_verifier: String,
+    remember_me: bool,
+    time: i64,
+}
+
+fn oidc_error<T>(message: impl ToString) -> Result<T, DfrError> {
+    DfrError::custom_error(ErrorCode::OIDC_LOGIN_FAILED, message.to_string())
+}
+
+/// Discover the endpoints and keys of the identity provider
+async fn discover_client(
+    settings: &OidcSettings,
+) -> Result<(OidcClient, reqwest::Client), DfrError> {
+    if settings.issuer_url.is_empty() {
+        return oidc_error("OpenID Connect login is not enabled");
+    }
+    let http_client = reqwest::ClientBuilder::new()
+        // following redirects opens the client to SSRF vulnerabilities
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .or_else(oidc_error)?;
+    let issuer_url = IssuerUrl::new(settings.issuer_url.clone()).or_else(oidc_error)?;
+    let provider_metadata = CoreProviderMetadata::discover_async(issuer_url, &http_client)
+        .await
+        .or_else(oidc_error)?;
+    let client_secret = if settings.client_secret.is_empty() {
+        None
+    } else {
+        Some(ClientSecret::new(settings.client_secret.clone()))
+    };
+    let client = CoreClient::from_provider_metadata(
+        provider_metadata,
+        ClientId::new(settings.client_id.clone()),
+        client_secret,
+    )
+    .set_redirect_uri(RedirectUrl::new(settings.redirect_url.clone()).or_else(oidc_error)?);
+    Ok((client, http_client))
+}
+
+#[utoipa::path(
+    summary = "Start login with the OpenID Connect identity provider",
+    params(OidcLoginParams),
+    responses(
+        (status = 302, description = "Redirect to the identity provider"),
+    ),
+)]
+#[get("/api/login/oidc")]
+pub async fn login_oidc(
+    query: web::Query<OidcLoginParams>,
+    settings: web::Data<SharedSettings>,
+    session: Session,
+) -> Result<HttpResponse, DfrError> {
+    let oidc_settings = settings.lock().await.oidc.clone();
+    let (client, _) = discover_client(&oidc_settings).await?;
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let mut request = client
+        .authorize_url(
+            CoreAuthenticationFlow::AuthorizationCode,
+            CsrfToken::new_random,
+            Nonce::new_random,
+        )
+        .set_pkce_challenge(pkce_challenge);
+    for scope in oidc_settings.scopes {
+        request = request.add_scope(Scope::new(scope));
+    }
+    let (authorize_url, state, nonce) = request.url();
+
+    session.clear();
+    session.renew();
+    session
+        .insert(
+            SESSION_KEY_PENDING_OIDC_LOGIN,
+            PendingOidcLogin {
+                state: state.secret().clone(),
+                nonce: nonce.secret().clone(),
+                pkce_verifier: pkce_verifier.secret().clone(),
+                remember_me: query.auto_login.unwrap_or(false),
+                time: Local::now().timestamp(),
+            },
+        )
+        .or_else(|e| DfrError::custom_error(ErrorCode::SYSTEM_ERROR, e.to_string()))?;
+    info!(
+        "Redirect login to identity provider {}",
+        oidc_settings.issuer_url
+    );
+    Ok(HttpResponse::Found()
+        .insert_header((header::LOCATION, authorize_url.to_string()))
+        .finish())
+}
+
+#[utoipa::path(
+    summary = "Complete login with the OpenID Connect identity provider",
+    params(OidcCallbackParams),
+    responses(
+        (status = 302, description = "Logged in, redirect to the home page"),
+    ),
+)]
+#[get("/api/login/oidc/callback")]
+pub async fn login_oidc_callback(
+    query: web::Query<OidcCallbackParams>,
+    db: web::Data<PoolDatabaseManager>,
+    settings: web::Data<SharedSettings>,
+    login_limiter: web::Data<SharedLoginLimiter>,
+    session: Session,
+    mut audit_context: AuditContext,
+) -> Result<HttpResponse, DfrError> {
+    audit_context.user_name = None;
+    let result = _login_oidc_callback(query.into_inner(), &db, &settings, &session).await;
+    let user_info = match result {
+        Ok(user_info) => user_info,
+        Err(e) => {
+            warn!("OpenID Connect login failed: {}", e);
+            let result = Err(e);
+            record_audit_log(
+                &db,
+                &audit_context,
+                AuditAction::Login,
+                AuditTarget::default(),
+                &result,
+            );
+            return result;
+        }
+    };
+    complete_login(&db, &login_limiter, audit_context, &user_info).await;
+    info!(
+        "Login with identity provider successful, username: {}",
+        user_info.user_name
+    );
+    Ok(HttpResponse::Found()
+        .insert_header((header::LOCATION, "/"))
+        .finish())
+}
+
+async fn _login_oidc_callback(
+    params: OidcCallbackParams,
+    db: &PoolDatabaseManager,
+    settings: &SharedSettings,
+    session: &Session,
+) -> Result<UserInfo, DfrError> {
+    let now = Local::now().timestamp();
+    let pending_login = session
+        .get::<PendingOidcLogin>(SESSION_KEY_PENDING_OIDC_LOGIN)
+        .ok()
+        .flatten()
+        .filter(|pending_login| now - pending_login.time <= PENDING_OIDC_LOGIN_TIMEOUT_S);
+    // the state, nonce and verifier are valid for one callback only
+    session.remove(SESSION_KEY_PENDING_OIDC_LOGIN);
+    let Some(pending_login) = pending_login else {
+        return oidc_error("Login is expired, please login again");
+    };
+    if let Some(error) = params.error {
+        return oidc_error(format!(
+            "Identity provider rejected login: {} {}",
+            error,
+            params.error_description.unwrap_or_default()
+        ));
+    }
+    if params.state.as_deref() != Some(pending_login.state.as_str()) {
+        return oidc_error("State of the callback does not match");
+    }
+    let Some(code) = params.code else {
+        return oidc_error("Authorization code is missing");
+    };
+
+    let oidc_settings = settings.lock().await.oidc.clone();
+    let (client, http_client) = discover_client(&oidc_settings).await?;
+    let token_response = client
+        .exchange_code(AuthorizationCode::new(code))
+        .or_else(oidc_error)?
+        .set_pkce_verifier(PkceCodeVerifier::new(pending_login.pkce_verifier))
+        .request_async(&http_client)
+        .await
+        .or_else(oidc_error)?;
+    let Some(id_token) = token_response.id_token() else {
+        return oidc_error("ID token is missing in token response");
+    };
+    // verify signature, issuer, audience, expiration and nonce
+    id_token
+        .claims(
+            &client.id_token_verifier(),
+            &Nonce::new(pending_login.nonce),
+        )
+        .or_else(oidc_error)?;
+    let claims = decode_jwt_claims(id_token.to_string().as_str())?;
+    let oidc_user = map_oidc_claims(&claims, &oidc_settings)?;
+
+    let mut user_info = provision_user(
+        db,
+        oidc_user.user_name.as_str(),
+        oidc_user.role.unwrap_or(oidc_settings.default_role),
+        "identity provider",
+    )?;
+    // the identity provider controls the user name claim, it must not take over local accounts
+    if !user_info.password_hash.is_empty() {
+        return oidc_error(format!(
+            "User {} has a local password and can not login with identity provider",
+            user_info.user_name
+        ));
+    }
+    if let Some(role) = oidc_user.role {
+        sync_user_role(db, &mut user_info, role)?;
+    }
+    session
+        .login(
+            user_info.user_name.as_str(),
+            pending_login.remember_me,
+            oidc_user.mfa,
+        )
+        .or_else(|e| DfrError::custom_error(ErrorCode::SYSTEM_ERROR, e.to_string()))?;
+    Ok(user_info)
+}
@@ -0,0 +1,136 @@
+use std::{collections::HashMap, path::Path};
+
+use actix_web::{get, middleware::from_fn, web, HttpResponse};
+use chrono::Local;
+use log::info;
+
+use crate::{
+    controller::{export::DuplicateExporter, user::require_read},
+    database::{sqlite::PoolDatabaseManager, user_info::UserInfo},
+    model::{
+        export::{DuplicateGroup, ExportFormat},
+        report::{
+            DuplicateReport, ReportBucket, ReportParams, ReportSummary, DEFAULT_REPORT_TOP_COUNT,
+        },
+        settings::ListSettings,
+    },
+    utils::{error::DfrError, report::render_html_report},
+};
+
+/// Name of the bucket of files without extension
+const NO_EXTENSION: &str = "(none)";
+
+/// Groups kept besides the top groups before sorting, so that they are not sorted for each group
+const EXTRA_GROUP_COUNT: usize = 100;
+
+/// Biggest buckets first, ties are broken by the name so that reports are stable
+fn top_buckets(buckets: HashMap<String, ReportBucket>, top_count: usize) -> Vec<ReportBucket> {
+    let mut buckets = buckets.into_values().collect::<Vec<_>>();
+    buckets.sort_by(|a, b| {
+        b.duplicate_size
+            .cmp(&a.duplicate_size)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    buckets.truncate(top_count);
+    buckets
+}
+
+fn sort_top_groups(groups: &mut Vec<DuplicateGroup>, top_count: usize) {
+    groups.sort_by(|a, b| {
+        b.reclaimable_size
+            .cmp(&a.reclaimable_size)
+            .then_with(|| a.md5.cmp(&b.md5))
+    });
+    groups.truncate(top_count);
+}
+
+/// Build the report of the duplicate files matching the list filters, page parameters are
+/// ignored
+pub fn build_duplicate_report(
+    db: &PoolDatabaseManager,
+    query_list_params: ListSettings,
+    allowed_paths: Vec<String>,
+    top_count: usize,
+) -> Result<DuplicateReport, DfrError> {
+    let mut reader = DuplicateExporter::new(
+        db.clone(),
+        query_list_params,
+        allowed_paths,
+        ExportFormat::default(),
+    );
+    let mut summary = ReportSummary::default();
+    let mut top_groups = Vec::new();
+    let mut directories: HashMap<String, ReportBucket> = HashMap::new();
+    let mut extensions: HashMap<String, ReportBucket> = HashMap::new();
+    while let Some(groups) = reader.next_groups()? {
+        for group in groups {
+            summary.group_count += 1;
+            summary.file_count += group.files.len();
+            summary.duplicate_size += group.size * group.files.len() as u64;
+            summary.reclaimable_size += group.reclaimable_size;
+            for file in group.files.iter() {
+                let extension = Path::new(file.file_name.as_str())
+                    .extension()
+                    .map(|extension| extension.to_string_lossy().to_lowercase())
+                    .unwrap_or_else(|| String::from(NO_EXTENSION));
+                for (buckets, name) in [
+                    (&mut directories, file.dir_path.clone()),
+                    (&mut extensions, extension),
+                ] {
+                    let bucket = buckets.entry(name.clone()).or_insert_with(|| ReportBucket {
+                        name,
+                        ..Default::default()
+                    });
+                    bucket.file_count += 1;
+                    bucket.duplicate_size += group.size;
+                }
+            }
+            top_groups.push(group);
+            // only keep the biggest groups read so far
+            if top_groups.len() >= top_count * 2 + EXTRA_GROUP_COUNT {
+                sort_top_groups(&mut top_groups, top_count);
+            }
+        }
+    }
+    sort_top_groups(&mut top_groups, top_count);
+    Ok(DuplicateReport {
+        generated_time: Local::now(),
+        summary,
+        top_groups,
+        directories: top_buckets(directories, top_count),
+        extensions: top_buckets(extensions, top_count),
+    })
+}
+
+#[utoipa::path(
+    summary = "Download the report of duplicate files as a single html file, page parameters are ignored",
+    params(ListSettings, ReportParams),
+    responses(
+        (status = 200, description = "Duplicate report in html format", content_type = "text/html")
+    ),
+)]
+#[get("/list/report", wrap = "from_fn(require_read)")]
+pub async fn download_duplicate_report(
+    query_list: web::Query<ListSettings>,
+    report_params: web::Query<ReportParams>,
+    db: web::Data<PoolDatabaseManager>,
+    current_user: UserInfo,
+) -> Result<HttpResponse, DfrError> {
+    let report = build_duplicate_report(
+        &db,
+        query_list.into_inner(),
+        current_user.allowed_paths,
+        report_params.top_count.unwrap_or(DEFAULT_REPORT_TOP_COUNT),
+    )?;
+    info!(
+        "Generate duplicate report of {} groups, user: {}",
+        report.summary.group_count, current_user.user_name
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .insert_header((
+            "Content-Disposition",
+            "attachment; filename=\"duplicate_report.html\"",
+        ))
+        .body(render_html_report(&report)))
+}
@@ -3,14 +3,21 @@ use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::controller::user::{require_read, require_scan};
 use crate::database::file_info::FileInfo;
+use crate::database::scan_history::ScanHistory;
 use crate::database::sqlite::PoolDatabaseManager;
+use crate::database::user_info::UserInfo;
 use crate::model::common::{ErrorCode, RestResponse};
 use crate::model::scan::{ScanStatus, SharedScanStatus};
 use crate::model::settings::ScanSettings;
+use crate::utils::archive::{is_archive_file, read_archive_members};
+use crate::utils::audio_fingerprint::{compute_audio_fingerprint, is_audio_file};
 use crate::utils::error::DfrError;
+use crate::utils::image_hash::{compute_image_hash, is_image_file};
+use crate::utils::payload_hash::{is_payload_hash_file, payload_md5};
 use crate::SharedSettings;
-use actix_web::{get, post, web, Error as AWError, HttpResponse};
+use actix_web::{get, middleware::from_fn, post, web, Error as AWError, HttpResponse};
 use chrono::{DateTime, Local};
 use log::{debug, error, info, warn};
 
@@ -23,7 +30,7 @@ static SCAN_FLAG: AtomicBool = AtomicBool::new(false);
         (status = 200, description = "Scan status", body = RestResponse<ScanStatus>),
     ),
 )]
-#[get("/scan/status")]
+#[get("/scan/status", wrap = "from_fn(require_read)")]
 pub async fn query_scan_status(
     scan_status: web::Data<SharedScanStatus>,
 ) -> Result<HttpResponse, AWError> {
@@ -38,7 +45,7 @@ pub async fn query_scan_status(
         (status = 200, description = "Scan settings", body = RestResponse<ScanSettings>),
     ),
 )]
-#[get("/scan/settings")]
+#[get("/scan/settings", wrap = "from_fn(require_read)")]
 pub async fn query_scan_settings(
     settings: web::Data<SharedSettings>,
 ) -> Result<HttpResponse, AWError> {
@@ -55,12 +62,13 @@ pub async fn query_scan_settings(
         (status  = 409, description = "Scan already in progress"),
     ),
 )]
-#[post("/scan/start")]
+#[post("/scan/start", wrap = "from_fn(require_scan)")]
 pub async fn start_scan(
     requst_json: web::Json<ScanSettings>,
     db: web::Data<PoolDatabaseManager>,
     scan_status: web::Data<SharedScanStatus>,
     settings: web::Data<SharedSettings>,
+    current_user: UserInfo,
 ) -> Result<HttpResponse, AWError> {
     let is_scan_started = SCAN_FLAG.load(Ordering::Acquire);
     if is_scan_started {
@@ -89,6 +97,17 @@ pub async fn start_scan(
             format!("Scan path '{}' does not exist", &scan_request.scan_path),
         )));
     }
+    // check the real path, as the scan path may be a symlink to a path which is not allowed
+    if !current_user.is_path_allowed(std::fs::canonicalize(path)?.as_path()) {
+        warn!(
+            "User {} is not allowed to scan {:?}",
+            current_user.user_name, path
+        );
+        return Ok(HttpResponse::Ok().json(RestResponse::failed(
+            ErrorCode::PERMISSION_DENIED,
+            format!("Scan path '{}' is not allowed", &scan_request.scan_path),
+        )));
+    }
     {
         let mut settings = settings.lock().await;
         settings.scan = scan_request.clone();
@@ -124,7 +143,7 @@ pub async fn start_scan(
 
 /// Stop the current file scan.
 #[utoipa::path(summary = "Stop the current file scan")]
-#[post("/scan/stop")]
+#[post("/scan/stop", wrap = "from_fn(require_scan)")]
 pub async fn stop_scan(scan_status: web::Data<SharedScanStatus>) -> Result<HttpResponse, AWError> {
     info!("Stopping scan");
     STOP_SCAN_FLAG.store(true, Ordering::Relaxed);
@@ -177,10 +196,20 @@ pub async fn scan_all_files(
     )
     .await;
     db.remove_deleted_inodes()?;
-    {
+    let scanned_file_count = {
         let mut status = scan_status.lock().await;
         status.started = false;
-    }
+        status.scanned_file_count
+    };
+    // a new scan history invalidates the cached file statistics
+    db.insert_scan_history(&ScanHistory {
+        version: scan_version,
+        scan_path: current_path.to_string_lossy().to_string(),
+        start_time: DateTime::<Local>::from(start),
+        end_time: Local::now(),
+        scanned_file_count: scanned_file_count as u64,
+        success: result.is_ok(),
+    })?;
 
     return result;
 }
@@ -246,7 +275,7 @@ async fn _scan_all_files(
         )?;
     }
     //remove deleted/filterd files from db
-    db.remove_deleted_files_by_version(scan_version)?;
+    db.remove_deleted_files_by_version(current_path.to_string_lossy().as_ref(), scan_version)?;
     Ok(())
 }
 
@@ -304,6 +333,8 @@ async fn scan_file(
                     file_info.file_path, db_file_info.version, file_info.version
                 );
                 db.update_version(&file_info)?;
+                analyse_media_file(scan_request, &db_file_info, db).await?;
+                update_archive_members(scan_request, &file_info, false, db).await?;
                 return Ok(());
             } else {
                 info!("File '{}' is changed, need to update, db inode info: {:?}, current inode info: {:?}", file_info.file_path, db_file_info.inode_info , file_info.inode_info);
@@ -342,5 +373,149 @@ async fn scan_file(
         "Insert file info '{}' to database succeed",
         file_info.file_path
     );
+    analyse_media_file(scan_request, &file_info, db).await?;
+    update_archive_members(scan_request, &file_info, true, db).await?;
+    Ok(())
+}
+
+/// Index the members of an archive file as virtual files if enabled. Members of an unchanged
+/// archive are kept with the new version, they are only read again if the archive is changed.
+/// Archives which can not be read are skipped, their members are removed with the old version.
+async fn update_archive_members(
+    scan_request: &ScanSettings,
+    archive: &FileInfo,
+    changed: bool,
+    db: &PoolDatabaseManager,
+) -> Result<(), DfrError> {
+    if !scan_request.archive_members.unwrap_or(false) || !is_archive_file(&archive.file_name) {
+        return Ok(());
+    }
+    if !changed && db.update_archive_member_version(archive)? > 0 {
+        return Ok(());
+    }
+    let file_path = archive.file_path.clone();
+    let result = tokio::task::spawn_blocking(move || read_archive_members(&file_path))
+        .await
+        .map_err(std::io::Error::from)?;
+    match result {
+        Ok(members) => {
+            let members = members
+                .into_iter()
+                .map(|member| {
+                    FileInfo::archive_member(
+                        archive,
+                        &member.path,
+                        member.md5,
+                        member.size,
+                        member.modified,
+                    )
+                })
+                .collect::<Vec<_>>();
+            debug!(
+                "Index {} members of archive '{}'",
+                members.len(),
+                archive.file_path
+            );
+            db.replace_archive_members(&archive.file_path, &members)?;
+        }
+        Err(e) => warn!("Skip members of archive '{}': {}", archive.file_path, e),
+    }
+    Ok(())
+}
+
+/// Run the optional analysis stages of images and audio files
+async fn analyse_media_file(
+    scan_request: &ScanSettings,
+    file_info: &FileInfo,
+    db: &PoolDatabaseManager,
+) -> Result<(), DfrError> {
+    update_payload_md5(scan_request, file_info, db).await?;
+    update_image_hash(scan_request, file_info, db).await?;
+    update_audio_fingerprint(scan_request, file_info, db).await?;
+    Ok(())
+}
+
+/// Compute the payload md5 of a media file if enabled and not computed for its inode yet. Files
+/// which can not be parsed are skipped, they are still grouped by md5.
+async fn update_payload_md5(
+    scan_request: &ScanSettings,
+    file_info: &FileInfo,
+    db: &PoolDatabaseManager,
+) -> Result<(), DfrError> {
+    if !scan_request.payload_hash.unwrap_or(false)
+        || !is_payload_hash_file(&file_info.file_name)
+        || file_info.inode_info.payload_md5.is_some()
+    {
+        return Ok(());
+    }
+    let file_path = file_info.file_path.clone();
+    let result = tokio::task::spawn_blocking(move || payload_md5(&file_path))
+        .await
+        .map_err(std::io::Error::from)?;
+    match result {
+        Ok(Some(payload_md5)) => {
+            let mut inode_info = file_info.inode_info.clone();
+            inode_info.payload_md5 = Some(payload_md5);
+            db.update_payload_md5(&inode_info)?;
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Skip payload md5 of '{}': {}", file_info.file_path, e),
+    }
+    Ok(())
+}
+
+/// Compute perceptual hashes of an image file if enabled and not computed for its md5 yet. Files
+/// which can not be decoded are skipped, they are still compared by md5.
+async fn update_image_hash(
+    scan_request: &ScanSettings,
+    file_info: &FileInfo,
+    db: &PoolDatabaseManager,
+) -> Result<(), DfrError> {
+    if !scan_request.image_hash.unwrap_or(false) || !is_image_file(&file_info.file_name) {
+        return Ok(());
+    }
+    let Some(md5) = file_info.inode_info.md5.clone() else {
+        return Ok(());
+    };
+    if db.has_image_hash(&md5)? {
+        return Ok(());
+    }
+    let file_path = file_info.file_path.clone();
+    let result = tokio::task::spawn_blocking(move || compute_image_hash(&file_path, &md5))
+        .await
+        .map_err(std::io::Error::from)?;
+    match result {
+        Ok(image_hash) => db.insert_image_hash(&image_hash)?,
+        Err(e) => warn!("Skip image hash of '{}': {}", file_info.file_path, e),
+    }
+    Ok(())
+}
+
+/// Compute the fingerprint of an audio file if enabled and not computed for its md5 yet. Files
+/// which can not be decoded are skipped, they are still compared by md5.
+async fn update_audio_fingerprint(
+    scan_request: &ScanSettings,
+    file_info: &FileInfo,
+    db: &PoolDatabaseManager,
+) -> Result<(), DfrError> {
+    if !scan_request.audio_fingerprint.unwrap_or(false) || !is_audio_file(&file_info.file_name) {
+        return Ok(());
+    }
+    let Some(md5) = file_info.inode_info.md5.clone() else {
+        return Ok(());
+    };
+    if db.has_audio_fingerprint(&md5)? {
+        return Ok(());
+    }
+    let file_path = file_info.file_path.clone();
+    let file_size = file_info.inode_info.size;
+    let result =
+        tokio::task::spawn_blocking(move || compute_audio_fingerprint(&file_path, &md5, file_size))
+            .await
+            .map_err(std::io::Error::from)?;
+    match result {
+        Ok(audio_fingerprint) => db.insert_audio_fingerprint(&audio_fingerprint)?,
+        Err(e) => warn!("Skip audio fingerprint of '{}': {}", file_info.file_path, e),
+    }
     Ok(())
 }
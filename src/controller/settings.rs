@@ -1,7 +1,12 @@
-use actix_web::{get, post, web, Error as AWError, HttpResponse};
-use log::info;
+use actix_web::{get, middleware::from_fn, post, web, Error as AWError, HttpResponse};
+use log::{info, warn};
 
 use crate::{
+    controller::{
+        audit::{record_audit_log, AuditContext, AuditTarget},
+        user::{require_admin, Authentication},
+    },
+    database::{audit_log::AuditAction, sqlite::PoolDatabaseManager},
     model::{common::RestResponse, settings::SystemSettings},
     SharedSettings,
 };
@@ -12,7 +17,7 @@ use crate::{
         (status = 200, description = "Query settings successfully", body=RestResponse<SystemSettings>),
     ),
 )]
-#[get("/settings")]
+#[get("/settings", wrap = "from_fn(require_admin)")]
 pub async fn query_settings(settings: web::Data<SharedSettings>) -> Result<HttpResponse, AWError> {
     let settings = settings.lock().await;
     let system_settings = settings.system.clone();
@@ -30,16 +35,41 @@ pub async fn query_settings(settings: web::Data<SharedSettings>) -> Result<HttpR
         (status = 200, description = "Update settings successfully"),
     ),
 )]
-#[post("/settings")]
+#[post("/settings", wrap = "from_fn(require_admin)")]
 pub async fn update_settings(
     requst_json: web::Json<SystemSettings>,
     settings: web::Data<SharedSettings>,
+    db: web::Data<PoolDatabaseManager>,
+    authentication: Authentication,
+    audit_context: AuditContext,
 ) -> Result<HttpResponse, AWError> {
     let params = requst_json.into_inner();
     let mut settings = settings.lock().await;
+    // otherwise an admin without second factor could turn the protection off, or reset it by
+    // omitting the field
+    if params.totp_required_for_delete != settings.system.totp_required_for_delete
+        && !authentication.is_totp_verified()
+    {
+        warn!("Login without two-factor authentication tried to change totp_required_for_delete");
+        return Err(actix_web::error::ErrorForbidden(
+            "Two-factor authentication is required to change totp_required_for_delete.",
+        ));
+    }
+    if let Err(e) = params.validate() {
+        warn!("Invalid settings, {}", e);
+        return Err(actix_web::error::ErrorBadRequest(e.to_string()));
+    }
     settings.system = params;
     // save new settings to file
-    settings.save()?;
+    let result = settings.save();
+    record_audit_log(
+        db.get_ref(),
+        &audit_context,
+        AuditAction::UpdateSettings,
+        AuditTarget::default(),
+        &result,
+    );
+    result?;
     info!("Update system settings successfully, {:?}", settings.system);
     Ok(HttpResponse::Ok().finish())
 }
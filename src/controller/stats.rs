@@ -0,0 +1,22 @@
+use actix_web::{get, middleware::from_fn, web, HttpResponse};
+
+use crate::{
+    controller::user::require_read,
+    database::{file_stats::FileStats, sqlite::PoolDatabaseManager, user_info::UserInfo},
+    utils::error::DfrError,
+};
+
+#[utoipa::path(
+    summary = "Get statistics of indexed and duplicate files, refreshed after each scan",
+    responses(
+        (status = 200, description = "File statistics", body = FileStats)
+    ),
+)]
+#[get("/stats", wrap = "from_fn(require_read)")]
+pub async fn query_file_stats(
+    db: web::Data<PoolDatabaseManager>,
+    current_user: UserInfo,
+) -> Result<HttpResponse, DfrError> {
+    let file_stats = db.get_file_stats(&current_user.allowed_paths)?;
+    Ok(HttpResponse::Ok().json(file_stats))
+}
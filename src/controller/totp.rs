@@ -0,0 +1,238 @@
+use actix_session::Session;
+use actix_web::{delete, get, post, web, HttpResponse};
+use chrono::Local;
+use log::{info, warn};
+
+use crate::{
+    controller::{
+        api_token::check_session,
+        audit::{record_audit_log, AuditContext, AuditTarget},
+        login::check_password,
+        user::{Authentication, SESSION_KEY_TOTP_VERIFIED},
+    },
+    database::{
+        audit_log::AuditAction, sqlite::PoolDatabaseManager, user_info::UserInfo,
+        user_totp::UserTotp,
+    },
+    model::{
+        common::{ErrorCode, RestResponse},
+        totp::{
+            ConfirmTotpRequest, DisableTotpRequest, TotpEnrollResult, TotpRecoveryCodes, TotpStatus,
+        },
+    },
+    utils::{
+        error::DfrError,
+        totp::{
+            generate_recovery_codes, generate_totp_secret, hash_recovery_code,
+            totp_provisioning_uri, totp_qr_code_svg, verify_totp,
+        },
+    },
+};
+
+/// Session key of the secret of a started enrollment, until it is confirmed with a code
+const SESSION_KEY_TOTP_PENDING_SECRET: &str = "totp_pending_secret";
+
+/// Verify a TOTP code or recovery code of the user. A TOTP code is accepted only once, and a
+/// recovery code is removed once used.
+pub fn verify_second_factor(
+    db: &PoolDatabaseManager,
+    user_name: &str,
+    code: &str,
+) -> Result<bool, DfrError> {
+    let Some(user_totp) = db.get_user_totp(user_name)? else {
+        return Ok(false);
+    };
+    if let Some(step) = verify_totp(user_totp.secret.as_str(), code, Local::now().timestamp())? {
+        if db.update_user_totp_last_step(user_name, step)? > 0 {
+            return Ok(true);
+        }
+        warn!("TOTP code of user {} is already used", user_name);
+        return Ok(false);
+    }
+    if db.remove_user_totp_recovery_code(user_name, hash_recovery_code(code).as_str())? > 0 {
+        warn!("Recovery code of user {} is used", user_name);
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+#[utoipa::path(
+    summary = "Query TOTP status of current user",
+    responses(
+        (status = 200, description = "TOTP status", body = RestResponse<TotpStatus>),
+    ),
+)]
+#[get("/totp")]
+pub async fn query_totp_status(
+    db: web::Data<PoolDatabaseManager>,
+    current_user: UserInfo,
+) -> Result<HttpResponse, DfrError> {
+    let user_totp = db.get_user_totp(current_user.user_name.as_str())?;
+    let totp_status = TotpStatus {
+        enabled: user_totp.is_some(),
+        recovery_code_count: user_totp
+            .map(|user_totp| user_totp.recovery_code_hashes.len())
+            .unwrap_or(0),
+    };
+    Ok(HttpResponse::Ok().json(RestResponse::succeed_with_data(totp_status)))
+}
+
+#[utoipa::path(
+    summary = "Start TOTP enrollment of current user",
+    responses(
+        (status = 200, description = "Secret and provisioning uri for authenticator apps", body = RestResponse<TotpEnrollResult>),
+    ),
+)]
+#[post("/totp/enroll")]
+pub async fn enroll_totp(
+    db: web::Data<PoolDatabaseManager>,
+    session: Session,
+    current_user: UserInfo,
+    authentication: Authentication,
+) -> Result<HttpResponse, DfrError> {
+    check_session(&authentication)?;
+    if db.get_user_totp(current_user.user_name.as_str())?.is_some() {
+        return DfrError::custom_error(
+            ErrorCode::TOTP_ALREADY_ENABLED,
+            format!(
+                "TOTP of user {} is already enabled, disable it first",
+                current_user.user_name
+            ),
+        );
+    }
+    let secret = generate_totp_secret();
+    let provisioning_uri = totp_provisioning_uri(secret.as_str(), &current_user.user_name);
+    let qr_code_svg = totp_qr_code_svg(provisioning_uri.as_str())?;
+    session
+        .insert(SESSION_KEY_TOTP_PENDING_SECRET, &secret)
+        .or_else(|e| DfrError::custom_error(ErrorCode::SYSTEM_ERROR, e.to_string()))?;
+    info!("Start TOTP enrollment of user {}", current_user.user_name);
+    Ok(
+        HttpResponse::Ok().json(RestResponse::succeed_with_data(TotpEnrollResult {
+            secret,
+            provisioning_uri,
+            qr_code_svg,
+        })),
+    )
+}
+
+#[utoipa::path(
+    summary = "Confirm TOTP enrollment of current user with a code of the authenticator app",
+    request_body(content = ConfirmTotpRequest),
+    responses(
+        (status = 200, description = "TOTP is enabled, the recovery codes are only returned once", body = RestResponse<TotpRecoveryCodes>),
+    ),
+)]
+#[post("/totp/confirm")]
+pub async fn confirm_totp(
+    requst_json: web::Json<ConfirmTotpRequest>,
+    db: web::Data<PoolDatabaseManager>,
+    session: Session,
+    current_user: UserInfo,
+    authentication: Authentication,
+    audit_context: AuditContext,
+) -> Result<HttpResponse, DfrError> {
+    check_session(&authentication)?;
+    let confirm_totp_request = requst_json.into_inner();
+    let Ok(Some(secret)) = session.get::<String>(SESSION_KEY_TOTP_PENDING_SECRET) else {
+        return DfrError::custom_error(
+            ErrorCode::TOTP_NOT_ENABLED,
+            String::from("TOTP enrollment is not started"),
+        );
+    };
+    let now = Local::now();
+    let Some(step) = verify_totp(
+        secret.as_str(),
+        confirm_totp_request.code.as_str(),
+        now.timestamp(),
+    )?
+    else {
+        return DfrError::custom_error(
+            ErrorCode::TOTP_INVALID_CODE,
+            String::from("Invalid TOTP code"),
+        );
+    };
+
+    let recovery_codes = generate_recovery_codes();
+    let user_totp = UserTotp {
+        user_name: current_user.user_name.clone(),
+        secret,
+        recovery_code_hashes: recovery_codes
+            .iter()
+            .map(|code| hash_recovery_code(code))
+            .collect(),
+        last_step: step,
+        created_time: now,
+    };
+    let result = db.save_user_totp(&user_totp);
+    record_audit_log(
+        db.get_ref(),
+        &audit_context,
+        AuditAction::EnableTotp,
+        AuditTarget::name(current_user.user_name.as_str()),
+        &result,
+    );
+    result?;
+    session.remove(SESSION_KEY_TOTP_PENDING_SECRET);
+    // the code just proved the second factor of this session
+    session
+        .insert(SESSION_KEY_TOTP_VERIFIED, true)
+        .or_else(|e| DfrError::custom_error(ErrorCode::SYSTEM_ERROR, e.to_string()))?;
+    info!("TOTP of user {} is enabled", current_user.user_name);
+    Ok(
+        HttpResponse::Ok().json(RestResponse::succeed_with_data(TotpRecoveryCodes {
+            recovery_codes,
+        })),
+    )
+}
+
+#[utoipa::path(
+    summary = "Disable TOTP of current user",
+    request_body(content = DisableTotpRequest),
+    responses(
+        (status = 200, description = "TOTP is disabled"),
+    ),
+)]
+#[delete("/totp")]
+pub async fn disable_totp(
+    requst_json: web::Json<DisableTotpRequest>,
+    db: web::Data<PoolDatabaseManager>,
+    session: Session,
+    current_user: UserInfo,
+    authentication: Authentication,
+    audit_context: AuditContext,
+) -> Result<HttpResponse, DfrError> {
+    check_session(&authentication)?;
+    let disable_totp_request = requst_json.into_inner();
+    let result = if !check_password(
+        disable_totp_request.password,
+        current_user.password_hash.clone(),
+    )
+    .await?
+    {
+        DfrError::custom_error(
+            ErrorCode::PERMISSION_DENIED,
+            String::from("Illegal password"),
+        )
+    } else {
+        match db.remove_user_totp(current_user.user_name.as_str()) {
+            Ok(0) => DfrError::custom_error(
+                ErrorCode::TOTP_NOT_ENABLED,
+                format!("TOTP of user {} is not enabled", current_user.user_name),
+            ),
+            Ok(count) => Ok(count),
+            Err(e) => Err(e),
+        }
+    };
+    record_audit_log(
+        db.get_ref(),
+        &audit_context,
+        AuditAction::DisableTotp,
+        AuditTarget::name(current_user.user_name.as_str()),
+        &result,
+    );
+    result?;
+    session.remove(SESSION_KEY_TOTP_VERIFIED);
+    info!("TOTP of user {} is disabled", current_user.user_name);
+    Ok(HttpResponse::Ok().finish())
+}
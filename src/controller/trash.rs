@@ -1,34 +1,197 @@
 use std::{
+    collections::HashSet,
     fs::{FileTimes, Permissions},
-    os::unix::fs::{chown, PermissionsExt},
-    path::PathBuf,
+    os::unix::fs::{chown, MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
-use actix_web::{delete, get, post, web, Error as AWError, HttpResponse};
-use chrono::Local;
-use log::{error, info, warn};
+use actix_web::{delete, get, middleware::from_fn, post, web, Error as AWError, HttpResponse};
+use chrono::{DateTime, Local};
+use log::{debug, error, info, warn};
 use tokio::{
     fs::{self, File},
     time::{self, Duration, Instant, MissedTickBehavior},
 };
+use uuid::Uuid;
 
 use crate::{
+    controller::{
+        audit::{record_audit_log, AuditContext, AuditTarget},
+        user::{check_path_allowed, require_delete, require_read, require_trash},
+    },
     database::{
-        file_info::{TrashFileInfo, TrashFileInfoList},
+        audit_log::AuditAction,
+        file_info::{
+            file_md5, ExtendedAttribute, FileInfo, TrashFileInfo, TrashFileInfoList, TrashStorage,
+        },
         sqlite::PoolDatabaseManager,
+        user_info::UserInfo,
     },
     model::{
         common::{ErrorCode, RestResponse},
         settings::TrashListSettings,
         trash::{
             DeleteTrashFileRequest, DeleteTrashFilesRequest, RestoreTrashFileRequest,
-            RestoreTrashFilesRequest,
+            RestoreTrashFileResult, RestoreTrashFilesRequest, TrashCheckResult,
         },
     },
-    utils::error::DfrError,
+    utils::{
+        compress::{compress_file, decompress_file, decompressed_md5},
+        error::DfrError,
+        xdg_trash::{move_to_xdg_trash, remove_from_xdg_trash, restore_from_xdg_trash},
+    },
     SharedSettings,
 };
 
+static CHECK_TRASH_FLAG: AtomicBool = AtomicBool::new(false);
+
+/// Suffix of trash files compressed with zstd
+const COMPRESSED_TRASH_FILE_SUFFIX: &str = ".zst";
+/// Suffix of temporary files trash files are compressed to, named as `<md5>.<uuid>.zst.tmp`
+const COMPRESSING_TRASH_FILE_SUFFIX: &str = ".zst.tmp";
+
+/// Trash file shared by trash info with the same md5
+pub struct TrashFile {
+    /// Path of the trash file
+    pub path: PathBuf,
+    /// Whether the trash file is compressed with zstd
+    pub compressed: bool,
+}
+
+/// Find trash file of md5, compressed trash files are named as `<md5>.zst`
+pub fn find_trash_file(trash_path: &str, md5: &str) -> Option<TrashFile> {
+    let mut path = PathBuf::from(trash_path);
+    path.push(format!("{}{}", md5, COMPRESSED_TRASH_FILE_SUFFIX));
+    if path.exists() {
+        return Some(TrashFile {
+            path,
+            compressed: true,
+        });
+    }
+    path.set_file_name(md5);
+    if path.exists() {
+        return Some(TrashFile {
+            path,
+            compressed: false,
+        });
+    }
+    None
+}
+
+/// Move file to trash dir, the file is compressed if trash compression is enabled,
+/// or moved to freedesktop.org trash if xdg trash is enabled.
+pub async fn move_file_to_trash(
+    settings: &SharedSettings,
+    file_info: &FileInfo,
+) -> Result<TrashStorage, DfrError> {
+    let system_settings = {
+        let settings = settings.lock().await;
+        settings.system.clone()
+    };
+    if system_settings.xdg_trash {
+        let xdg_trash_path = move_to_xdg_trash(Path::new(file_info.file_path.as_str()))?;
+        return Ok(TrashStorage {
+            compressed_size: None,
+            xdg_trash_path: Some(xdg_trash_path.to_string_lossy().to_string()),
+        });
+    }
+    let md5 = file_info.inode_info.md5.clone().unwrap();
+    if let Some(trash_file) = find_trash_file(system_settings.trash_path.as_str(), md5.as_str()) {
+        info!(
+            "Found file in trash dir: {:?}, remove file directly",
+            trash_file.path
+        );
+        fs::remove_file(file_info.file_path.as_str()).await?;
+        let mut storage = TrashStorage::default();
+        if trash_file.compressed {
+            storage.compressed_size = Some(fs::metadata(trash_file.path.as_path()).await?.len());
+        }
+        return Ok(storage);
+    }
+
+    let mut trash_file_path = PathBuf::from(system_settings.trash_path.as_str());
+    let need_compress = system_settings.trash_compression
+        && !file_info.file_extension.as_ref().is_some_and(|ext| {
+            system_settings
+                .trash_compression_exclude_extensions
+                .contains(ext)
+        });
+    if need_compress {
+        trash_file_path.push(format!("{}{}", md5, COMPRESSED_TRASH_FILE_SUFFIX));
+        // compress to a temporary file first, so that a partial trash file is never used. Copies
+        // of a file may be deleted concurrently, each of them needs its own temporary file.
+        let tmp_file_path = trash_file_path.with_file_name(format!(
+            "{}.{}{}",
+            md5,
+            Uuid::new_v4().simple(),
+            COMPRESSING_TRASH_FILE_SUFFIX
+        ));
+        let src = PathBuf::from(file_info.file_path.as_str());
+        let dst = tmp_file_path.clone();
+        let level = system_settings.trash_compression_level;
+        let result = tokio::task::spawn_blocking(move || compress_file(&src, &dst, level))
+            .await
+            .map_err(std::io::Error::from)?;
+        let compressed_size = match result {
+            Ok(compressed_size) => compressed_size,
+            Err(e) => {
+                let _ = fs::remove_file(tmp_file_path.as_path()).await;
+                return Err(e);
+            }
+        };
+        fs::rename(tmp_file_path.as_path(), trash_file_path.as_path()).await?;
+        fs::remove_file(file_info.file_path.as_str()).await?;
+        info!(
+            "Compress file {} to {:?}, size: {} -> {}",
+            file_info.file_path, trash_file_path, file_info.inode_info.size, compressed_size
+        );
+        return Ok(TrashStorage {
+            compressed_size: Some(compressed_size),
+            xdg_trash_path: None,
+        });
+    }
+
+    trash_file_path.push(md5.as_str());
+    // try to rename file
+    let result = fs::rename(file_info.file_path.as_str(), trash_file_path.as_path()).await;
+    if let Err(error) = result {
+        warn!("Failed to rename file: {:?}, try to copy and delete", error);
+        // if rename failed, try to copy and delete
+        fs::copy(file_info.file_path.as_str(), trash_file_path.as_path()).await?;
+        fs::remove_file(file_info.file_path.as_str()).await?;
+    }
+    Ok(TrashStorage::default())
+}
+
+/// Remove trash info of files which were restored or removed from freedesktop.org trash by others,
+/// restored files are added to file info again.
+pub async fn sync_xdg_trash_files(db: &PoolDatabaseManager) -> Result<(), DfrError> {
+    for trash_file_info in db.list_xdg_trash_files()?.iter() {
+        let xdg_trash_path = trash_file_info.xdg_trash_path.clone().unwrap();
+        if Path::new(xdg_trash_path.as_str()).exists() {
+            continue;
+        }
+        let origin_file_path = trash_file_info.get_file_path();
+        db.remove_trash_file_by_path(&trash_file_info.dir_path, &trash_file_info.file_name)?;
+        if !Path::new(origin_file_path.as_str()).is_file() {
+            info!(
+                "Trash file {} was removed from freedesktop.org trash",
+                xdg_trash_path
+            );
+            continue;
+        }
+        info!(
+            "Trash file {} was restored to {} from freedesktop.org trash",
+            xdg_trash_path, origin_file_path
+        );
+        let mut file_info = FileInfo::new(origin_file_path.as_str(), 0, Local::now())?;
+        file_info.update_md5().await?;
+        db.insert_file_info(&file_info)?;
+    }
+    Ok(())
+}
+
 pub async fn setup_remove_trash_file_timer(
     settings: web::Data<SharedSettings>,
     db: PoolDatabaseManager,
@@ -46,6 +209,9 @@ pub async fn setup_remove_trash_file_timer(
         intv.set_missed_tick_behavior(MissedTickBehavior::Delay);
         loop {
             intv.tick().await;
+            if let Err(e) = sync_xdg_trash_files(&db).await {
+                error!("Failed to sync freedesktop.org trash files: {:?}", e);
+            }
             let clear_trash_interval_s = {
                 let settings = settings.lock().await;
                 settings.system.clear_trash_interval_s
@@ -57,14 +223,22 @@ pub async fn setup_remove_trash_file_timer(
             query_list_params.end_removed_time = Some(need_remove_time.clone());
             info!("Start to clear old trash files before {} ", need_remove_time);
             loop {
-                let list_result = db.list_trash_files(&query_list_params);
+                let list_result = db.list_trash_files(&query_list_params, &[]);
                 if list_result.is_err() {
                     error!("Failed to list trash files: {:?}", list_result.err());
                     break;
                 }
                 let trash_files = list_result.unwrap();
                 for file in trash_files.trash_file_info_list.iter() {
-                    if let Err(e) = clear_trash_file(&settings, &db, file).await {
+                    let result = clear_trash_file(&settings, &db, file).await;
+                    record_audit_log(
+                        &db,
+                        &AuditContext::default(),
+                        AuditAction::PurgeTrashFile,
+                        AuditTarget::from(file),
+                        &result,
+                    );
+                    if let Err(e) = result {
                         error!("Failed to delete trash file {:?}: {:?}", file, e);
                     } else {
                         info!("Deleted trash file {:?}", file);
@@ -79,32 +253,206 @@ pub async fn setup_remove_trash_file_timer(
     Ok(())
 }
 
-async fn clear_trash_file(
+pub async fn setup_check_trash_timer(
+    settings: web::Data<SharedSettings>,
+    db: PoolDatabaseManager,
+) -> Result<(), DfrError> {
+    tokio::spawn(async move {
+        info!("Start to setup checking trash file timer");
+        let mut last_check_time = Instant::now();
+        // Set up a timer to run every 60 seconds, the check itself runs every check_trash_interval_s
+        let mut intv = time::interval_at(
+            Instant::now() + Duration::from_secs(60),
+            Duration::from_secs(60),
+        );
+        intv.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            intv.tick().await;
+            let check_trash_interval_s = {
+                let settings = settings.lock().await;
+                settings.system.check_trash_interval_s
+            };
+            if check_trash_interval_s == 0
+                || last_check_time.elapsed() < Duration::from_secs(check_trash_interval_s as u64)
+            {
+                continue;
+            }
+            last_check_time = Instant::now();
+            match check_trash_files(&settings, &db, &AuditContext::default()).await {
+                Ok(result) => info!("Check trash files finished: {:?}", result),
+                Err(e) => error!("Failed to check trash files: {:?}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Re-hash trash files, remove orphan trash files and mark trash info whose trash file is missing or corrupted
+pub async fn check_trash_files(
+    settings: &SharedSettings,
+    db: &PoolDatabaseManager,
+    audit_context: &AuditContext,
+) -> Result<TrashCheckResult, DfrError> {
+    if CHECK_TRASH_FLAG
+        .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        return DfrError::custom_error(
+            ErrorCode::TRASH_CHECK_IN_PROGRESS,
+            String::from("Trash check already in progress"),
+        );
+    }
+    let result = _check_trash_files(settings, db, audit_context).await;
+    CHECK_TRASH_FLAG.store(false, Ordering::Release);
+    result
+}
+
+async fn _check_trash_files(
+    settings: &SharedSettings,
+    db: &PoolDatabaseManager,
+    audit_context: &AuditContext,
+) -> Result<TrashCheckResult, DfrError> {
+    let (trash_path, orphan_grace_period_s) = {
+        let settings = settings.lock().await;
+        (
+            settings.system.trash_path.clone(),
+            settings.system.orphan_trash_grace_period_s as i64,
+        )
+    };
+    info!("Start to check trash files in {}", trash_path);
+    let mut result = TrashCheckResult {
+        start_time: Local::now(),
+        end_time: Local::now(),
+        checked_count: 0,
+        corrupted_files: Vec::new(),
+        orphan_files: Vec::new(),
+        missing_files: Vec::new(),
+        unknown_files: Vec::new(),
+        stale_tmp_files: Vec::new(),
+    };
+    let mut md5_set: HashSet<String> = db.list_trash_md5()?.into_iter().collect();
+    let mut entries = fs::read_dir(trash_path.as_str()).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let metadata = entry.metadata().await?;
+        if metadata.is_file() && file_name.ends_with(COMPRESSING_TRASH_FILE_SUFFIX) {
+            // left behind if the server stopped while compressing
+            let moved_s = Local::now().timestamp() - metadata.ctime();
+            if moved_s >= orphan_grace_period_s {
+                warn!("Remove stale temporary trash file {:?}", entry.path());
+                fs::remove_file(entry.path()).await?;
+                result.stale_tmp_files.push(file_name);
+            }
+            continue;
+        }
+        let (file_md5_name, compressed) = match file_name.strip_suffix(COMPRESSED_TRASH_FILE_SUFFIX)
+        {
+            Some(file_md5_name) => (file_md5_name.to_string(), true),
+            None => (file_name.clone(), false),
+        };
+        if !metadata.is_file() || !is_md5(file_md5_name.as_str()) {
+            result.unknown_files.push(file_name);
+            continue;
+        }
+        if !md5_set.remove(&file_md5_name) {
+            let moved_s = Local::now().timestamp() - metadata.ctime();
+            if moved_s < orphan_grace_period_s {
+                debug!("Skip recently moved trash file {:?}", entry.path());
+                continue;
+            }
+            warn!("Remove orphan trash file {:?}", entry.path());
+            let remove_result = fs::remove_file(entry.path()).await.map_err(DfrError::from);
+            let target = AuditTarget {
+                path: Some(entry.path().to_string_lossy().to_string()),
+                md5: Some(file_md5_name.clone()),
+                size: Some(metadata.len()),
+            };
+            record_audit_log(
+                db,
+                audit_context,
+                AuditAction::RemoveOrphanTrashFile,
+                target,
+                &remove_result,
+            );
+            remove_result?;
+            result.orphan_files.push(file_md5_name);
+            continue;
+        }
+        let md5_result = if compressed {
+            let path = entry.path();
+            tokio::task::spawn_blocking(move || decompressed_md5(&path))
+                .await
+                .map_err(std::io::Error::from)?
+        } else {
+            file_md5(entry.path().to_string_lossy().as_ref())
+                .await
+                .map(|(md5, _)| md5)
+        };
+        let md5 = match md5_result {
+            Ok(md5) => md5,
+            Err(e) => {
+                // trash file may be removed while checking
+                warn!("Failed to hash trash file {:?}: {:?}", entry.path(), e);
+                continue;
+            }
+        };
+        result.checked_count += 1;
+        let corrupted = md5 != file_md5_name;
+        if corrupted {
+            error!(
+                "Trash file {:?} is corrupted, current md5: {}",
+                entry.path(),
+                md5
+            );
+            result.corrupted_files.push(file_md5_name.clone());
+        }
+        db.update_trash_blob_status(file_md5_name.as_str(), false, corrupted)?;
+    }
+    for md5 in md5_set.into_iter() {
+        warn!("Trash file {} is missing in {}", md5, trash_path);
+        db.update_trash_blob_status(md5.as_str(), true, false)?;
+        result.missing_files.push(md5);
+    }
+    result.end_time = Local::now();
+    Ok(result)
+}
+
+fn is_md5(file_name: &str) -> bool {
+    file_name.len() == 32
+        && file_name
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
+
+pub async fn clear_trash_file(
     settings: &SharedSettings,
     db: &PoolDatabaseManager,
     trash_file_info: &TrashFileInfo,
 ) -> Result<(), DfrError> {
+    if let Some(xdg_trash_path) = trash_file_info.xdg_trash_path.clone() {
+        remove_from_xdg_trash(Path::new(xdg_trash_path.as_str()))?;
+        db.remove_trash_file_by_path(&trash_file_info.dir_path, &trash_file_info.file_name)?;
+        info!("Delete trash file '{}' successfully", xdg_trash_path);
+        return Ok(());
+    }
     let trash_path = {
         let settings = settings.lock().await;
         settings.system.trash_path.clone()
     };
-    let mut file = PathBuf::from(trash_path);
-    file.push(trash_file_info.md5.as_str());
-    if !file.exists() {
-        // remove trash file from db
-        db.remove_trash_file_by_md5(&trash_file_info.md5)?;
-        warn!(
-            "Trash file {:?} is not exist, remove trash file from db by md5",
-            file
-        );
+    let file = match find_trash_file(trash_path.as_str(), trash_file_info.md5.as_str()) {
+        Some(trash_file) => trash_file.path,
+        None => {
+            // remove trash file from db
+            db.remove_trash_file_by_md5(&trash_file_info.md5)?;
+            warn!(
+                "Trash file {} is not exist in {}, remove trash file from db by md5",
+                trash_file_info.md5, trash_path
+            );
 
-        return Ok(());
-    }
-    let mut query_list_params = TrashListSettings::default();
-    query_list_params.md5 = Some(trash_file_info.md5.clone());
-
-    let trash_file_list = db.list_trash_files(&query_list_params)?;
-    if trash_file_list.trash_file_info_list.len() == 1 {
+            return Ok(());
+        }
+    };
+    if db.count_trash_file_by_md5(trash_file_info.md5.as_str())? == 1 {
         fs::remove_file(file.as_path()).await?;
     }
     db.remove_trash_file_by_path(&trash_file_info.dir_path, &trash_file_info.file_name)?;
@@ -119,7 +467,7 @@ async fn clear_trash_file(
         (status = 200, description = "Trash List settings", body = RestResponse<TrashListSettings>),
     ),
 )]
-#[get("/trash/list/settings")]
+#[get("/trash/list/settings", wrap = "from_fn(require_read)")]
 pub async fn query_trash_list_settings(
     settings: web::Data<SharedSettings>,
 ) -> Result<HttpResponse, AWError> {
@@ -134,14 +482,17 @@ pub async fn query_trash_list_settings(
         (status = 200, description = "The list of trash file", body=TrashFileInfoList)
     ),
 )]
-#[get("/trash/list")]
+#[get("/trash/list", wrap = "from_fn(require_read)")]
 pub async fn list_trash_files(
     query_list: web::Query<TrashListSettings>,
     db: web::Data<PoolDatabaseManager>,
     settings: web::Data<SharedSettings>,
+    current_user: UserInfo,
 ) -> Result<HttpResponse, AWError> {
     let conn = db.get_ref();
-    let file_info_list = conn.0.list_trash_files(&query_list)?;
+    let file_info_list = conn
+        .0
+        .list_trash_files(&query_list, &current_user.allowed_paths)?;
     {
         let mut settings = settings.lock().await;
         settings.trash_list = query_list.into_inner().clone();
@@ -159,20 +510,34 @@ pub async fn list_trash_files(
         (status = 501, description = "Not implemented"),
     ),
 )]
-#[delete("/trash/file")]
+#[delete("/trash/file", wrap = "from_fn(require_delete)")]
 pub async fn delete_trash_file(
     requst_json: web::Json<DeleteTrashFileRequest>,
     db: web::Data<PoolDatabaseManager>,
     settings: web::Data<SharedSettings>,
+    current_user: UserInfo,
+    audit_context: AuditContext,
 ) -> Result<HttpResponse, DfrError> {
     let delete_trash_file_request = requst_json.into_inner();
+    check_path_allowed(
+        &current_user,
+        Path::new(delete_trash_file_request.dir_path.as_str()),
+    )?;
 
     let db_file_info = db.get_trash_file_by_path(
         delete_trash_file_request.dir_path.as_str(),
         delete_trash_file_request.file_name.as_str(),
     )?;
 
-    clear_trash_file(&settings, db.get_ref(), &db_file_info).await?;
+    let result = clear_trash_file(&settings, db.get_ref(), &db_file_info).await;
+    record_audit_log(
+        db.get_ref(),
+        &audit_context,
+        AuditAction::DeleteTrashFile,
+        AuditTarget::from(&db_file_info),
+        &result,
+    );
+    result?;
     Ok(HttpResponse::Ok().finish())
 }
 
@@ -185,7 +550,7 @@ pub async fn delete_trash_file(
         (status = 501, description = "Not implemented"),
     ),
 )]
-#[delete("/trash/files")]
+#[delete("/trash/files", wrap = "from_fn(require_delete)")]
 pub async fn delete_trash_files(
     requst_json: web::Json<DeleteTrashFilesRequest>,
 ) -> Result<HttpResponse, AWError> {
@@ -200,39 +565,48 @@ pub async fn delete_trash_files(
 
 #[utoipa::path(
     summary = "Restore a trash file",
-    request_body(content = DeleteTrashFileRequest),
+    request_body(content = RestoreTrashFileRequest),
     responses(
-        (status = 200, description = "Delete trash file successfully"),
+        (status = 200, description = "Restore trash file successfully", body = RestResponse<RestoreTrashFileResult>),
         (status = 400, description = "Bad request"),
         (status = 501, description = "Not implemented"),
     ),
 )]
-#[post("/trash/file/restore")]
+#[post("/trash/file/restore", wrap = "from_fn(require_trash)")]
 pub async fn restore_trash_file(
     requst_json: web::Json<RestoreTrashFileRequest>,
     db: web::Data<PoolDatabaseManager>,
     settings: web::Data<SharedSettings>,
+    current_user: UserInfo,
+    audit_context: AuditContext,
 ) -> Result<HttpResponse, DfrError> {
-    let delete_trash_file_request = requst_json.into_inner();
+    let restore_trash_file_request = requst_json.into_inner();
+    check_path_allowed(
+        &current_user,
+        Path::new(restore_trash_file_request.dir_path.as_str()),
+    )?;
 
     let trash_file_info = db.get_trash_file_by_path(
-        delete_trash_file_request.dir_path.as_str(),
-        delete_trash_file_request.file_name.as_str(),
+        restore_trash_file_request.dir_path.as_str(),
+        restore_trash_file_request.file_name.as_str(),
     )?;
 
-    let trash_path = {
-        let settings = settings.lock().await;
-        settings.system.trash_path.clone()
-    };
-    let mut file = PathBuf::from(trash_path);
-    file.push(trash_file_info.md5.as_str());
-    if !file.exists() {
-        error!("Trash file {:?} is not exist", file);
-        let message = format!("Trash file {:?} is not exist", file);
-        return DfrError::custom_error(ErrorCode::SYSTEM_ERROR, message);
-    }
-    // copy trash file to origin path
+    let result = _restore_trash_file(&trash_file_info, db.get_ref(), settings.get_ref()).await;
+    record_audit_log(
+        db.get_ref(),
+        &audit_context,
+        AuditAction::RestoreTrashFile,
+        AuditTarget::from(&trash_file_info),
+        &result,
+    );
+    Ok(HttpResponse::Ok().json(RestResponse::succeed_with_data(result?)))
+}
 
+pub async fn _restore_trash_file(
+    trash_file_info: &TrashFileInfo,
+    db: &PoolDatabaseManager,
+    settings: &SharedSettings,
+) -> Result<RestoreTrashFileResult, DfrError> {
     let mut origin_file_path = PathBuf::from(trash_file_info.dir_path.as_str());
     if !origin_file_path.is_dir() {
         // create directory
@@ -240,40 +614,140 @@ pub async fn restore_trash_file(
     }
     origin_file_path.push(trash_file_info.file_name.as_str());
 
-    fs::copy(file.as_path(), origin_file_path.as_path()).await?;
-    let restore_file = File::open(origin_file_path.as_path()).await?;
-    // set permissions
-    restore_file
-        .set_permissions(Permissions::from_mode(trash_file_info.permissions))
-        .await?;
-    let std_file = restore_file.into_std().await;
-    // set modified time
-    // FIXME we can not set create time by using std library
-    let times = FileTimes::new().set_modified(trash_file_info.modified.into());
-    std_file.set_times(times)?;
-    // set owner
-    chown(
-        origin_file_path.as_path(),
-        Some(trash_file_info.uid),
-        Some(trash_file_info.gid),
+    // trash file in trash path which is not shared by other trash info any more
+    let mut unused_trash_file = None;
+    if let Some(xdg_trash_path) = trash_file_info.xdg_trash_path.clone() {
+        // move file in freedesktop.org trash back
+        let xdg_trash_path = PathBuf::from(xdg_trash_path);
+        if !xdg_trash_path.exists() {
+            error!("Trash file {:?} is not exist", xdg_trash_path);
+            let message = format!("Trash file {:?} is not exist", xdg_trash_path);
+            return DfrError::custom_error(ErrorCode::TRASH_FILE_NOT_FOUND, message);
+        }
+        restore_from_xdg_trash(&xdg_trash_path, &origin_file_path)?;
+    } else {
+        let trash_path = {
+            let settings = settings.lock().await;
+            settings.system.trash_path.clone()
+        };
+        let trash_file = match find_trash_file(trash_path.as_str(), trash_file_info.md5.as_str()) {
+            Some(trash_file) => trash_file,
+            None => {
+                error!(
+                    "Trash file {} is not exist in {}",
+                    trash_file_info.md5, trash_path
+                );
+                db.update_trash_blob_status(trash_file_info.md5.as_str(), true, false)?;
+                let message = format!(
+                    "Trash file {} is not exist in {}",
+                    trash_file_info.md5, trash_path
+                );
+                return DfrError::custom_error(ErrorCode::TRASH_FILE_NOT_FOUND, message);
+            }
+        };
+        if trash_file_info.blob_corrupted {
+            let message = format!("Trash file {:?} is corrupted", trash_file.path);
+            return DfrError::custom_error(ErrorCode::TRASH_FILE_CORRUPTED, message);
+        }
+        // copy trash file to origin path
+        if trash_file.compressed {
+            let src = trash_file.path.clone();
+            let dst = origin_file_path.clone();
+            tokio::task::spawn_blocking(move || decompress_file(&src, &dst))
+                .await
+                .map_err(std::io::Error::from)??;
+        } else {
+            fs::copy(trash_file.path.as_path(), origin_file_path.as_path()).await?;
+        }
+        // check if trash file is unique
+        if db.count_trash_file_by_md5(trash_file_info.md5.as_str())? == 1 {
+            unused_trash_file = Some(trash_file.path);
+        }
+    }
+    let xattrs = db.get_trash_file_xattrs(
+        trash_file_info.dir_path.as_str(),
+        trash_file_info.file_name.as_str(),
     )?;
+    let unrestored_attributes =
+        restore_file_attributes(origin_file_path.as_path(), trash_file_info, &xattrs).await?;
 
-    // check if trash file is unique
-    let mut query_list_params = TrashListSettings::default();
-    query_list_params.md5 = Some(trash_file_info.md5.clone());
-
-    let trash_file_list = db.list_trash_files(&query_list_params)?;
-    if trash_file_list.trash_file_info_list.len() == 1 {
-        info!("Remove trash file {:?}", file);
-        fs::remove_file(file.as_path()).await?;
+    if let Some(unused_trash_file) = unused_trash_file {
+        info!("Remove trash file {:?}", unused_trash_file);
+        fs::remove_file(unused_trash_file.as_path()).await?;
     }
-    db.restore_trash_file_by_path(&trash_file_info)?;
+    db.restore_trash_file_by_path(trash_file_info)?;
 
     info!(
         "Restore trash file '{}' successfully",
         trash_file_info.get_file_path()
     );
-    Ok(HttpResponse::Ok().finish())
+    Ok(RestoreTrashFileResult {
+        unrestored_attributes,
+    })
+}
+
+/// Restore owner, extended attributes, permissions and timestamps of the restored file.
+/// Return the attributes which could not be restored.
+async fn restore_file_attributes(
+    file_path: &Path,
+    trash_file_info: &TrashFileInfo,
+    xattrs: &[ExtendedAttribute],
+) -> Result<Vec<String>, DfrError> {
+    let mut unrestored_attributes = Vec::new();
+    // set owner first, changing owner may clear the setuid and setgid bits
+    if let Err(err) = chown(
+        file_path,
+        Some(trash_file_info.uid),
+        Some(trash_file_info.gid),
+    ) {
+        warn!("Failed to restore owner of {:?}: {:?}", file_path, err);
+        unrestored_attributes.push(String::from("owner"));
+    }
+    // set extended attributes, including POSIX ACLs
+    for xattr in xattrs.iter() {
+        if let Err(err) = xattr::set(file_path, xattr.name.as_str(), xattr.value.as_slice()) {
+            warn!(
+                "Failed to restore extended attribute {} of {:?}: {:?}",
+                xattr.name, file_path, err
+            );
+            unrestored_attributes.push(format!("xattr:{}", xattr.name));
+        }
+    }
+    let restore_file = File::open(file_path).await?;
+    // set permissions
+    if let Err(err) = restore_file
+        .set_permissions(Permissions::from_mode(trash_file_info.permissions))
+        .await
+    {
+        warn!(
+            "Failed to restore permissions of {:?}: {:?}",
+            file_path, err
+        );
+        unrestored_attributes.push(String::from("permissions"));
+    }
+    let std_file = restore_file.into_std().await;
+    // set modified and accessed time
+    let mut times = FileTimes::new().set_modified(trash_file_info.modified.into());
+    if let Some(accessed) = trash_file_info.accessed {
+        times = times.set_accessed(accessed.into());
+    }
+    if let Err(err) = std_file.set_times(times) {
+        warn!("Failed to restore times of {:?}: {:?}", file_path, err);
+        unrestored_attributes.push(String::from("modified"));
+        if trash_file_info.accessed.is_some() {
+            unrestored_attributes.push(String::from("accessed"));
+        }
+    }
+    // we can not set create time by using std library, and some file systems have no create time
+    match std_file.metadata().and_then(|metadata| metadata.created()) {
+        Ok(created) if DateTime::<Local>::from(created) == trash_file_info.created => {}
+        Ok(_) => unrestored_attributes.push(String::from("created")),
+        Err(err) => {
+            warn!("Failed to read create time of {:?}: {:?}", file_path, err);
+            unrestored_attributes.push(String::from("created"));
+        }
+    }
+    Ok(unrestored_attributes)
 }
 
 #[utoipa::path(
@@ -285,7 +759,7 @@ pub async fn restore_trash_file(
         (status = 501, description = "Not implemented"),
     ),
 )]
-#[post("/trash/files/restore")]
+#[post("/trash/files/restore", wrap = "from_fn(require_trash)")]
 pub async fn restore_trash_files(
     requst_json: web::Json<RestoreTrashFilesRequest>,
 ) -> Result<HttpResponse, AWError> {
@@ -297,3 +771,19 @@ pub async fn restore_trash_files(
     );
     Ok(HttpResponse::Ok().finish())
 }
+
+#[utoipa::path(
+    summary = "Check trash files",
+    responses(
+        (status = 200, description = "Trash check result", body = RestResponse<TrashCheckResult>),
+    ),
+)]
+#[post("/trash/check", wrap = "from_fn(require_delete)")]
+pub async fn check_trash(
+    db: web::Data<PoolDatabaseManager>,
+    settings: web::Data<SharedSettings>,
+    audit_context: AuditContext,
+) -> Result<HttpResponse, DfrError> {
+    let result = check_trash_files(&settings, db.get_ref(), &audit_context).await?;
+    Ok(HttpResponse::Ok().json(RestResponse::succeed_with_data(result)))
+}
@@ -1,20 +1,108 @@
-use actix_session::{Session, SessionGetError};
-use actix_web::{body::MessageBody, dev::{ServiceRequest, ServiceResponse}, get, middleware::Next, Error as AWError, FromRequest, HttpResponse};
+use std::{
+    future::{ready, Ready},
+    net::IpAddr,
+    path::{Component, Path},
+};
+
+use actix_session::{Session, SessionGetError, SessionInsertError};
+use actix_web::{
+    body::MessageBody,
+    delete,
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    get,
+    http::header,
+    middleware::{from_fn, Next},
+    post, put, web, Error as AWError, FromRequest, HttpMessage, HttpRequest, HttpResponse,
+};
+use chrono::Local;
+use ipnet::IpNet;
 use log::{info, warn};
 
-use crate::model::user::{CurrentUser, NoLogintUser, NoticeIconList, UserRespone};
+use crate::{
+    controller::{
+        audit::{record_audit_log, AuditContext, AuditTarget},
+        login::new_password_hash,
+    },
+    database::{
+        api_token::{ApiToken, TokenScope},
+        audit_log::AuditAction,
+        sqlite::PoolDatabaseManager,
+        user_info::{UserInfo, UserInfoList, UserRole},
+    },
+    model::{
+        common::ErrorCode,
+        settings::{SystemSettings, UserSettings},
+        user::{
+            AddUserRequest, CurrentUser, DeleteUserRequest, NoLogintUser, NoticeIconList,
+            UpdateUserRequest, UserRespone,
+        },
+    },
+    utils::{error::DfrError, token::hash_api_token},
+    SharedSettings,
+};
 
 pub const SESSION_KEY_USERNAME: &str = "username";
-pub const USER_ADMIN: &str = "admin";
+pub const SESSION_KEY_LOGIN_TIME: &str = "login_time";
+pub const SESSION_KEY_LAST_ACCESS_TIME: &str = "last_access_time";
+pub const SESSION_KEY_REMEMBER_ME: &str = "remember_me";
+pub const SESSION_KEY_TOTP_VERIFIED: &str = "totp_verified";
 
 pub trait SessionExt {
     fn get_current_user(&self) -> Result<Option<String>, SessionGetError>;
+    fn login(
+        &self,
+        user_name: &str,
+        remember_me: bool,
+        totp_verified: bool,
+    ) -> Result<(), SessionInsertError>;
+    fn get_active_user(&self, settings: &SystemSettings) -> Result<Option<String>, AWError>;
 }
 
 impl SessionExt for Session {
     fn get_current_user(&self) -> Result<Option<String>, SessionGetError> {
         self.get::<String>(SESSION_KEY_USERNAME)
     }
+
+    /// Start a new session for the user, remembered sessions are not subject to the idle timeout
+    fn login(
+        &self,
+        user_name: &str,
+        remember_me: bool,
+        totp_verified: bool,
+    ) -> Result<(), SessionInsertError> {
+        let now = Local::now().timestamp();
+        self.clear();
+        self.renew();
+        self.insert(SESSION_KEY_USERNAME, user_name)?;
+        self.insert(SESSION_KEY_LOGIN_TIME, now)?;
+        self.insert(SESSION_KEY_LAST_ACCESS_TIME, now)?;
+        self.insert(SESSION_KEY_REMEMBER_ME, remember_me)?;
+        self.insert(SESSION_KEY_TOTP_VERIFIED, totp_verified)
+    }
+
+    /// Get the user of the session if it is not expired, expired sessions are purged. The last
+    /// access time of the session is refreshed.
+    fn get_active_user(&self, settings: &SystemSettings) -> Result<Option<String>, AWError> {
+        let Some(user_name) = self.get_current_user()? else {
+            return Ok(None);
+        };
+        let now = Local::now().timestamp();
+        let login_time = self.get::<i64>(SESSION_KEY_LOGIN_TIME)?.unwrap_or(0);
+        let last_access_time = self.get::<i64>(SESSION_KEY_LAST_ACCESS_TIME)?.unwrap_or(0);
+        let expired = if self.get::<bool>(SESSION_KEY_REMEMBER_ME)?.unwrap_or(false) {
+            now - login_time > settings.session_remember_me_s as i64
+        } else {
+            now - login_time > settings.session_absolute_timeout_s as i64
+                || now - last_access_time > settings.session_idle_timeout_s as i64
+        };
+        if expired {
+            info!("Session of user {} expired", user_name);
+            self.purge();
+            return Ok(None);
+        }
+        self.insert(SESSION_KEY_LAST_ACCESS_TIME, now)?;
+        Ok(Some(user_name))
+    }
 }
 
 #[utoipa::path(
@@ -25,10 +113,17 @@ impl SessionExt for Session {
     ),
 )]
 #[get("/api/currentUser")]
-pub async fn get_current_user(session: Session) -> Result<HttpResponse, AWError> {
-    if let Some(username) = session.get_current_user()? {
+pub async fn get_current_user(
+    req: HttpRequest,
+    session: Session,
+    db: web::Data<PoolDatabaseManager>,
+    settings: web::Data<SharedSettings>,
+) -> Result<HttpResponse, AWError> {
+    let system_settings = settings.lock().await.system.clone();
+    let user_info = get_request_user(&req, &session, &db, &system_settings)?;
+    if let Some(user_info) = user_info {
         let current_user = CurrentUser {
-            name: Some(username),
+            name: Some(user_info.user_name),
             avatar: None,
             userid: None,
             email: None,
@@ -39,7 +134,7 @@ pub async fn get_current_user(session: Session) -> Result<HttpResponse, AWError>
             notify_count: None,
             unread_count: None,
             country: None,
-            access: Some(USER_ADMIN.to_string()),
+            access: Some(user_info.role.to_string()),
             geographic: None,
             address: None,
             phone: None,
@@ -73,12 +168,18 @@ pub async fn get_current_user(session: Session) -> Result<HttpResponse, AWError>
     ),
 )]
 #[get("/api/notices")]
-pub async fn get_notices(session: Session) -> Result<HttpResponse, AWError> {
-    let user = session.get_current_user()?;
+pub async fn get_notices(
+    req: HttpRequest,
+    session: Session,
+    db: web::Data<PoolDatabaseManager>,
+    settings: web::Data<SharedSettings>,
+) -> Result<HttpResponse, AWError> {
+    let system_settings = settings.lock().await.system.clone();
+    let user = get_request_user(&req, &session, &db, &system_settings)?;
     if user.is_none() {
         return Ok(HttpResponse::Unauthorized().body("User is not logged in."));
     }
-    let username = user.unwrap();
+    let username = user.unwrap().user_name;
     info!("Fetching notices for user: {}", username);
 
     // Simulate fetching notices for the user
@@ -90,22 +191,647 @@ pub async fn get_notices(session: Session) -> Result<HttpResponse, AWError> {
     return Ok(HttpResponse::Ok().json(notice_icon_list));
 }
 
-/// Middleware to reject anonymous users.
+/// How a request is authenticated, stored in request extensions by `reject_anonymous_users`
+#[derive(Debug, Clone)]
+pub enum Authentication {
+    /// Cookie session created by login
+    Session {
+        /// The login is verified with a TOTP code or recovery code
+        totp_verified: bool,
+    },
+    /// `Authorization: Bearer` api token
+    ApiToken(ApiToken),
+    /// User name header set by a trusted reverse proxy
+    Proxy,
+}
+
+impl Authentication {
+    /// Requests authenticated with session have all scopes
+    pub fn has_scope(&self, scope: TokenScope) -> bool {
+        match self {
+            Authentication::Session { .. } | Authentication::Proxy => true,
+            Authentication::ApiToken(api_token) => api_token.scopes.contains(&scope),
+        }
+    }
+
+    /// Only sessions verified with a second factor count, api tokens have no second factor. Proxy
+    /// logins neither, so `totp_required_for_delete` is rejected together with proxy auth
+    pub fn is_totp_verified(&self) -> bool {
+        matches!(
+            self,
+            Authentication::Session {
+                totp_verified: true
+            }
+        )
+    }
+}
+
+impl FromRequest for Authentication {
+    type Error = AWError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<Authentication>()
+                .cloned()
+                .ok_or_else(|| actix_web::error::ErrorUnauthorized("User is not logged in.")),
+        )
+    }
+}
+
+/// Get the token of `Authorization: Bearer <token>` header
+fn get_bearer_token(req: &ServiceRequest) -> Option<String> {
+    let authorization = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = authorization.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("Bearer") {
+        return None;
+    }
+    Some(token.trim().to_string())
+}
+
+/// Look up the user of a valid api token, and update the last used time of the token
+fn authenticate_api_token(
+    db: &PoolDatabaseManager,
+    token: &str,
+) -> Result<Option<(UserInfo, ApiToken)>, DfrError> {
+    let Some(api_token) = db.get_api_token_by_hash(hash_api_token(token).as_str())? else {
+        return Ok(None);
+    };
+    let now = Local::now();
+    if !api_token.is_valid(&now) {
+        warn!(
+            "Api token {} of user {} is revoked or expired",
+            api_token.name, api_token.user_name
+        );
+        return Ok(None);
+    }
+    let Some(user_info) = db.get_user_by_name(api_token.user_name.as_str())? else {
+        return Ok(None);
+    };
+    db.update_api_token_last_used_time(api_token.id, &now)?;
+    Ok(Some((user_info, api_token)))
+}
+
+/// Whether the client ip is one of the trusted reverse proxies
+pub fn is_trusted_proxy(ip: IpAddr, settings: &SystemSettings) -> bool {
+    // ipv4 clients of a dual stack listener are seen as ipv4 mapped ipv6 addresses
+    let ip = match ip {
+        IpAddr::V6(ipv6) => ipv6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    };
+    settings
+        .proxy_auth_trusted_ips
+        .iter()
+        .any(|trusted_ip| match trusted_ip.parse::<IpNet>() {
+            Ok(trusted_net) => trusted_net.contains(&ip),
+            Err(_) => trusted_ip.parse::<IpAddr>() == Ok(ip),
+        })
+}
+
+/// User name set by a trusted reverse proxy, none if the request is not from a trusted proxy or
+/// has no user name header
+pub fn get_proxy_user_name(req: &HttpRequest, settings: &SystemSettings) -> Option<String> {
+    let peer_ip = req.peer_addr()?.ip();
+    if !is_trusted_proxy(peer_ip, settings) {
+        warn!(
+            "Ignore header {} of untrusted client {}",
+            settings.proxy_auth_header, peer_ip
+        );
+        return None;
+    }
+    let user_name = req
+        .headers()
+        .get(settings.proxy_auth_header.as_str())?
+        .to_str()
+        .ok()?
+        .trim();
+    if user_name.is_empty() {
+        return None;
+    }
+    Some(user_name.to_string())
+}
+
+/// Look up the user authenticated by the reverse proxy or identity provider, unknown users are
+/// created with the role and without local password
+pub fn provision_user(
+    db: &PoolDatabaseManager,
+    user_name: &str,
+    role: UserRole,
+    source: &str,
+) -> Result<UserInfo, DfrError> {
+    if let Some(user_info) = db.get_user_by_name(user_name)? {
+        return Ok(user_info);
+    }
+    let now = Local::now();
+    let user_info = UserInfo {
+        user_name: user_name.to_string(),
+        password_hash: String::new(),
+        role,
+        allowed_paths: vec![],
+        created_time: now,
+        modified_time: now,
+    };
+    let result = db.insert_user(&user_info);
+    record_audit_log(
+        db,
+        &AuditContext::default(),
+        AuditAction::AddUser,
+        AuditTarget::name(user_name),
+        &result,
+    );
+    if let Err(e) = result {
+        // the user may be created by a concurrent request
+        return db.get_user_by_name(user_name)?.ok_or(e);
+    }
+    info!(
+        "Create user {} with role {} authenticated by {}",
+        user_name, user_info.role, source
+    );
+    Ok(user_info)
+}
+
+/// Change the role of the user to the role granted by the identity provider
+pub fn sync_user_role(
+    db: &PoolDatabaseManager,
+    user_info: &mut UserInfo,
+    role: UserRole,
+) -> Result<(), DfrError> {
+    if user_info.role == role {
+        return Ok(());
+    }
+    if role < user_info.role {
+        check_last_admin(db, user_info)?;
+    }
+    let mut new_user_info = user_info.clone();
+    new_user_info.role = role;
+    new_user_info.modified_time = Local::now();
+    let result = db.update_user(user_info.user_name.as_str(), &new_user_info);
+    record_audit_log(
+        db,
+        &AuditContext::default(),
+        AuditAction::UpdateUser,
+        AuditTarget::name(user_info.user_name.as_str()),
+        &result,
+    );
+    result?;
+    info!(
+        "Change role of user {} from {} to {}",
+        user_info.user_name, user_info.role, role
+    );
+    *user_info = new_user_info;
+    Ok(())
+}
+
+/// User of a request without api token, authenticated by the reverse proxy if proxy
+/// authentication is enabled, otherwise by the cookie session
+fn get_request_user(
+    req: &HttpRequest,
+    session: &Session,
+    db: &PoolDatabaseManager,
+    settings: &SystemSettings,
+) -> Result<Option<UserInfo>, AWError> {
+    if !settings.proxy_auth_header.is_empty() {
+        return match get_proxy_user_name(req, settings) {
+            Some(user_name) => Ok(Some(provision_user(
+                db,
+                &user_name,
+                settings.proxy_auth_default_role,
+                "reverse proxy",
+            )?)),
+            None => Ok(None),
+        };
+    }
+    match session.get_active_user(settings)? {
+        Some(user_name) => Ok(db.get_user_by_name(user_name.as_str())?),
+        None => Ok(None),
+    }
+}
+
+/// Middleware to reject anonymous users. Requests are authenticated with the
+/// `Authorization: Bearer` api token if present, otherwise with the user name header of the
+/// reverse proxy if proxy authentication is enabled, otherwise with the cookie session. The user
+/// is looked up in the database, so that deleted users are rejected and role changes take effect
+/// immediately, and is stored in request extensions for the role checks and handlers.
 pub async fn reject_anonymous_users(
     mut req: ServiceRequest,
     next: Next<impl MessageBody>,
 ) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
-    let session = {
-        let (http_request, payload) = req.parts_mut();
-        //TypedSession::from_request(http_request, payload).await
-        Session::from_request(http_request, payload).await
-    }?;
-
-    match session.get_current_user()? {
-        Some(_) => next.call(req).await,
+    let Some(db) = req.app_data::<web::Data<PoolDatabaseManager>>().cloned() else {
+        return Err(actix_web::error::ErrorInternalServerError(
+            "Database is not available.",
+        ));
+    };
+    let Some(settings) = req.app_data::<web::Data<SharedSettings>>().cloned() else {
+        return Err(actix_web::error::ErrorInternalServerError(
+            "Settings are not available.",
+        ));
+    };
+
+    let authenticated = match get_bearer_token(&req) {
+        Some(token) => match authenticate_api_token(&db, token.as_str())? {
+            Some((user_info, api_token)) => Some((user_info, Authentication::ApiToken(api_token))),
+            None => {
+                warn!("Invalid api token tried to access protected resource.");
+                return Err(actix_web::error::ErrorUnauthorized("Invalid api token."));
+            }
+        },
+        None => {
+            let session = {
+                let (http_request, payload) = req.parts_mut();
+                //TypedSession::from_request(http_request, payload).await
+                Session::from_request(http_request, payload).await
+            }?;
+            let system_settings = settings.lock().await.system.clone();
+            let authentication = if system_settings.proxy_auth_header.is_empty() {
+                let totp_verified = session
+                    .get::<bool>(SESSION_KEY_TOTP_VERIFIED)?
+                    .unwrap_or(false);
+                Authentication::Session { totp_verified }
+            } else {
+                Authentication::Proxy
+            };
+            get_request_user(req.request(), &session, &db, &system_settings)?
+                .map(|user_info| (user_info, authentication))
+        }
+    };
+    match authenticated {
+        Some((user_info, authentication)) => {
+            req.extensions_mut().insert(user_info);
+            req.extensions_mut().insert(authentication);
+            next.call(req).await
+        }
         None => {
             warn!("Anonymous user tried to access protected resource.");
-            Err( actix_web::error::ErrorUnauthorized("User is not logged in."))
+            Err(actix_web::error::ErrorUnauthorized(
+                "User is not logged in.",
+            ))
         }
     }
-}
\ No newline at end of file
+}
+
+/// Reject users whose role is lower than the required role, and api tokens without the required
+/// scope. Must be wrapped inside `reject_anonymous_users`.
+async fn check_user_role<B: MessageBody>(
+    required_role: UserRole,
+    required_scope: TokenScope,
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<B>, actix_web::Error> {
+    let role = req
+        .extensions()
+        .get::<UserInfo>()
+        .map(|user_info| user_info.role);
+    let has_scope = req
+        .extensions()
+        .get::<Authentication>()
+        .is_some_and(|authentication| authentication.has_scope(required_scope));
+    match role {
+        Some(role) if role >= required_role && has_scope => next.call(req).await,
+        Some(role) => {
+            warn!(
+                "User with role {} tried to access {} which requires role {} and scope {:?}",
+                role,
+                req.path(),
+                required_role,
+                required_scope
+            );
+            Err(actix_web::error::ErrorForbidden("Permission denied."))
+        }
+        None => Err(actix_web::error::ErrorUnauthorized(
+            "User is not logged in.",
+        )),
+    }
+}
+
+/// Middleware to reject api tokens without read scope, every user role can read
+pub async fn require_read(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    check_user_role(UserRole::Viewer, TokenScope::Read, req, next).await
+}
+
+/// Middleware to reject users below operator role and api tokens without scan scope
+pub async fn require_scan(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    check_user_role(UserRole::Operator, TokenScope::Scan, req, next).await
+}
+
+/// Middleware to reject users below operator role and api tokens without trash scope
+pub async fn require_trash(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    check_user_role(UserRole::Operator, TokenScope::Trash, req, next).await
+}
+
+/// Middleware to reject users below admin role and api tokens without delete scope, and logins
+/// without second factor if `totp_required_for_delete` is set
+pub async fn require_delete(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let Some(settings) = req.app_data::<web::Data<SharedSettings>>().cloned() else {
+        return Err(actix_web::error::ErrorInternalServerError(
+            "Settings are not available.",
+        ));
+    };
+    let totp_required = settings.lock().await.system.totp_required_for_delete;
+    let totp_verified = req
+        .extensions()
+        .get::<Authentication>()
+        .is_some_and(|authentication| authentication.is_totp_verified());
+    if totp_required && !totp_verified {
+        warn!(
+            "Login without two-factor authentication tried to access {}",
+            req.path()
+        );
+        return Err(actix_web::error::ErrorForbidden(
+            "Two-factor authentication is required to delete files permanently.",
+        ));
+    }
+    check_user_role(UserRole::Admin, TokenScope::Delete, req, next).await
+}
+
+/// Middleware to reject users below admin role and api tokens without admin scope
+pub async fn require_admin(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    check_user_role(UserRole::Admin, TokenScope::Admin, req, next).await
+}
+
+/// Logged in user stored by `reject_anonymous_users`
+impl FromRequest for UserInfo {
+    type Error = AWError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<UserInfo>()
+                .cloned()
+                .ok_or_else(|| actix_web::error::ErrorUnauthorized("User is not logged in.")),
+        )
+    }
+}
+
+/// Reject the path if it is not under the allowed paths of the user
+pub fn check_path_allowed(user_info: &UserInfo, path: &Path) -> Result<(), DfrError> {
+    if !user_info.is_path_allowed(path) {
+        warn!(
+            "User {} tried to access {:?} which is not in allowed paths",
+            user_info.user_name, path
+        );
+        return DfrError::custom_error(
+            ErrorCode::PERMISSION_DENIED,
+            format!(
+                "User {} is not allowed to access {}",
+                user_info.user_name,
+                path.display()
+            ),
+        );
+    }
+    Ok(())
+}
+
+/// Allowed paths must be absolute paths without `..`
+fn check_allowed_paths(allowed_paths: &[String]) -> Result<(), DfrError> {
+    for allowed_path in allowed_paths.iter() {
+        let path = Path::new(allowed_path);
+        if !path.is_absolute()
+            || path
+                .components()
+                .any(|component| component == Component::ParentDir)
+        {
+            return DfrError::custom_error(
+                ErrorCode::SYSTEM_ERROR,
+                format!("Allowed path {} must be an absolute path", allowed_path),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Create the first admin user from user settings if there is no user yet
+pub fn init_admin_user(
+    db: &PoolDatabaseManager,
+    user_settings: &UserSettings,
+) -> Result<(), DfrError> {
+    if db.count_users(None)? > 0 {
+        return Ok(());
+    }
+    info!("Create admin user {}", user_settings.login_user_name);
+    let now = Local::now();
+    db.insert_user(&UserInfo {
+        user_name: user_settings.login_user_name.clone(),
+        password_hash: user_settings.login_password_hash.clone(),
+        role: UserRole::Admin,
+        allowed_paths: Vec::new(),
+        created_time: now,
+        modified_time: now,
+    })
+}
+
+/// Make sure there is still an admin after the user is demoted or deleted
+fn check_last_admin(db: &PoolDatabaseManager, user_info: &UserInfo) -> Result<(), DfrError> {
+    if user_info.role == UserRole::Admin && db.count_users(Some(UserRole::Admin))? <= 1 {
+        return DfrError::custom_error(
+            ErrorCode::NOT_ALLOW_CHANGE_USER,
+            format!("User {} is the last admin", user_info.user_name),
+        );
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    summary = "List users",
+    responses(
+        (status = 200, description = "The list of users", body = UserInfoList),
+        (status = 403, description = "Permission denied"),
+    ),
+)]
+#[get("/user/list", wrap = "from_fn(require_admin)")]
+pub async fn list_users(db: web::Data<PoolDatabaseManager>) -> Result<HttpResponse, DfrError> {
+    let user_info_list = db.list_users()?;
+    Ok(HttpResponse::Ok().json(user_info_list))
+}
+
+#[utoipa::path(
+    summary = "Add user",
+    request_body(content = AddUserRequest),
+    responses(
+        (status = 200, description = "Add user successfully"),
+        (status = 403, description = "Permission denied"),
+    ),
+)]
+#[post("/user", wrap = "from_fn(require_admin)")]
+pub async fn add_user(
+    requst_json: web::Json<AddUserRequest>,
+    db: web::Data<PoolDatabaseManager>,
+    audit_context: AuditContext,
+) -> Result<HttpResponse, DfrError> {
+    let add_user_request = requst_json.into_inner();
+    let result = _add_user(&add_user_request, db.get_ref()).await;
+    record_audit_log(
+        db.get_ref(),
+        &audit_context,
+        AuditAction::AddUser,
+        AuditTarget::name(add_user_request.user_name.as_str()),
+        &result,
+    );
+    result?;
+    info!(
+        "Add user {} with role {} successfully",
+        add_user_request.user_name, add_user_request.role
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn _add_user(
+    add_user_request: &AddUserRequest,
+    db: &PoolDatabaseManager,
+) -> Result<(), DfrError> {
+    if add_user_request.user_name.is_empty() || add_user_request.password.is_empty() {
+        return DfrError::custom_error(
+            ErrorCode::SYSTEM_ERROR,
+            String::from("User name or password is empty"),
+        );
+    }
+    if db
+        .get_user_by_name(add_user_request.user_name.as_str())?
+        .is_some()
+    {
+        return DfrError::custom_error(
+            ErrorCode::USER_ALREADY_EXISTS,
+            format!("User {} already exists", add_user_request.user_name),
+        );
+    }
+    let allowed_paths = add_user_request.allowed_paths.clone().unwrap_or_default();
+    check_allowed_paths(&allowed_paths)?;
+    let now = Local::now();
+    db.insert_user(&UserInfo {
+        user_name: add_user_request.user_name.clone(),
+        password_hash: new_password_hash(add_user_request.password.clone()).await?,
+        role: add_user_request.role,
+        allowed_paths,
+        created_time: now,
+        modified_time: now,
+    })
+}
+
+#[utoipa::path(
+    summary = "Update password or role of a user",
+    request_body(content = UpdateUserRequest),
+    responses(
+        (status = 200, description = "Update user successfully"),
+        (status = 403, description = "Permission denied"),
+    ),
+)]
+#[put("/user", wrap = "from_fn(require_admin)")]
+pub async fn update_user(
+    requst_json: web::Json<UpdateUserRequest>,
+    db: web::Data<PoolDatabaseManager>,
+    audit_context: AuditContext,
+) -> Result<HttpResponse, DfrError> {
+    let update_user_request = requst_json.into_inner();
+    let result = _update_user(&update_user_request, db.get_ref()).await;
+    record_audit_log(
+        db.get_ref(),
+        &audit_context,
+        AuditAction::UpdateUser,
+        AuditTarget::name(update_user_request.user_name.as_str()),
+        &result,
+    );
+    result?;
+    info!("Update user {} successfully", update_user_request.user_name);
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn _update_user(
+    update_user_request: &UpdateUserRequest,
+    db: &PoolDatabaseManager,
+) -> Result<(), DfrError> {
+    let Some(mut user_info) = db.get_user_by_name(update_user_request.user_name.as_str())? else {
+        return DfrError::custom_error(
+            ErrorCode::USER_NOT_FOUND,
+            format!("User {} not found", update_user_request.user_name),
+        );
+    };
+    if let Some(role) = update_user_request.role {
+        if role != user_info.role {
+            check_last_admin(db, &user_info)?;
+            user_info.role = role;
+        }
+    }
+    if let Some(allowed_paths) = update_user_request.allowed_paths.clone() {
+        check_allowed_paths(&allowed_paths)?;
+        user_info.allowed_paths = allowed_paths;
+    }
+    if let Some(password) = update_user_request.password.clone() {
+        if !password.is_empty() {
+            user_info.password_hash = new_password_hash(password).await?;
+        }
+    }
+    user_info.modified_time = Local::now();
+    db.update_user(update_user_request.user_name.as_str(), &user_info)?;
+    if update_user_request.reset_totp.unwrap_or(false) {
+        warn!("Reset TOTP of user {}", user_info.user_name);
+        db.remove_user_totp(user_info.user_name.as_str())?;
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    summary = "Delete user",
+    request_body(content = DeleteUserRequest),
+    responses(
+        (status = 200, description = "Delete user successfully"),
+        (status = 403, description = "Permission denied"),
+    ),
+)]
+#[delete("/user", wrap = "from_fn(require_admin)")]
+pub async fn delete_user(
+    requst_json: web::Json<DeleteUserRequest>,
+    db: web::Data<PoolDatabaseManager>,
+    current_user: UserInfo,
+    audit_context: AuditContext,
+) -> Result<HttpResponse, DfrError> {
+    let delete_user_request = requst_json.into_inner();
+    let result = _delete_user(&delete_user_request, db.get_ref(), &current_user);
+    record_audit_log(
+        db.get_ref(),
+        &audit_context,
+        AuditAction::DeleteUser,
+        AuditTarget::name(delete_user_request.user_name.as_str()),
+        &result,
+    );
+    result?;
+    info!("Delete user {} successfully", delete_user_request.user_name);
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn _delete_user(
+    delete_user_request: &DeleteUserRequest,
+    db: &PoolDatabaseManager,
+    current_user: &UserInfo,
+) -> Result<(), DfrError> {
+    if delete_user_request.user_name == current_user.user_name {
+        return DfrError::custom_error(
+            ErrorCode::NOT_ALLOW_CHANGE_USER,
+            String::from("Not allow to delete current user"),
+        );
+    }
+    let Some(user_info) = db.get_user_by_name(delete_user_request.user_name.as_str())? else {
+        return DfrError::custom_error(
+            ErrorCode::USER_NOT_FOUND,
+            format!("User {} not found", delete_user_request.user_name),
+        );
+    };
+    check_last_admin(db, &user_info)?;
+    db.remove_user(user_info.user_name.as_str())?;
+    Ok(())
+}
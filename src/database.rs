@@ -1,2 +1,11 @@
 pub mod sqlite;
-pub mod file_info;
\ No newline at end of file
+pub mod file_info;
+pub mod audit_log;
+pub mod user_info;
+pub mod api_token;
+pub mod user_totp;
+pub mod scan_history;
+pub mod file_stats;
+pub mod dir_tree;
+pub mod image_hash;
+pub mod audio_fingerprint;
\ No newline at end of file
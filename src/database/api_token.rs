@@ -0,0 +1,63 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Scope of an API token, a token can only access the routes covered by its scopes and allowed by
+/// the role of its user
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+    /// List files, trash files and query settings
+    Read,
+    /// Start and stop scan
+    Scan,
+    /// Move files to trash and restore trash files
+    Trash,
+    /// Delete files and trash files permanently, check trash files and remove orphan ones
+    Delete,
+    /// Manage system settings, users and audit logs
+    Admin,
+}
+
+/// API token of a user, only the hash of the token is stored
+#[derive(Debug, Serialize, ToSchema, Clone)]
+pub struct ApiToken {
+    /// Token id
+    pub id: i64,
+    /// User the token belongs to
+    pub user_name: String,
+    /// Token name, to tell the tokens apart
+    pub name: String,
+    /// SHA-256 hash of the token, never serialized
+    #[serde(skip)]
+    pub token_hash: String,
+    /// Token scopes
+    pub scopes: Vec<TokenScope>,
+    /// Created time
+    pub created_time: DateTime<Local>,
+    /// Expire time, none if the token never expires
+    pub expire_time: Option<DateTime<Local>>,
+    /// Last time the token was used
+    pub last_used_time: Option<DateTime<Local>>,
+    /// Whether the token is revoked
+    pub revoked: bool,
+}
+
+impl ApiToken {
+    /// Check if the token is neither revoked nor expired
+    pub fn is_valid(&self, now: &DateTime<Local>) -> bool {
+        !self.revoked
+            && self
+                .expire_time
+                .is_none_or(|expire_time| expire_time > *now)
+    }
+}
+
+/// API token list
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiTokenList {
+    /// API token list
+    pub api_token_list: Vec<ApiToken>,
+    /// Total API token count
+    pub total_count: u64,
+}
@@ -0,0 +1,25 @@
+/// Fingerprint and quality of an audio track, shared by the files with the same md5
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioFingerprint {
+    pub md5: String,
+    pub duration_s: f64,
+    /// Short name of the codec, e.g. `mp3`, `flac` or `pcm_s16le`
+    pub codec: String,
+    /// Average bits per second of the file
+    pub bitrate: u64,
+    pub sample_rate: u32,
+    pub channels: u32,
+    /// Bits per sample of lossless codecs
+    pub bit_depth: Option<u32>,
+    /// Chroma sub-fingerprints of the start of the track, one per frame of about 1/8 second
+    pub fingerprint: Vec<u32>,
+}
+
+/// Indexed audio file with the fingerprint of its content
+#[derive(Debug, Clone)]
+pub struct AudioFingerprintFile {
+    pub audio_fingerprint: AudioFingerprint,
+    pub dir_path: String,
+    pub file_name: String,
+    pub size: u64,
+}
@@ -0,0 +1,149 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{DateTime, Local};
+use rusqlite::{
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+    ToSql,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Destructive action recorded in audit log
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// File is deleted permanently
+    DeleteFile,
+    /// File is moved to trash
+    MoveToTrash,
+    /// Trash file is restored
+    RestoreTrashFile,
+    /// Trash file is deleted by user
+    DeleteTrashFile,
+    /// Trash file is deleted by the clear trash timer
+    PurgeTrashFile,
+    /// Trash file without trash info is deleted by trash check
+    RemoveOrphanTrashFile,
+    /// System settings are changed
+    UpdateSettings,
+    /// Username or password is changed
+    ChangePassword,
+    /// User is added
+    AddUser,
+    /// User password or role is changed by admin
+    UpdateUser,
+    /// User is deleted
+    DeleteUser,
+    /// Api token is created
+    CreateApiToken,
+    /// Api token is revoked
+    RevokeApiToken,
+    /// User logs in, failed attempts are recorded as unsuccessful
+    Login,
+    /// User enables TOTP
+    EnableTotp,
+    /// User disables TOTP
+    DisableTotp,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::DeleteFile => "delete_file",
+            AuditAction::MoveToTrash => "move_to_trash",
+            AuditAction::RestoreTrashFile => "restore_trash_file",
+            AuditAction::DeleteTrashFile => "delete_trash_file",
+            AuditAction::PurgeTrashFile => "purge_trash_file",
+            AuditAction::RemoveOrphanTrashFile => "remove_orphan_trash_file",
+            AuditAction::UpdateSettings => "update_settings",
+            AuditAction::ChangePassword => "change_password",
+            AuditAction::AddUser => "add_user",
+            AuditAction::UpdateUser => "update_user",
+            AuditAction::DeleteUser => "delete_user",
+            AuditAction::CreateApiToken => "create_api_token",
+            AuditAction::RevokeApiToken => "revoke_api_token",
+            AuditAction::Login => "login",
+            AuditAction::EnableTotp => "enable_totp",
+            AuditAction::DisableTotp => "disable_totp",
+        }
+    }
+}
+
+impl Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AuditAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "delete_file" => Ok(AuditAction::DeleteFile),
+            "move_to_trash" => Ok(AuditAction::MoveToTrash),
+            "restore_trash_file" => Ok(AuditAction::RestoreTrashFile),
+            "delete_trash_file" => Ok(AuditAction::DeleteTrashFile),
+            "purge_trash_file" => Ok(AuditAction::PurgeTrashFile),
+            "remove_orphan_trash_file" => Ok(AuditAction::RemoveOrphanTrashFile),
+            "update_settings" => Ok(AuditAction::UpdateSettings),
+            "change_password" => Ok(AuditAction::ChangePassword),
+            "add_user" => Ok(AuditAction::AddUser),
+            "update_user" => Ok(AuditAction::UpdateUser),
+            "delete_user" => Ok(AuditAction::DeleteUser),
+            "create_api_token" => Ok(AuditAction::CreateApiToken),
+            "revoke_api_token" => Ok(AuditAction::RevokeApiToken),
+            "login" => Ok(AuditAction::Login),
+            "enable_totp" => Ok(AuditAction::EnableTotp),
+            "disable_totp" => Ok(AuditAction::DisableTotp),
+            _ => Err(format!("Unknown audit action: {}", s)),
+        }
+    }
+}
+
+impl ToSql for AuditAction {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(self.as_str().into())
+    }
+}
+
+impl FromSql for AuditAction {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
+
+/// Audit log of a destructive action
+#[derive(Debug, Serialize, ToSchema, Clone)]
+pub struct AuditLog {
+    /// Time of the action
+    pub time: DateTime<Local>,
+    /// User who performed the action, none for actions performed by the system
+    pub user_name: Option<String>,
+    /// Client ip of the request
+    pub client_ip: Option<String>,
+    /// Action
+    pub action: AuditAction,
+    /// File path the action is performed on, or user / api token name for user / api token actions
+    pub path: Option<String>,
+    /// File md5
+    pub md5: Option<String>,
+    /// File size
+    pub size: Option<u64>,
+    /// Whether the action succeeded
+    pub success: bool,
+    /// Error message if the action failed
+    pub message: Option<String>,
+}
+
+/// Audit log list with total count
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuditLogList {
+    /// Audit log list
+    pub audit_log_list: Vec<AuditLog>,
+    /// Total audit log count
+    pub total_count: u64,
+}
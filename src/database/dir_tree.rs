@@ -0,0 +1,77 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Indexed and duplicate files of a directory and its subdirectories
+#[derive(Debug, Serialize, ToSchema, Clone, Default, PartialEq)]
+pub struct DirTreeEntry {
+    /// Name of the directory, empty for the files directly in a directory
+    pub name: String,
+    pub dir_path: String,
+    /// Indexed file paths, hard links are counted for each path
+    pub file_count: u64,
+    pub size: u64,
+    /// Files having the same md5 as another file
+    pub duplicate_file_count: u64,
+    pub duplicate_size: u64,
+}
+
+impl DirTreeEntry {
+    fn add(&mut self, other: &DirTreeEntry) {
+        self.file_count += other.file_count;
+        self.size += other.size;
+        self.duplicate_file_count += other.duplicate_file_count;
+        self.duplicate_size += other.duplicate_size;
+    }
+}
+
+/// One level of the indexed directory tree, only files in or under the allowed paths are
+/// counted if allowed paths is not empty
+#[derive(Debug, Serialize, ToSchema, Clone, Default)]
+pub struct DirTree {
+    /// None for the root directory
+    pub parent_dir_path: Option<String>,
+    /// The directory with its subdirectories
+    pub total: DirTreeEntry,
+    /// Files directly in the directory
+    pub files: DirTreeEntry,
+    /// Subdirectories with the most duplicate bytes first
+    pub sub_dirs: Vec<DirTreeEntry>,
+}
+
+impl DirTree {
+    pub fn new(
+        dir_path: &str,
+        parent_dir_path: Option<String>,
+        entries: Vec<DirTreeEntry>,
+    ) -> Self {
+        let name = dir_path.rsplit('/').next().unwrap_or_default().to_string();
+        let mut dir_tree = DirTree {
+            parent_dir_path,
+            total: DirTreeEntry {
+                name,
+                dir_path: dir_path.to_string(),
+                ..Default::default()
+            },
+            files: DirTreeEntry {
+                dir_path: dir_path.to_string(),
+                ..Default::default()
+            },
+            sub_dirs: Vec::new(),
+        };
+        for entry in entries {
+            dir_tree.total.add(&entry);
+            if entry.name.is_empty() {
+                dir_tree.files = entry;
+            } else {
+                dir_tree.sub_dirs.push(entry);
+            }
+        }
+        dir_tree.sub_dirs.sort_by(|a, b| {
+            b.duplicate_size
+                .cmp(&a.duplicate_size)
+                .then_with(|| b.size.cmp(&a.size))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        dir_tree
+    }
+}
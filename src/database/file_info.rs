@@ -1,7 +1,7 @@
 use std::{os::linux::fs::MetadataExt, path::PathBuf};
 
 use chrono::{DateTime, Local};
-use log::debug;
+use log::{debug, warn};
 use md5::{Digest, Md5};
 use serde::Serialize;
 use tokio::{
@@ -10,9 +10,28 @@ use tokio::{
 };
 use utoipa::ToSchema;
 
-use crate::utils::error::DfrError;
+use crate::{model::settings::FileHashType, utils::error::DfrError};
 
 use super::sqlite::FileInfoDO;
+/// Calculate md5 of file content, return md5 and total size
+pub async fn file_md5(file_path: &str) -> Result<(String, usize), DfrError> {
+    let file = File::open(file_path).await?;
+    let mut hasher = Md5::new();
+    let mut reader = BufReader::new(file);
+    let mut buffer = [0; 65536];
+    let mut file_sizes = 0;
+    loop {
+        let n = reader.read(&mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+        file_sizes += n;
+        hasher.update(&buffer[..n]);
+    }
+    let hash = hasher.finalize();
+    Ok((format!("{:x}", hash), file_sizes))
+}
+
 /// Inode info
 #[derive(Debug, Serialize, Clone, ToSchema)]
 pub struct InodeInfo {
@@ -28,12 +47,25 @@ pub struct InodeInfo {
     pub modified: DateTime<Local>,
     /// File md5
     pub md5: Option<String>,
+    /// Md5 of the file content without metadata, only computed for supported media files if
+    /// payload hash is enabled in the scan
+    pub payload_md5: Option<String>,
     /// File size
     pub size: u64,
 }
 
+impl InodeInfo {
+    /// Hash which files are grouped as duplicates by, the payload md5 falls back to the md5
+    pub fn group_md5(&self, hash_type: FileHashType) -> Option<&str> {
+        match hash_type {
+            FileHashType::Md5 => self.md5.as_deref(),
+            FileHashType::Payload => self.payload_md5.as_deref().or(self.md5.as_deref()),
+        }
+    }
+}
+
 /// Implement PartialEq for InodeInfo to compare two instances based on their fields.
-/// This comparison ignores the md5 field.
+/// This comparison ignores the md5 and payload md5 fields.
 impl PartialEq<InodeInfo> for InodeInfo {
     fn eq(&self, other: &Self) -> bool {
         self.inode == other.inode
@@ -65,6 +97,9 @@ pub struct FileInfo {
     pub version: u64,
     /// scan_time is the time when the file was last scanned
     pub scan_time: DateTime<Local>,
+    /// Path of the archive if the file is a member of it, e.g. `/data/backup.zip` of
+    /// `/data/backup.zip!/dir/file`. Archive members are never deleted.
+    pub archive_path: Option<String>,
 }
 
 impl FileInfo {
@@ -97,6 +132,7 @@ impl FileInfo {
             created,
             modified,
             md5: None, // Initialize MD5 as None
+            payload_md5: None,
             size: metadata.len(),
         };
         Ok(Self {
@@ -107,27 +143,55 @@ impl FileInfo {
             file_extension,
             scan_time,
             version,
+            archive_path: None,
         })
     }
+
+    /// File info of an archive member, with the path `<archive path>!/<member path>`. Members
+    /// have no inode, their inode info is of the archive with device ID 0, and the inode number is
+    /// assigned when they are inserted.
+    pub fn archive_member(
+        archive: &FileInfo,
+        member_path: &str,
+        md5: String,
+        size: u64,
+        modified: Option<DateTime<Local>>,
+    ) -> Self {
+        let file_path = format!("{}!/{}", archive.file_path, member_path);
+        let (dir_path, file_name) = file_path.rsplit_once('/').unwrap();
+        let (dir_path, file_name) = (dir_path.to_string(), file_name.to_string());
+        let file_extension = file_name
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_lowercase());
+        let modified = modified.unwrap_or(archive.inode_info.modified);
+        let inode_info = InodeInfo {
+            inode: 0,
+            dev_id: 0,
+            nlink: 1,
+            created: modified,
+            modified,
+            md5: Some(md5),
+            payload_md5: None,
+            size,
+            ..archive.inode_info.clone()
+        };
+        Self {
+            inode_info,
+            dir_path,
+            file_name,
+            file_path,
+            file_extension,
+            version: archive.version,
+            scan_time: archive.scan_time,
+            archive_path: Some(archive.file_path.clone()),
+        }
+    }
     /// update file hash async
     pub async fn update_md5(&mut self) -> Result<(), DfrError> {
         let file_path = format!("{}/{}", self.dir_path, self.file_name);
         debug!("begin update md5: {}/{}", self.file_path, self.file_name);
 
-        let file = File::open(file_path).await?;
-        let mut hasher = Md5::new();
-        let mut reader = BufReader::new(file);
-        let mut buffer = [0; 65536];
-        let mut file_sizes = 0;
-        while let Ok(n) = reader.read(&mut buffer).await {
-            if n == 0 {
-                break;
-            }
-            file_sizes += n;
-            hasher.update(&buffer[..n]);
-        }
-        let hash = hasher.finalize();
-        let hash_str = format!("{:x}", hash);
+        let (hash_str, file_sizes) = file_md5(file_path.as_str()).await?;
         debug!(
             "{}/{}(total size: {}) md5: {}",
             self.file_path, self.file_name, file_sizes, hash_str,
@@ -146,10 +210,70 @@ impl FileInfo {
             file_extension: file_info_do.file_extension,
             scan_time: file_info_do.scan_time,
             version: file_info_do.version,
+            archive_path: file_info_do.archive_path,
+        }
+    }
+}
+
+/// Extended attribute of a file
+#[derive(Debug, Clone)]
+pub struct ExtendedAttribute {
+    /// Attribute name, e.g. `user.tags`
+    pub name: String,
+    /// Raw attribute value
+    pub value: Vec<u8>,
+}
+
+/// File attributes which are not tracked by inode info, but need to be kept when a file is moved to trash
+#[derive(Debug, Clone, Default)]
+pub struct FileAttributes {
+    /// Last access time
+    pub accessed: Option<DateTime<Local>>,
+    /// Extended attributes. POSIX ACLs are included as `system.posix_acl_access` and `system.posix_acl_default`
+    pub xattrs: Vec<ExtendedAttribute>,
+}
+
+impl FileAttributes {
+    /// read access time and extended attributes of file
+    pub fn read(file_path: &str) -> Result<Self, DfrError> {
+        let metadata = std::fs::metadata(file_path)?;
+        let accessed = Some(DateTime::<Local>::from(metadata.accessed()?));
+        let mut xattrs = Vec::new();
+        let names = match xattr::list(file_path) {
+            Ok(names) => names,
+            Err(err) => {
+                // file system may not support extended attributes
+                warn!(
+                    "Failed to list extended attributes of {}: {:?}",
+                    file_path, err
+                );
+                return Ok(Self { accessed, xattrs });
+            }
+        };
+        for name in names {
+            let name = name.to_string_lossy().to_string();
+            match xattr::get(file_path, name.as_str()) {
+                Ok(Some(value)) => xattrs.push(ExtendedAttribute { name, value }),
+                Ok(None) => {}
+                Err(err) => warn!(
+                    "Failed to read extended attribute {} of {}: {:?}",
+                    name, file_path, err
+                ),
+            }
         }
+        Ok(Self { accessed, xattrs })
     }
 }
 
+/// Where a file is stored after it is moved to trash
+#[derive(Debug, Clone, Default)]
+pub struct TrashStorage {
+    /// Size of the compressed trash file, none if the trash file is not compressed
+    pub compressed_size: Option<u64>,
+    /// Path of the file in freedesktop.org trash, none if the file is stored in trash path by md5
+    pub xdg_trash_path: Option<String>,
+}
+
 /// File info with md5 count
 #[derive(Debug, Serialize, ToSchema)]
 pub struct FileInfoWithMd5Count {
@@ -161,6 +285,15 @@ pub struct FileInfoWithMd5Count {
     pub filter_md5_count: Option<usize>,
 }
 
+/// Path and content hash of a file, used to compare directories
+#[derive(Debug, Clone)]
+pub struct FileHash {
+    pub dir_path: String,
+    pub file_name: String,
+    pub md5: String,
+    pub size: u64,
+}
+
 /// File info list with total count
 #[derive(Debug, Serialize, ToSchema)]
 pub struct FileInfoList {
@@ -188,10 +321,20 @@ pub struct TrashFileInfo {
     pub created: DateTime<Local>,
     /// Modified time
     pub modified: DateTime<Local>,
+    /// Accessed time, none if the file was moved to trash by an older version
+    pub accessed: Option<DateTime<Local>>,
     /// File md5
     pub md5: String,
     /// File size
     pub size: u64,
+    /// Size of the compressed trash file, none if the trash file is not compressed
+    pub compressed_size: Option<u64>,
+    /// Path of the file in freedesktop.org trash, none if the file is stored in trash path by md5
+    pub xdg_trash_path: Option<String>,
+    /// Trash file was not found by the last trash check
+    pub blob_missing: bool,
+    /// Trash file content did not match md5 in the last trash check
+    pub blob_corrupted: bool,
 }
 
 impl TrashFileInfo {
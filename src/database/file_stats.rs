@@ -0,0 +1,51 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::scan_history::ScanHistory;
+
+/// Indexed and duplicate files of an extension, size range or directory
+#[derive(Debug, Serialize, ToSchema, Clone, Default)]
+pub struct StatsBucket {
+    pub name: String,
+    pub file_count: u64,
+    pub size: u64,
+    /// Files having the same md5 as another file
+    pub duplicate_file_count: u64,
+    pub duplicate_size: u64,
+}
+
+/// Files moved to trash
+#[derive(Debug, Serialize, ToSchema, Clone, Default)]
+pub struct TrashStats {
+    pub file_count: u64,
+    /// Original size of the files
+    pub size: u64,
+}
+
+/// Statistics of the indexed files, only files in or under the allowed paths are counted if
+/// allowed paths is not empty
+#[derive(Debug, Serialize, ToSchema, Clone, Default)]
+pub struct FileStats {
+    /// Version of the last scan the statistics are computed after, 0 if no scan is recorded
+    pub scan_version: u64,
+    /// Indexed file paths, hard links are counted for each path
+    pub total_file_count: u64,
+    /// Size of the indexed files, hard links are counted once
+    pub total_size: u64,
+    /// Count of md5 shared by more than one file
+    pub duplicate_group_count: u64,
+    /// Files of the duplicate groups, hard links are counted once
+    pub duplicate_file_count: u64,
+    /// Bytes freed if only one file of each duplicate group is kept
+    pub redundant_size: u64,
+    /// Extensions with the most duplicate bytes
+    pub extensions: Vec<StatsBucket>,
+    /// Files by size range, from small to large
+    pub size_buckets: Vec<StatsBucket>,
+    /// Directories with the most duplicate bytes
+    pub top_directories: Vec<StatsBucket>,
+    /// Read for each request, as files are moved to trash between scans
+    pub trash: TrashStats,
+    pub last_scan: Option<ScanHistory>,
+    pub last_scan_duration_s: Option<i64>,
+}
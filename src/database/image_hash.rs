@@ -0,0 +1,21 @@
+/// Perceptual hashes of an image, shared by the files with the same md5
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageHash {
+    pub md5: String,
+    /// Difference hash, whether each pixel is darker than its right neighbour in a 9x8 thumbnail
+    pub dhash: u64,
+    /// Perceptual hash, whether each low frequency of the DCT of a 32x32 thumbnail is above the
+    /// median
+    pub phash: u64,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Indexed image file with the perceptual hashes of its content
+#[derive(Debug, Clone)]
+pub struct ImageHashFile {
+    pub image_hash: ImageHash,
+    pub dir_path: String,
+    pub file_name: String,
+    pub size: u64,
+}
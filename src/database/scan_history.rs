@@ -0,0 +1,22 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Completed or stopped scan of a path
+#[derive(Debug, Serialize, ToSchema, Clone)]
+pub struct ScanHistory {
+    /// Scan version, the start time in seconds since epoch, stored with the scanned files
+    pub version: u64,
+    pub scan_path: String,
+    pub start_time: DateTime<Local>,
+    pub end_time: DateTime<Local>,
+    pub scanned_file_count: u64,
+    /// False if the scan failed or was stopped
+    pub success: bool,
+}
+
+impl ScanHistory {
+    pub fn duration_s(&self) -> i64 {
+        (self.end_time - self.start_time).num_seconds()
+    }
+}
@@ -1,17 +1,42 @@
-use std::{fs, ops::Deref, path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    fs,
+    ops::Deref,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 
 use chrono::{DateTime, Local};
 use log::{debug, error, info};
-use rusqlite::{params_from_iter, Connection, Params, Result, ToSql};
+use rusqlite::{params_from_iter, types::Type, Connection, Params, Result, Row, ToSql};
 
 use crate::{
-    database::file_info::TrashFileInfo,
-    model::settings::{ListSettings, TrashListSettings},
-    utils::{self, error::DfrError},
+    database::{
+        api_token::{ApiToken, ApiTokenList},
+        audio_fingerprint::{AudioFingerprint, AudioFingerprintFile},
+        audit_log::{AuditLog, AuditLogList},
+        dir_tree::{DirTree, DirTreeEntry},
+        file_info::TrashFileInfo,
+        file_stats::{FileStats, StatsBucket, TrashStats},
+        image_hash::{ImageHash, ImageHashFile},
+        scan_history::ScanHistory,
+        user_info::{UserInfo, UserInfoList, UserRole},
+        user_totp::UserTotp,
+    },
+    model::{
+        audit::AuditLogListParams,
+        settings::{FileHashType, ListSettings, TrashListSettings},
+    },
+    utils::{
+        self,
+        duplicate_dir::{join_path, parent_dir},
+        error::DfrError,
+    },
 };
 
 use super::file_info::{
-    FileInfo, FileInfoList, FileInfoWithMd5Count, InodeInfo, TrashFileInfoList,
+    ExtendedAttribute, FileAttributes, FileHash, FileInfo, FileInfoList, FileInfoWithMd5Count,
+    InodeInfo, TrashFileInfoList, TrashStorage,
 };
 use r2d2_sqlite::SqliteConnectionManager;
 pub type Pool = r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>;
@@ -23,6 +48,7 @@ pub struct FileInfoDO {
     pub file_extension: Option<String>,
     pub scan_time: DateTime<Local>,
     pub version: u64,
+    pub archive_path: Option<String>,
 }
 #[derive(Debug)]
 pub struct InodeInfoDO {
@@ -30,8 +56,39 @@ pub struct InodeInfoDO {
     pub id: i64,
 }
 
+/// Columns of user_info, in the order read by `DatabaseManager::user_info_from_row`
+const USER_INFO_COLUMNS: &str =
+    "user_name, password_hash, role, created_time, modified_time, allowed_paths";
+
+/// Columns of api_token, in the order read by `DatabaseManager::api_token_from_row`
+const API_TOKEN_COLUMNS: &str =
+    "id, user_name, name, token_hash, scopes, created_time, expire_time, last_used_time, revoked";
+
+/// Columns of user_totp, in the order read by `DatabaseManager::user_totp_from_row`
+const USER_TOTP_COLUMNS: &str = "user_name, secret, recovery_code_hashes, last_step, created_time";
+
+/// Columns of scan_history, in the order read by `DatabaseManager::get_last_scan_history`
+const SCAN_HISTORY_COLUMNS: &str =
+    "version, scan_path, start_time, end_time, scanned_file_count, success";
+
+/// Count of extensions and directories in file statistics
+const STATS_TOP_COUNT: i64 = 20;
+
+/// Size range of a file in file statistics
+const STATS_SIZE_BUCKET_SQL: &str = "CASE
+    WHEN a1.size < 1024 THEN '< 1 KiB'
+    WHEN a1.size < 1048576 THEN '1 KiB - 1 MiB'
+    WHEN a1.size < 104857600 THEN '1 MiB - 100 MiB'
+    WHEN a1.size < 1073741824 THEN '100 MiB - 1 GiB'
+    ELSE '>= 1 GiB' END";
+
+/// Columns of trash_info, in the order read by `DatabaseManager::trash_file_info_from_row`
+const TRASH_INFO_COLUMNS: &str = "dir_path, file_name, file_extension, remove_time, permissions, uid, gid, created, modified, md5, size, accessed, blob_missing, blob_corrupted, compressed_size, xdg_trash_path";
+
 pub struct DatabaseManager {
     pool: Pool,
+    /// File statistics of the last scan by allowed paths
+    stats_cache: Mutex<HashMap<Vec<String>, FileStats>>,
 }
 
 pub struct PoolDatabaseManager(pub Arc<DatabaseManager>);
@@ -77,7 +134,10 @@ impl DatabaseManager {
         });
         let pool = Pool::new(manager)?;
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            stats_cache: Mutex::new(HashMap::new()),
+        })
     }
 
     pub fn create_tables(&self) -> Result<(), DfrError> {
@@ -135,15 +195,193 @@ impl DatabaseManager {
         );
         CREATE INDEX IF NOT EXISTS idx_trash_info_file_name ON trash_info (file_name);
         CREATE INDEX IF NOT EXISTS idx_trash_info_md5 ON trash_info (md5);
+
+        CREATE TABLE IF NOT EXISTS trash_xattr_info (
+            dir_path TEXT NOT NULL,
+            file_name TEXT NOT NULL,
+            name TEXT NOT NULL,
+            value BLOB NOT NULL,
+            UNIQUE(dir_path, file_name, name)
+        );
+
+        CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            time DATETIME DEFAULT CURRENT_TIMESTAMP,
+            user_name TEXT NULL,
+            client_ip TEXT NULL,
+            action TEXT NOT NULL,
+            path TEXT NULL,
+            md5 TEXT NULL,
+            size INTEGER NULL,
+            success BOOLEAN NOT NULL,
+            message TEXT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_audit_log_time ON audit_log (time);
+
+        CREATE TABLE IF NOT EXISTS api_token (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_name TEXT NOT NULL,
+            name TEXT NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE,
+            scopes TEXT NOT NULL,
+            created_time DATETIME NOT NULL,
+            expire_time DATETIME NULL,
+            last_used_time DATETIME NULL,
+            revoked BOOLEAN NOT NULL DEFAULT 0
+        );
+        CREATE INDEX IF NOT EXISTS idx_api_token_user_name ON api_token (user_name);
+
+        CREATE TABLE IF NOT EXISTS user_info (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_name TEXT NOT NULL UNIQUE,
+            password_hash TEXT NOT NULL,
+            role TEXT NOT NULL,
+            created_time DATETIME NOT NULL,
+            modified_time DATETIME NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS scan_history (
+            version INTEGER NOT NULL,
+            scan_path TEXT NOT NULL,
+            start_time DATETIME NOT NULL,
+            end_time DATETIME NOT NULL,
+            scanned_file_count INTEGER NOT NULL,
+            success BOOLEAN NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS image_hash (
+            md5 TEXT PRIMARY KEY,
+            dhash INTEGER NOT NULL,
+            phash INTEGER NOT NULL,
+            width INTEGER NOT NULL,
+            height INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS audio_fingerprint (
+            md5 TEXT PRIMARY KEY,
+            duration_s REAL NOT NULL,
+            codec TEXT NOT NULL,
+            bitrate INTEGER NOT NULL,
+            sample_rate INTEGER NOT NULL,
+            channels INTEGER NOT NULL,
+            bit_depth INTEGER NULL,
+            fingerprint BLOB NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS user_totp (
+            user_name TEXT PRIMARY KEY,
+            secret TEXT NOT NULL,
+            recovery_code_hashes TEXT NOT NULL,
+            last_step INTEGER NOT NULL,
+            created_time DATETIME NOT NULL
+        );
         ";
         tx.execute_batch(sql)?;
+        // add columns introduced after the table was created
+        Self::add_column_if_not_exists(&tx, "trash_info", "accessed", "DATETIME NULL")?;
+        Self::add_column_if_not_exists(
+            &tx,
+            "trash_info",
+            "blob_missing",
+            "BOOLEAN NOT NULL DEFAULT 0",
+        )?;
+        Self::add_column_if_not_exists(
+            &tx,
+            "trash_info",
+            "blob_corrupted",
+            "BOOLEAN NOT NULL DEFAULT 0",
+        )?;
+        Self::add_column_if_not_exists(&tx, "trash_info", "compressed_size", "INTEGER NULL")?;
+        Self::add_column_if_not_exists(&tx, "trash_info", "xdg_trash_path", "TEXT NULL")?;
+        Self::add_column_if_not_exists(
+            &tx,
+            "user_info",
+            "allowed_paths",
+            "TEXT NOT NULL DEFAULT '[]'",
+        )?;
+        Self::add_column_if_not_exists(&tx, "inode_info", "payload_md5", "TEXT NULL")?;
+        tx.execute(
+            "CREATE INDEX IF NOT EXISTS idx_payload_md5 ON inode_info (payload_md5)",
+            (),
+        )?;
+        Self::add_column_if_not_exists(&tx, "file_info", "archive_path", "TEXT NULL")?;
+        tx.execute(
+            "CREATE INDEX IF NOT EXISTS idx_archive_path ON file_info (archive_path)",
+            (),
+        )?;
         tx.commit()?;
         Ok(())
     }
 
+    /// Add column to an existing table if it does not exist
+    fn add_column_if_not_exists(
+        conn: &Connection,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<()> {
+        let sql = format!(
+            "SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?",
+            table
+        );
+        let column_count: u64 = conn.query_row(sql.as_str(), [column], |row| row.get(0))?;
+        if column_count == 0 {
+            info!("Add column {} to table {}", column, table);
+            conn.execute(
+                format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition).as_str(),
+                (),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Append filter of paths in or under one of the dir paths to the query, nothing is appended
+    /// if dir paths is empty
+    fn push_dir_paths_filter(
+        column: &str,
+        dir_paths: &[String],
+        query_sql: &mut String,
+        params: &mut Vec<Arc<dyn ToSql>>,
+    ) {
+        if dir_paths.is_empty() {
+            return;
+        }
+        let mut conditions = Vec::new();
+        for dir_path in dir_paths.iter() {
+            let dir_path = dir_path.trim_end_matches('/');
+            if dir_path.is_empty() {
+                // root dir contains all paths
+                conditions.push(String::from("1=1"));
+                continue;
+            }
+            let prefix = format!("{}/", dir_path);
+            conditions.push(format!("{0} = ? OR substr({0}, 1, ?) = ?", column));
+            params.push(Arc::new(dir_path.to_string()));
+            params.push(Arc::new(prefix.chars().count() as i64));
+            params.push(Arc::new(prefix));
+        }
+        *query_sql += format!(" AND ({})", conditions.join(" OR ")).as_str();
+    }
+
+    /// Column of the hash files are grouped by, the payload md5 falls back to the md5
+    fn group_md5_column(group_by: FileHashType, table: &str) -> String {
+        match group_by {
+            FileHashType::Md5 => format!("{}md5", table),
+            FileHashType::Payload => format!("COALESCE({0}payload_md5, {0}md5)", table),
+        }
+    }
+
     pub fn drop_tables(&self) -> Result<(), DfrError> {
         let conn = self.pool.get()?;
         let sql = "
+        DROP TABLE IF EXISTS audio_fingerprint;
+        DROP TABLE IF EXISTS image_hash;
+        DROP TABLE IF EXISTS scan_history;
+        DROP TABLE IF EXISTS user_totp;
+        DROP TABLE IF EXISTS api_token;
+        DROP TABLE IF EXISTS user_info;
+        DROP TABLE IF EXISTS audit_log;
+        DROP TABLE IF EXISTS trash_xattr_info;
         DROP TABLE IF EXISTS trash_info;
         DROP TABLE IF EXISTS inode_info;
         DROP TABLE IF EXISTS file_info;
@@ -180,8 +418,8 @@ impl DatabaseManager {
                 ),
             }
 
-            let sql = "INSERT OR REPLACE INTO inode_info (inode, dev_id, permissions, nlink, uid, gid, created, modified, md5, size) 
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)";
+            let sql = "INSERT OR REPLACE INTO inode_info (inode, dev_id, permissions, nlink, uid, gid, created, modified, md5, size, payload_md5) 
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)";
             tx.execute(
                 sql,
                 (
@@ -195,6 +433,7 @@ impl DatabaseManager {
                     &file_info.inode_info.modified,
                     &file_info.inode_info.md5,
                     file_info.inode_info.size,
+                    &file_info.inode_info.payload_md5,
                 ),
             )?;
             tx.last_insert_rowid()
@@ -204,8 +443,8 @@ impl DatabaseManager {
                 info!("Need to update file {} inode info, db inode info: {:?}, current file inode info: {:?}",file_info.file_path, db_inode_info , file_info.inode_info);
                 let sql = "
                 UPDATE inode_info 
-                SET inode=?1, dev_id=?2, permissions=?3, nlink=?4, uid=?5, gid=?6, created=?7, modified=?8, md5=?9, size=?10
-                WHERE id=?11
+                SET inode=?1, dev_id=?2, permissions=?3, nlink=?4, uid=?5, gid=?6, created=?7, modified=?8, md5=?9, size=?10, payload_md5=?11
+                WHERE id=?12
                 ";
                 tx.execute(
                     sql,
@@ -220,6 +459,7 @@ impl DatabaseManager {
                         &file_info.inode_info.modified,
                         &file_info.inode_info.md5,
                         file_info.inode_info.size,
+                        &file_info.inode_info.payload_md5,
                         db_inode_info.id,
                     ),
                 )?;
@@ -227,17 +467,28 @@ impl DatabaseManager {
             db_inode_info.id
         };
 
-        let sql = "INSERT OR REPLACE INTO file_info (inode_info_id, dir_path, file_name, file_extension, scan_time, version) 
-          VALUES (?1, ?2, ?3, ?4, ?5, ?6)";
-        let result = match tx.execute(
+        Self::insert_file_info_do(&tx, last_insert_id, file_info)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn insert_file_info_do(
+        conn: &Connection,
+        inode_info_id: i64,
+        file_info: &FileInfo,
+    ) -> Result<()> {
+        let sql = "INSERT OR REPLACE INTO file_info (inode_info_id, dir_path, file_name, file_extension, scan_time, version, archive_path) 
+          VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)";
+        let result = match conn.execute(
             sql,
             (
-                last_insert_id,
+                inode_info_id,
                 &file_info.dir_path,
                 &file_info.file_name,
                 &file_info.file_extension,
                 &file_info.scan_time,
                 &file_info.version,
+                &file_info.archive_path,
             ),
         ) {
             Ok(_) => Ok(()),
@@ -246,11 +497,73 @@ impl DatabaseManager {
                 Err(_e)
             }
         };
-        result?;
+        result
+    }
+
+    /// Replace the members of the archive, members are inserted with device ID 0 and the next
+    /// free inode number of it
+    pub fn replace_archive_members(
+        &self,
+        archive_path: &str,
+        members: &[FileInfo],
+    ) -> Result<(), DfrError> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+        Self::remove_archive_members(&tx, archive_path)?;
+        for member in members.iter() {
+            let sql = "INSERT INTO inode_info (inode, dev_id, permissions, nlink, uid, gid, created, modified, md5, size)
+            VALUES ((SELECT COALESCE(MAX(inode), 0) + 1 FROM inode_info WHERE dev_id = 0), 0, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+            tx.execute(
+                sql,
+                (
+                    member.inode_info.permissions,
+                    member.inode_info.nlink,
+                    member.inode_info.uid,
+                    member.inode_info.gid,
+                    &member.inode_info.created,
+                    &member.inode_info.modified,
+                    &member.inode_info.md5,
+                    member.inode_info.size,
+                ),
+            )?;
+            Self::insert_file_info_do(&tx, tx.last_insert_rowid(), member)?;
+        }
         tx.commit()?;
         Ok(())
     }
 
+    /// Remove the members of the archive and their inodes
+    fn remove_archive_members(conn: &Connection, archive_path: &str) -> Result<()> {
+        conn.execute(
+            "DELETE FROM file_info WHERE archive_path = ?",
+            [archive_path],
+        )?;
+        conn.execute(
+            "DELETE FROM inode_info WHERE dev_id = 0 AND id NOT IN (SELECT inode_info_id FROM file_info)",
+            (),
+        )?;
+        Ok(())
+    }
+
+    /// Update version of the members of an unchanged archive, return the count of the members
+    pub fn update_archive_member_version(&self, archive: &FileInfo) -> Result<usize, DfrError> {
+        let conn = self.pool.get()?;
+        Ok(conn.execute(
+            "UPDATE file_info SET version = ? WHERE archive_path = ?",
+            (archive.version, &archive.file_path),
+        )?)
+    }
+
+    /// Update the payload md5 of the inode of the file
+    pub fn update_payload_md5(&self, inode_info: &InodeInfo) -> Result<(), DfrError> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE inode_info SET payload_md5 = ? WHERE dev_id = ? AND inode = ?",
+            (&inode_info.payload_md5, inode_info.dev_id, inode_info.inode),
+        )?;
+        Ok(())
+    }
+
     pub fn query_inode_info_do_by_inode(
         &self,
         conn: &Connection,
@@ -258,7 +571,7 @@ impl DatabaseManager {
         node_id: u64,
     ) -> Result<InodeInfoDO> {
         let sql = "
-        SELECT inode, dev_id, permissions, nlink, uid, gid, created, modified, md5, size, id
+        SELECT inode, dev_id, permissions, nlink, uid, gid, created, modified, md5, size, id, payload_md5
         FROM inode_info
         WHERE dev_id = ? AND inode = ?
         ";
@@ -267,7 +580,7 @@ impl DatabaseManager {
 
     fn get_inode_info_do_by_id(&self, conn: &Connection, id: u64) -> Result<InodeInfoDO> {
         let sql = "
-        SELECT inode, dev_id, permissions, nlink, uid, gid, created, modified, md5, size, id
+        SELECT inode, dev_id, permissions, nlink, uid, gid, created, modified, md5, size, id, payload_md5
         FROM inode_info
         WHERE id = ?
         ";
@@ -293,6 +606,7 @@ impl DatabaseManager {
                     created: row.get(6)?,
                     modified: row.get(7)?,
                     md5: row.get(8)?,
+                    payload_md5: row.get(11)?,
                     size: row.get(9)?,
                 },
                 id: row.get(10)?,
@@ -306,7 +620,7 @@ impl DatabaseManager {
         dir_path: &str,
         file_name: &str,
     ) -> Result<FileInfoDO> {
-        let sql = "SELECT inode_info_id, dir_path, file_name, file_extension, scan_time, version
+        let sql = "SELECT inode_info_id, dir_path, file_name, file_extension, scan_time, version, archive_path
         FROM file_info 
         WHERE dir_path = ? and file_name = ?";
         let mut stmt = conn.prepare(sql)?;
@@ -318,6 +632,7 @@ impl DatabaseManager {
                 file_extension: row.get(3)?,
                 scan_time: row.get(4)?,
                 version: row.get(5)?,
+                archive_path: row.get(6)?,
             })
         });
         result
@@ -335,7 +650,7 @@ impl DatabaseManager {
         let conn = self.pool.get()?;
         let sql = "SELECT 
         a1.inode, a1.dev_id, a1.permissions, a1.nlink, a1.uid, a1.gid, a1.created, a1.modified, a1.md5, a1.size,
-        a2.dir_path, a2.file_name, a2.file_extension, a2.scan_time, a2.version
+        a2.dir_path, a2.file_name, a2.file_extension, a2.scan_time, a2.version, a1.payload_md5, a2.archive_path
         FROM inode_info as a1
         JOIN file_info as a2 ON a1.id= a2.inode_info_id
         WHERE a1.md5 = ?";
@@ -351,6 +666,7 @@ impl DatabaseManager {
                 created: row.get(6)?,
                 modified: row.get(7)?,
                 md5: row.get(8)?,
+                payload_md5: row.get(15)?,
                 size: row.get(9)?,
             };
 
@@ -363,6 +679,7 @@ impl DatabaseManager {
                     file_extension: row.get(12)?,
                     scan_time: row.get(13)?,
                     version: row.get(14)?,
+                    archive_path: row.get(16)?,
                 },
             ))
         });
@@ -390,6 +707,10 @@ impl DatabaseManager {
         // delete file info from db
         let sql = "DELETE FROM file_info WHERE dir_path = ? AND file_name = ?";
         conn.execute(sql, (dir_path, file_name))?;
+        // members of an archive are removed with it
+        if file_info_do.archive_path.is_none() {
+            Self::remove_archive_members(conn, &join_path(dir_path, file_name))?;
+        }
 
         if file_count <= 1 {
             // delete inode info from db
@@ -423,12 +744,18 @@ impl DatabaseManager {
         Ok(())
     }
 
-    /// remove not existed files from database based on version
-    pub fn remove_deleted_files_by_version(&self, version: u64) -> Result<(), DfrError> {
+    /// remove not existed files under the scan path from database based on version
+    pub fn remove_deleted_files_by_version(
+        &self,
+        scan_path: &str,
+        version: u64,
+    ) -> Result<(), DfrError> {
         let mut conn = self.pool.get()?;
         let tx = conn.transaction()?;
-        let sql = "DELETE FROM file_info WHERE version != ?";
-        let update_rows = tx.execute(sql, [version])?;
+        let mut params: Vec<Arc<dyn ToSql>> = vec![Arc::new(version)];
+        let mut sql = String::from("DELETE FROM file_info WHERE version != ?");
+        Self::push_dir_paths_filter("dir_path", &[scan_path.to_string()], &mut sql, &mut params);
+        let update_rows = tx.execute(sql.as_str(), params_from_iter(params.iter()))?;
         tx.commit()?;
         if update_rows > 0 {
             info!(
@@ -450,10 +777,24 @@ impl DatabaseManager {
                 SELECT DISTINCT inode_info_id 
                 FROM file_info)";
         let update_rows = tx.execute(sql, ())?;
+        // hashes are kept by md5, so they are only removed with the last inode of the md5
+        let sql = "DELETE FROM image_hash WHERE md5 NOT IN (SELECT md5 FROM inode_info)";
+        let image_hash_rows = tx.execute(sql, ())?;
+        let sql = "DELETE FROM audio_fingerprint WHERE md5 NOT IN (SELECT md5 FROM inode_info)";
+        let audio_fingerprint_rows = tx.execute(sql, ())?;
         tx.commit()?;
         if update_rows > 0 {
             info!("deleted {} rows in inode_info", update_rows);
         }
+        if image_hash_rows > 0 {
+            info!("deleted {} rows in image_hash", image_hash_rows);
+        }
+        if audio_fingerprint_rows > 0 {
+            info!(
+                "deleted {} rows in audio_fingerprint",
+                audio_fingerprint_rows
+            );
+        }
         Ok(())
     }
 
@@ -470,7 +811,12 @@ impl DatabaseManager {
         Ok(update_rows)
     }
 
-    pub fn move_file_to_trash(&self, file_info: &FileInfo) -> Result<(), DfrError> {
+    pub fn move_file_to_trash(
+        &self,
+        file_info: &FileInfo,
+        attributes: &FileAttributes,
+        storage: &TrashStorage,
+    ) -> Result<(), DfrError> {
         let mut conn = self.pool.get()?;
         let tx = conn.transaction()?;
         let md5 = file_info.inode_info.md5.clone().unwrap();
@@ -481,8 +827,8 @@ impl DatabaseManager {
         )?;
 
         let sql = "
-            INSERT OR REPLACE INTO trash_info (dir_path, file_name, file_extension, remove_time, permissions, uid, gid, created, modified, md5, size) 
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)";
+            INSERT OR REPLACE INTO trash_info (dir_path, file_name, file_extension, remove_time, permissions, uid, gid, created, modified, md5, size, accessed, compressed_size, xdg_trash_path) 
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)";
         tx.execute(
             sql,
             (
@@ -497,21 +843,53 @@ impl DatabaseManager {
                 &file_info.inode_info.modified,
                 &md5,
                 file_info.inode_info.size,
+                &attributes.accessed,
+                storage.compressed_size,
+                &storage.xdg_trash_path,
             ),
         )?;
 
+        Self::remove_trash_xattrs(&tx, &file_info.dir_path, &file_info.file_name)?;
+        let sql = "
+            INSERT INTO trash_xattr_info (dir_path, file_name, name, value)
+            VALUES (?1, ?2, ?3, ?4)";
+        for xattr in attributes.xattrs.iter() {
+            tx.execute(
+                sql,
+                (
+                    &file_info.dir_path,
+                    &file_info.file_name,
+                    &xattr.name,
+                    &xattr.value,
+                ),
+            )?;
+        }
+
         tx.commit()?;
         Ok(())
     }
 
-    pub fn list_files(&self, query_list_params: &ListSettings) -> Result<FileInfoList, DfrError> {
+    /// List files, only files in or under the allowed paths are listed and counted as duplicates
+    /// if allowed paths is not empty
+    pub fn list_files(
+        &self,
+        query_list_params: &ListSettings,
+        allowed_paths: &[String],
+    ) -> Result<FileInfoList, DfrError> {
         let mut conn = self.pool.get()?;
         let mut params: Vec<Arc<dyn ToSql>> = Vec::new();
-        let mut sub_query_sql = String::from(
-            "SELECT md5, COUNT(md5) AS md5_count
+        let group_by = query_list_params.group_by.unwrap_or_default();
+        let mut sub_query_sql = format!(
+            "SELECT {0} AS md5, COUNT(*) AS md5_count
             FROM inode_info 
             WHERE 1=1",
+            Self::group_md5_column(group_by, ""),
         );
+        if !allowed_paths.is_empty() {
+            sub_query_sql += " AND id IN (SELECT inode_info_id FROM file_info WHERE 1=1";
+            Self::push_dir_paths_filter("dir_path", allowed_paths, &mut sub_query_sql, &mut params);
+            sub_query_sql += ")";
+        }
         if let Some(min_file_size) = query_list_params.min_file_size {
             params.push(Arc::new(min_file_size));
             sub_query_sql += " AND size >= ?";
@@ -520,7 +898,7 @@ impl DatabaseManager {
             params.push(Arc::new(max_file_size));
             sub_query_sql += " AND size < ?";
         }
-        sub_query_sql += " GROUP BY md5";
+        sub_query_sql += format!(" GROUP BY {}", Self::group_md5_column(group_by, "")).as_str();
         let mut filter_sub_query_sql = String::new();
         let mut filter_select_params = String::new();
         let mut filter_join_sql = String::new();
@@ -536,11 +914,12 @@ impl DatabaseManager {
                     )));
                 }
                 let dir_path = query_list_params.dir_path.clone().unwrap();
-                let mut sub_query_sql = String::from(
-                    "SELECT b1.md5, COUNT(b1.md5) AS md5_count
+                let mut sub_query_sql = format!(
+                    "SELECT {0} AS md5, COUNT(*) AS md5_count
                     FROM inode_info AS b1,
                         file_info AS b2
                     WHERE b1.id = b2.inode_info_id",
+                    Self::group_md5_column(group_by, "b1."),
                 );
                 if let Some(min_file_size) = query_list_params.min_file_size {
                     params.push(Arc::new(min_file_size));
@@ -553,8 +932,15 @@ impl DatabaseManager {
 
                 params.push(Arc::new(format!("%{}%", dir_path)));
                 sub_query_sql += " AND b2.dir_path LIKE ?";
+                Self::push_dir_paths_filter(
+                    "b2.dir_path",
+                    allowed_paths,
+                    &mut sub_query_sql,
+                    &mut params,
+                );
 
-                sub_query_sql += " GROUP BY b1.md5";
+                sub_query_sql +=
+                    format!(" GROUP BY {}", Self::group_md5_column(group_by, "b1.")).as_str();
                 filter_sub_query_sql = format!(", ({}) AS a4", sub_query_sql);
                 filter_select_params = String::from(", a4.md5_count AS filter_md5_count");
                 filter_join_sql = String::from("AND a4.md5 = a3.md5");
@@ -569,14 +955,18 @@ impl DatabaseManager {
                 {}
             WHERE 
                 a1.id = a2.inode_info_id 
-                AND a1.md5 = a3.md5
+                AND {} = a3.md5
                 {}",
-            sub_query_sql, filter_sub_query_sql, filter_join_sql
+            sub_query_sql,
+            filter_sub_query_sql,
+            Self::group_md5_column(group_by, "a1."),
+            filter_join_sql
         );
         if let Some(dir_path) = query_list_params.dir_path.clone() {
             query_sql += " AND a2.dir_path LIKE ?";
             params.push(Arc::new(format!("%{}%", dir_path)));
         }
+        Self::push_dir_paths_filter("a2.dir_path", allowed_paths, &mut query_sql, &mut params);
         if let Some(file_name) = query_list_params.file_name.clone() {
             query_sql += " AND a2.file_name LIKE ?";
             params.push(Arc::new(format!("%{}%", file_name)));
@@ -633,7 +1023,7 @@ impl DatabaseManager {
         info!("list file query count sql: {}", count_sql);
 
         let mut sql = String::from("SELECT a1.inode, a1.dev_id, a1.permissions, a1.nlink, a1.uid, a1.gid, a1.created, a1.modified, a1.md5, a1.size,
-            a2.dir_path, a2.file_name, a2.file_extension, a2.scan_time, a2.version, a3.md5_count") +&filter_select_params+ &query_sql;
+            a2.dir_path, a2.file_name, a2.file_extension, a2.scan_time, a2.version, a1.payload_md5, a2.archive_path, a3.md5_count") +&filter_select_params+ &query_sql;
 
         // order by
         let mut order_by_list: Vec<String> = Vec::new();
@@ -651,6 +1041,8 @@ impl DatabaseManager {
         }
         //order by md5_count default
         order_by_list.push(String::from("a3.md5_count DESC"));
+        // files of the same md5 are adjacent and pages are stable, so that exports can page
+        order_by_list.push(String::from("a3.md5, a1.md5, a2.dir_path, a2.file_name"));
         sql += format!(" order by {}", order_by_list.join(",")).as_str();
 
         //  add limit
@@ -686,6 +1078,7 @@ impl DatabaseManager {
                 created: row.get(6)?,
                 modified: row.get(7)?,
                 md5: row.get(8)?,
+                payload_md5: row.get(15)?,
                 size: row.get(9)?,
             };
             let file_info = FileInfo::from_do(
@@ -697,17 +1090,18 @@ impl DatabaseManager {
                     file_extension: row.get(12)?,
                     scan_time: row.get(13)?,
                     version: row.get(14)?,
+                    archive_path: row.get(16)?,
                 },
             );
             let filter_md5_count;
             if has_filter_md5_count {
-                filter_md5_count = Some(row.get(16)?);
+                filter_md5_count = Some(row.get(18)?);
             } else {
                 filter_md5_count = None;
             }
             Ok(FileInfoWithMd5Count {
                 file_info,
-                md5_count: row.get(15)?,
+                md5_count: row.get(17)?,
                 filter_md5_count,
             })
         });
@@ -721,9 +1115,265 @@ impl DatabaseManager {
         })
     }
 
+    /// List path and md5 of all files ordered by path, only files in or under the allowed paths
+    /// are listed if allowed paths is not empty
+    pub fn list_file_hashes(&self, allowed_paths: &[String]) -> Result<Vec<FileHash>, DfrError> {
+        let conn = self.pool.get()?;
+        let mut params: Vec<Arc<dyn ToSql>> = Vec::new();
+        let mut sql = String::from(
+            "SELECT a2.dir_path, a2.file_name, a1.md5, a1.size
+            FROM file_info AS a2
+            JOIN inode_info AS a1 ON a1.id = a2.inode_info_id
+            WHERE 1=1",
+        );
+        Self::push_dir_paths_filter("a2.dir_path", allowed_paths, &mut sql, &mut params);
+        sql += " ORDER BY a2.dir_path, a2.file_name";
+        let mut stmt = conn.prepare(sql.as_str())?;
+        let file_hash_iter = stmt.query_map(params_from_iter(params.iter()), |row| {
+            Ok(FileHash {
+                dir_path: row.get(0)?,
+                file_name: row.get(1)?,
+                md5: row.get(2)?,
+                size: row.get(3)?,
+            })
+        })?;
+        let mut file_hashes = Vec::new();
+        for file_hash in file_hash_iter {
+            file_hashes.push(file_hash?);
+        }
+        Ok(file_hashes)
+    }
+
+    /// Get the files directly in the directory and the files of each subdirectory, only files in
+    /// or under the allowed paths are counted if allowed paths is not empty. Only the rows of the
+    /// directory are read, by a range of the dir path index
+    pub fn get_dir_tree(
+        &self,
+        dir_path: &str,
+        allowed_paths: &[String],
+    ) -> Result<DirTree, DfrError> {
+        let conn = self.pool.get()?;
+        let dir_path = dir_path.trim_end_matches('/');
+        // '0' is the character after '/', so paths under the directory are between the bounds
+        let (prefix, upper_bound) = (format!("{}/", dir_path), format!("{}0", dir_path));
+        let dir_path = if dir_path.is_empty() { "/" } else { dir_path };
+        let mut params: Vec<Arc<dyn ToSql>> = vec![
+            Arc::new(dir_path.to_string()),
+            Arc::new(prefix.chars().count() as i64 + 1),
+        ];
+        // a file is a duplicate if another inode has the same md5, inodes of other files are only
+        // counted in the allowed paths, the same as in list files
+        let mut duplicate_sql = String::from(
+            "EXISTS (SELECT 1 FROM inode_info AS b1 WHERE b1.md5 = a1.md5 AND b1.id != a1.id",
+        );
+        if !allowed_paths.is_empty() {
+            duplicate_sql += " AND b1.id IN (SELECT inode_info_id FROM file_info WHERE 1=1";
+            Self::push_dir_paths_filter("dir_path", allowed_paths, &mut duplicate_sql, &mut params);
+            duplicate_sql += ")";
+        }
+        duplicate_sql += ")";
+        let mut sql = format!(
+            "SELECT name, COUNT(*), COALESCE(SUM(size), 0), SUM(is_duplicate),
+                COALESCE(SUM(CASE WHEN is_duplicate THEN size ELSE 0 END), 0)
+            FROM (
+                SELECT CASE WHEN instr(rest, '/') > 0 THEN substr(rest, 1, instr(rest, '/') - 1)
+                    ELSE rest END AS name, size, is_duplicate
+                FROM (
+                    SELECT CASE WHEN a2.dir_path = ? THEN '' ELSE substr(a2.dir_path, ?) END AS rest,
+                        a1.size AS size, {} AS is_duplicate
+                    FROM file_info AS a2
+                    JOIN inode_info AS a1 ON a1.id = a2.inode_info_id
+                    WHERE (a2.dir_path = ? OR (a2.dir_path >= ? AND a2.dir_path < ?))",
+            duplicate_sql
+        );
+        params.push(Arc::new(dir_path.to_string()));
+        params.push(Arc::new(prefix));
+        params.push(Arc::new(upper_bound));
+        Self::push_dir_paths_filter("a2.dir_path", allowed_paths, &mut sql, &mut params);
+        sql += ")) GROUP BY name";
+        let mut stmt = conn.prepare(sql.as_str())?;
+        let entry_iter = stmt.query_map(params_from_iter(params.iter()), |row| {
+            let name: String = row.get(0)?;
+            Ok(DirTreeEntry {
+                dir_path: if name.is_empty() {
+                    dir_path.to_string()
+                } else {
+                    join_path(dir_path, &name)
+                },
+                name,
+                file_count: row.get(1)?,
+                size: row.get(2)?,
+                duplicate_file_count: row.get(3)?,
+                duplicate_size: row.get(4)?,
+            })
+        })?;
+        let mut entries = Vec::new();
+        for entry in entry_iter {
+            entries.push(entry?);
+        }
+        let parent_dir_path = parent_dir(dir_path).map(String::from);
+        Ok(DirTree::new(dir_path, parent_dir_path, entries))
+    }
+
+    pub fn has_image_hash(&self, md5: &str) -> Result<bool, DfrError> {
+        let conn = self.pool.get()?;
+        let count: u64 = conn.query_row(
+            "SELECT COUNT(*) FROM image_hash WHERE md5 = ?",
+            [md5],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    pub fn insert_image_hash(&self, image_hash: &ImageHash) -> Result<(), DfrError> {
+        let conn = self.pool.get()?;
+        // hashes are stored as signed integers, the bits are kept
+        conn.execute(
+            "INSERT OR REPLACE INTO image_hash (md5, dhash, phash, width, height)
+            VALUES (?, ?, ?, ?, ?)",
+            (
+                &image_hash.md5,
+                image_hash.dhash as i64,
+                image_hash.phash as i64,
+                image_hash.width,
+                image_hash.height,
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// List image files with perceptual hashes, only files in or under the allowed paths are
+    /// listed if allowed paths is not empty
+    pub fn list_image_hash_files(
+        &self,
+        allowed_paths: &[String],
+    ) -> Result<Vec<ImageHashFile>, DfrError> {
+        let conn = self.pool.get()?;
+        let mut params: Vec<Arc<dyn ToSql>> = Vec::new();
+        let mut sql = String::from(
+            "SELECT a4.md5, a4.dhash, a4.phash, a4.width, a4.height, a2.dir_path, a2.file_name,
+                a1.size
+            FROM file_info AS a2
+            JOIN inode_info AS a1 ON a1.id = a2.inode_info_id
+            JOIN image_hash AS a4 ON a4.md5 = a1.md5
+            WHERE 1=1",
+        );
+        Self::push_dir_paths_filter("a2.dir_path", allowed_paths, &mut sql, &mut params);
+        sql += " ORDER BY a4.md5, a2.dir_path, a2.file_name";
+        let mut stmt = conn.prepare(sql.as_str())?;
+        let file_iter = stmt.query_map(params_from_iter(params.iter()), |row| {
+            Ok(ImageHashFile {
+                image_hash: ImageHash {
+                    md5: row.get(0)?,
+                    dhash: row.get::<_, i64>(1)? as u64,
+                    phash: row.get::<_, i64>(2)? as u64,
+                    width: row.get(3)?,
+                    height: row.get(4)?,
+                },
+                dir_path: row.get(5)?,
+                file_name: row.get(6)?,
+                size: row.get(7)?,
+            })
+        })?;
+        let mut files = Vec::new();
+        for file in file_iter {
+            files.push(file?);
+        }
+        Ok(files)
+    }
+
+    pub fn has_audio_fingerprint(&self, md5: &str) -> Result<bool, DfrError> {
+        let conn = self.pool.get()?;
+        let count: u64 = conn.query_row(
+            "SELECT COUNT(*) FROM audio_fingerprint WHERE md5 = ?",
+            [md5],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    pub fn insert_audio_fingerprint(
+        &self,
+        audio_fingerprint: &AudioFingerprint,
+    ) -> Result<(), DfrError> {
+        let conn = self.pool.get()?;
+        // sub-fingerprints are stored as little endian bytes
+        let fingerprint = audio_fingerprint
+            .fingerprint
+            .iter()
+            .flat_map(|sub_fingerprint| sub_fingerprint.to_le_bytes())
+            .collect::<Vec<_>>();
+        conn.execute(
+            "INSERT OR REPLACE INTO audio_fingerprint
+            (md5, duration_s, codec, bitrate, sample_rate, channels, bit_depth, fingerprint)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            (
+                &audio_fingerprint.md5,
+                audio_fingerprint.duration_s,
+                &audio_fingerprint.codec,
+                audio_fingerprint.bitrate,
+                audio_fingerprint.sample_rate,
+                audio_fingerprint.channels,
+                audio_fingerprint.bit_depth,
+                fingerprint,
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// List audio files with fingerprints, only files in or under the allowed paths are listed if
+    /// allowed paths is not empty
+    pub fn list_audio_fingerprint_files(
+        &self,
+        allowed_paths: &[String],
+    ) -> Result<Vec<AudioFingerprintFile>, DfrError> {
+        let conn = self.pool.get()?;
+        let mut params: Vec<Arc<dyn ToSql>> = Vec::new();
+        let mut sql = String::from(
+            "SELECT a4.md5, a4.duration_s, a4.codec, a4.bitrate, a4.sample_rate, a4.channels,
+                a4.bit_depth, a4.fingerprint, a2.dir_path, a2.file_name, a1.size
+            FROM file_info AS a2
+            JOIN inode_info AS a1 ON a1.id = a2.inode_info_id
+            JOIN audio_fingerprint AS a4 ON a4.md5 = a1.md5
+            WHERE 1=1",
+        );
+        Self::push_dir_paths_filter("a2.dir_path", allowed_paths, &mut sql, &mut params);
+        sql += " ORDER BY a4.md5, a2.dir_path, a2.file_name";
+        let mut stmt = conn.prepare(sql.as_str())?;
+        let file_iter = stmt.query_map(params_from_iter(params.iter()), |row| {
+            let fingerprint: Vec<u8> = row.get(7)?;
+            Ok(AudioFingerprintFile {
+                audio_fingerprint: AudioFingerprint {
+                    md5: row.get(0)?,
+                    duration_s: row.get(1)?,
+                    codec: row.get(2)?,
+                    bitrate: row.get(3)?,
+                    sample_rate: row.get(4)?,
+                    channels: row.get(5)?,
+                    bit_depth: row.get(6)?,
+                    fingerprint: fingerprint
+                        .chunks_exact(4)
+                        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                        .collect(),
+                },
+                dir_path: row.get(8)?,
+                file_name: row.get(9)?,
+                size: row.get(10)?,
+            })
+        })?;
+        let mut files = Vec::new();
+        for file in file_iter {
+            files.push(file?);
+        }
+        Ok(files)
+    }
+
+    /// List trash files, only trash files restored to the allowed paths are listed if allowed
+    /// paths is not empty
     pub fn list_trash_files(
         &self,
         query_list_params: &TrashListSettings,
+        allowed_paths: &[String],
     ) -> Result<TrashFileInfoList, DfrError> {
         let mut conn = self.pool.get()?;
         let mut params: Vec<Arc<dyn ToSql>> = Vec::new();
@@ -741,6 +1391,7 @@ impl DatabaseManager {
             query_sql += " AND dir_path LIKE ?";
             params.push(Arc::new(format!("%{}%", dir_path)));
         }
+        Self::push_dir_paths_filter("dir_path", allowed_paths, &mut query_sql, &mut params);
         if let Some(file_name) = query_list_params.file_name.clone() {
             query_sql += " AND file_name LIKE ?";
             params.push(Arc::new(format!("%{}%", file_name)));
@@ -798,7 +1449,7 @@ impl DatabaseManager {
         let count_params = params.to_vec();
         info!("list file query count sql: {}", count_sql);
 
-        let mut sql = String::from("SELECT dir_path, file_name, file_extension, remove_time, permissions, uid, gid, created, modified, md5, size") + &query_sql;
+        let mut sql = format!("SELECT {}", TRASH_INFO_COLUMNS) + &query_sql;
 
         // order by
         let mut order_by_list: Vec<String> = Vec::new();
@@ -838,24 +1489,10 @@ impl DatabaseManager {
         let total_count = count_iter?.next().unwrap()?;
 
         let mut stmt = trans.prepare(&sql)?;
-        let file_iter = stmt.query_map(params_from_iter(params.iter()), |row| {
-            // dir_path, file_name, file_extension, remove_time, permissions, uid, gid, created, modified, md5, size
-            let trash_file_info = TrashFileInfo {
-                dir_path: row.get(0)?,
-                file_name: row.get(1)?,
-                file_extension: row.get(2)?,
-                remove_time: row.get(3)?,
-                permissions: row.get(4)?,
-                uid: row.get(5)?,
-                gid: row.get(6)?,
-                created: row.get(7)?,
-                modified: row.get(8)?,
-                md5: row.get(9)?,
-                size: row.get(10)?,
-            };
-
-            Ok(trash_file_info)
-        });
+        let file_iter = stmt.query_map(
+            params_from_iter(params.iter()),
+            Self::trash_file_info_from_row,
+        );
         let mut files = Vec::new();
         for item in file_iter? {
             files.push(item?);
@@ -872,27 +1509,86 @@ impl DatabaseManager {
         file_name: &str,
     ) -> Result<TrashFileInfo, DfrError> {
         let conn = self.pool.get()?;
-        let sql = "
-            SELECT dir_path, file_name, file_extension, remove_time, permissions, uid, gid, created, modified, md5, size
+        let sql = format!(
+            "SELECT {}
+            FROM trash_info 
+            WHERE dir_path = ? AND file_name = ?",
+            TRASH_INFO_COLUMNS
+        );
+        let mut stmt = conn.prepare(sql.as_str())?;
+        let trash_file_info = stmt.query_row([dir_path, file_name], Self::trash_file_info_from_row);
+        Ok(trash_file_info?)
+    }
+
+    /// List trash files stored in freedesktop.org trash
+    pub fn list_xdg_trash_files(&self) -> Result<Vec<TrashFileInfo>, DfrError> {
+        let conn = self.pool.get()?;
+        let sql = format!(
+            "SELECT {}
             FROM trash_info 
+            WHERE xdg_trash_path IS NOT NULL",
+            TRASH_INFO_COLUMNS
+        );
+        let mut stmt = conn.prepare(sql.as_str())?;
+        let file_iter = stmt.query_map([], Self::trash_file_info_from_row)?;
+        let mut files = Vec::new();
+        for item in file_iter {
+            files.push(item?);
+        }
+        Ok(files)
+    }
+
+    fn trash_file_info_from_row(row: &Row) -> Result<TrashFileInfo> {
+        Ok(TrashFileInfo {
+            dir_path: row.get(0)?,
+            file_name: row.get(1)?,
+            file_extension: row.get(2)?,
+            remove_time: row.get(3)?,
+            permissions: row.get(4)?,
+            uid: row.get(5)?,
+            gid: row.get(6)?,
+            created: row.get(7)?,
+            modified: row.get(8)?,
+            md5: row.get(9)?,
+            size: row.get(10)?,
+            accessed: row.get(11)?,
+            blob_missing: row.get(12)?,
+            blob_corrupted: row.get(13)?,
+            compressed_size: row.get(14)?,
+            xdg_trash_path: row.get(15)?,
+        })
+    }
+
+    /// Get extended attributes saved when the file was moved to trash
+    pub fn get_trash_file_xattrs(
+        &self,
+        dir_path: &str,
+        file_name: &str,
+    ) -> Result<Vec<ExtendedAttribute>, DfrError> {
+        let conn = self.pool.get()?;
+        let sql = "
+            SELECT name, value
+            FROM trash_xattr_info
             WHERE dir_path = ? AND file_name = ?";
         let mut stmt = conn.prepare(sql)?;
-        let trash_file_info = stmt.query_row([dir_path, file_name], |row| {
-            Ok(TrashFileInfo {
-                dir_path: row.get(0)?,
-                file_name: row.get(1)?,
-                file_extension: row.get(2)?,
-                remove_time: row.get(3)?,
-                permissions: row.get(4)?,
-                uid: row.get(5)?,
-                gid: row.get(6)?,
-                created: row.get(7)?,
-                modified: row.get(8)?,
-                md5: row.get(9)?,
-                size: row.get(10)?,
+        let xattr_iter = stmt.query_map([dir_path, file_name], |row| {
+            Ok(ExtendedAttribute {
+                name: row.get(0)?,
+                value: row.get(1)?,
             })
-        });
-        Ok(trash_file_info?)
+        })?;
+        let mut xattrs = Vec::new();
+        for item in xattr_iter {
+            xattrs.push(item?);
+        }
+        Ok(xattrs)
+    }
+
+    fn remove_trash_xattrs(conn: &Connection, dir_path: &str, file_name: &str) -> Result<usize> {
+        let sql = "
+            DELETE FROM trash_xattr_info 
+            WHERE dir_path = ? AND file_name = ?";
+        conn.execute(sql, (dir_path, file_name))
     }
 
     pub fn remove_trash_file_by_path(
@@ -907,22 +1603,67 @@ impl DatabaseManager {
             DELETE FROM trash_info 
             WHERE dir_path = ? AND file_name = ?";
         let usize = tx.execute(sql, (dir_path, file_name))?;
+        Self::remove_trash_xattrs(&tx, dir_path, file_name)?;
         tx.commit()?;
         Ok(usize)
     }
 
+    /// Remove trash info sharing the trash file with the md5 in trash path
     pub fn remove_trash_file_by_md5(&self, md5: &str) -> Result<usize, DfrError> {
         let mut conn = self.pool.get()?;
         let tx = conn.transaction()?;
 
+        let sql = "
+            DELETE FROM trash_xattr_info 
+            WHERE (dir_path, file_name) IN (
+                SELECT dir_path, file_name 
+                FROM trash_info 
+                WHERE md5 = ? AND xdg_trash_path IS NULL)";
+        tx.execute(sql, [md5])?;
+
         let sql = "
             DELETE FROM trash_info 
-            WHERE md5 = ?";
+            WHERE md5 = ? AND xdg_trash_path IS NULL";
         let usize = tx.execute(sql, [md5])?;
         tx.commit()?;
         Ok(usize)
     }
 
+    /// List distinct md5 of trash files stored in trash path
+    pub fn list_trash_md5(&self) -> Result<Vec<String>, DfrError> {
+        let conn = self.pool.get()?;
+        let sql = "SELECT DISTINCT md5 FROM trash_info WHERE xdg_trash_path IS NULL";
+        let mut stmt = conn.prepare(sql)?;
+        let md5_iter = stmt.query_map([], |row| row.get(0))?;
+        let mut md5_list = Vec::new();
+        for item in md5_iter {
+            md5_list.push(item?);
+        }
+        Ok(md5_list)
+    }
+
+    /// Mark whether the trash file shared by trash info with the md5 is missing or corrupted
+    pub fn update_trash_blob_status(
+        &self,
+        md5: &str,
+        blob_missing: bool,
+        blob_corrupted: bool,
+    ) -> Result<usize, DfrError> {
+        let conn = self.pool.get()?;
+        let sql = "
+            UPDATE trash_info 
+            SET blob_missing = ?, blob_corrupted = ?
+            WHERE md5 = ? AND xdg_trash_path IS NULL";
+        Ok(conn.execute(sql, (blob_missing, blob_corrupted, md5))?)
+    }
+
+    /// Count trash info sharing the trash file with the md5 in trash path
+    pub fn count_trash_file_by_md5(&self, md5: &str) -> Result<u64, DfrError> {
+        let conn = self.pool.get()?;
+        let sql = "SELECT COUNT(*) FROM trash_info WHERE md5 = ? AND xdg_trash_path IS NULL";
+        Ok(conn.query_row(sql, [md5], |row| row.get(0))?)
+    }
+
     pub fn restore_trash_file_by_path(
         &self,
         trash_file_info: &TrashFileInfo,
@@ -942,4 +1683,619 @@ impl DatabaseManager {
         )?;
         Ok(())
     }
+
+    pub fn insert_audit_log(&self, audit_log: &AuditLog) -> Result<(), DfrError> {
+        let conn = self.pool.get()?;
+        let sql = "
+            INSERT INTO audit_log (time, user_name, client_ip, action, path, md5, size, success, message)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)";
+        conn.execute(
+            sql,
+            (
+                &audit_log.time,
+                &audit_log.user_name,
+                &audit_log.client_ip,
+                &audit_log.action,
+                &audit_log.path,
+                &audit_log.md5,
+                audit_log.size,
+                audit_log.success,
+                &audit_log.message,
+            ),
+        )?;
+        Ok(())
+    }
+
+    pub fn list_audit_logs(
+        &self,
+        query_list_params: &AuditLogListParams,
+    ) -> Result<AuditLogList, DfrError> {
+        let conn = self.pool.get()?;
+        let mut params: Vec<Arc<dyn ToSql>> = Vec::new();
+        let mut query_sql = String::from(" FROM audit_log WHERE 1=1");
+        if let Some(start_time) = query_list_params.start_time {
+            query_sql += " AND time >= ?";
+            params.push(Arc::new(start_time));
+        }
+        if let Some(end_time) = query_list_params.end_time {
+            query_sql += " AND time <= ?";
+            params.push(Arc::new(end_time));
+        }
+        if let Some(user_name) = query_list_params.user_name.clone() {
+            query_sql += " AND user_name = ?";
+            params.push(Arc::new(user_name));
+        }
+        if let Some(client_ip) = query_list_params.client_ip.clone() {
+            query_sql += " AND client_ip = ?";
+            params.push(Arc::new(client_ip));
+        }
+        if let Some(action) = query_list_params.action {
+            query_sql += " AND action = ?";
+            params.push(Arc::new(action));
+        }
+        if let Some(path) = query_list_params.path.clone() {
+            query_sql += " AND path LIKE ?";
+            params.push(Arc::new(format!("%{}%", path)));
+        }
+        if let Some(md5) = query_list_params.md5.clone() {
+            query_sql += " AND md5 = ?";
+            params.push(Arc::new(md5));
+        }
+        if let Some(success) = query_list_params.success {
+            query_sql += " AND success = ?";
+            params.push(Arc::new(success));
+        }
+
+        let count_sql = String::from("SELECT COUNT(*)") + &query_sql;
+        let total_count: u64 =
+            conn.query_row(&count_sql, params_from_iter(params.iter()), |row| {
+                row.get(0)
+            })?;
+
+        let sql = String::from(
+            "SELECT time, user_name, client_ip, action, path, md5, size, success, message",
+        ) + &query_sql
+            + " ORDER BY id DESC LIMIT ? OFFSET ?";
+        params.push(Arc::new(query_list_params.page_count));
+        params.push(Arc::new(
+            (query_list_params.page_no - 1) * query_list_params.page_count,
+        ));
+        debug!("List audit log query sql: {}", sql);
+
+        let mut stmt = conn.prepare(&sql)?;
+        let audit_log_iter = stmt.query_map(params_from_iter(params.iter()), |row| {
+            Ok(AuditLog {
+                time: row.get(0)?,
+                user_name: row.get(1)?,
+                client_ip: row.get(2)?,
+                action: row.get(3)?,
+                path: row.get(4)?,
+                md5: row.get(5)?,
+                size: row.get(6)?,
+                success: row.get(7)?,
+                message: row.get(8)?,
+            })
+        })?;
+        let mut audit_log_list = Vec::new();
+        for item in audit_log_iter {
+            audit_log_list.push(item?);
+        }
+        Ok(AuditLogList {
+            audit_log_list,
+            total_count,
+        })
+    }
+
+    fn user_info_from_row(row: &Row) -> Result<UserInfo> {
+        let allowed_paths: String = row.get(5)?;
+        Ok(UserInfo {
+            user_name: row.get(0)?,
+            password_hash: row.get(1)?,
+            role: row.get(2)?,
+            created_time: row.get(3)?,
+            modified_time: row.get(4)?,
+            allowed_paths: serde_json::from_str(allowed_paths.as_str()).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(5, Type::Text, Box::new(e))
+            })?,
+        })
+    }
+
+    /// Count users, only users of the role are counted if role is some
+    pub fn count_users(&self, role: Option<UserRole>) -> Result<u64, DfrError> {
+        let conn = self.pool.get()?;
+        let count = match role {
+            Some(role) => conn.query_row(
+                "SELECT COUNT(*) FROM user_info WHERE role = ?",
+                [role],
+                |row| row.get(0),
+            )?,
+            None => conn.query_row("SELECT COUNT(*) FROM user_info", [], |row| row.get(0))?,
+        };
+        Ok(count)
+    }
+
+    pub fn insert_user(&self, user_info: &UserInfo) -> Result<(), DfrError> {
+        let conn = self.pool.get()?;
+        let sql = format!(
+            "INSERT INTO user_info ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            USER_INFO_COLUMNS
+        );
+        conn.execute(
+            sql.as_str(),
+            (
+                &user_info.user_name,
+                &user_info.password_hash,
+                &user_info.role,
+                &user_info.created_time,
+                &user_info.modified_time,
+                serde_json::to_string(&user_info.allowed_paths)?,
+            ),
+        )?;
+        Ok(())
+    }
+
+    pub fn get_user_by_name(&self, user_name: &str) -> Result<Option<UserInfo>, DfrError> {
+        let conn = self.pool.get()?;
+        let sql = format!(
+            "SELECT {} FROM user_info WHERE user_name = ?",
+            USER_INFO_COLUMNS
+        );
+        let mut stmt = conn.prepare(sql.as_str())?;
+        let mut user_info_iter = stmt.query_map([user_name], Self::user_info_from_row)?;
+        match user_info_iter.next() {
+            Some(user_info) => Ok(Some(user_info?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn list_users(&self) -> Result<UserInfoList, DfrError> {
+        let conn = self.pool.get()?;
+        let sql = format!("SELECT {} FROM user_info ORDER BY id", USER_INFO_COLUMNS);
+        let mut stmt = conn.prepare(sql.as_str())?;
+        let user_info_iter = stmt.query_map([], Self::user_info_from_row)?;
+        let mut user_list = Vec::new();
+        for item in user_info_iter {
+            user_list.push(item?);
+        }
+        let total_count = user_list.len() as u64;
+        Ok(UserInfoList {
+            user_list,
+            total_count,
+        })
+    }
+
+    /// Update user, the user name can be changed as well
+    pub fn update_user(&self, user_name: &str, user_info: &UserInfo) -> Result<usize, DfrError> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+        let sql = "
+            UPDATE user_info
+            SET user_name = ?1, password_hash = ?2, role = ?3, modified_time = ?4, allowed_paths = ?5
+            WHERE user_name = ?6";
+        let count = tx.execute(
+            sql,
+            (
+                &user_info.user_name,
+                &user_info.password_hash,
+                &user_info.role,
+                &user_info.modified_time,
+                serde_json::to_string(&user_info.allowed_paths)?,
+                user_name,
+            ),
+        )?;
+        // api tokens and TOTP follow the renamed user
+        tx.execute(
+            "UPDATE api_token SET user_name = ?1 WHERE user_name = ?2",
+            (&user_info.user_name, user_name),
+        )?;
+        tx.execute(
+            "UPDATE user_totp SET user_name = ?1 WHERE user_name = ?2",
+            (&user_info.user_name, user_name),
+        )?;
+        tx.commit()?;
+        Ok(count)
+    }
+
+    pub fn remove_user(&self, user_name: &str) -> Result<usize, DfrError> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+        let count = tx.execute("DELETE FROM user_info WHERE user_name = ?", [user_name])?;
+        tx.execute("DELETE FROM api_token WHERE user_name = ?", [user_name])?;
+        tx.execute("DELETE FROM user_totp WHERE user_name = ?", [user_name])?;
+        tx.commit()?;
+        Ok(count)
+    }
+
+    fn api_token_from_row(row: &Row) -> Result<ApiToken> {
+        let scopes: String = row.get(4)?;
+        Ok(ApiToken {
+            id: row.get(0)?,
+            user_name: row.get(1)?,
+            name: row.get(2)?,
+            token_hash: row.get(3)?,
+            scopes: serde_json::from_str(scopes.as_str()).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(4, Type::Text, Box::new(e))
+            })?,
+            created_time: row.get(5)?,
+            expire_time: row.get(6)?,
+            last_used_time: row.get(7)?,
+            revoked: row.get(8)?,
+        })
+    }
+
+    /// Insert api token, return the id of the token
+    pub fn insert_api_token(&self, api_token: &ApiToken) -> Result<i64, DfrError> {
+        let conn = self.pool.get()?;
+        let sql = "
+            INSERT INTO api_token (user_name, name, token_hash, scopes, created_time, expire_time, revoked)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)";
+        conn.execute(
+            sql,
+            (
+                &api_token.user_name,
+                &api_token.name,
+                &api_token.token_hash,
+                serde_json::to_string(&api_token.scopes)?,
+                &api_token.created_time,
+                &api_token.expire_time,
+                api_token.revoked,
+            ),
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn get_api_token_by_hash(&self, token_hash: &str) -> Result<Option<ApiToken>, DfrError> {
+        let conn = self.pool.get()?;
+        let sql = format!(
+            "SELECT {} FROM api_token WHERE token_hash = ?",
+            API_TOKEN_COLUMNS
+        );
+        let mut stmt = conn.prepare(sql.as_str())?;
+        let mut api_token_iter = stmt.query_map([token_hash], Self::api_token_from_row)?;
+        match api_token_iter.next() {
+            Some(api_token) => Ok(Some(api_token?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn list_api_tokens(&self, user_name: &str) -> Result<ApiTokenList, DfrError> {
+        let conn = self.pool.get()?;
+        let sql = format!(
+            "SELECT {} FROM api_token WHERE user_name = ? ORDER BY id DESC",
+            API_TOKEN_COLUMNS
+        );
+        let mut stmt = conn.prepare(sql.as_str())?;
+        let api_token_iter = stmt.query_map([user_name], Self::api_token_from_row)?;
+        let mut api_token_list = Vec::new();
+        for item in api_token_iter {
+            api_token_list.push(item?);
+        }
+        let total_count = api_token_list.len() as u64;
+        Ok(ApiTokenList {
+            api_token_list,
+            total_count,
+        })
+    }
+
+    /// Revoke api token of the user, return the count of revoked tokens
+    pub fn revoke_api_token(&self, user_name: &str, id: i64) -> Result<usize, DfrError> {
+        let conn = self.pool.get()?;
+        let count = conn.execute(
+            "UPDATE api_token SET revoked = 1 WHERE user_name = ? AND id = ?",
+            (user_name, id),
+        )?;
+        Ok(count)
+    }
+
+    pub fn update_api_token_last_used_time(
+        &self,
+        id: i64,
+        last_used_time: &DateTime<Local>,
+    ) -> Result<(), DfrError> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE api_token SET last_used_time = ? WHERE id = ?",
+            (last_used_time, id),
+        )?;
+        Ok(())
+    }
+
+    fn user_totp_from_row(row: &Row) -> Result<UserTotp> {
+        let recovery_code_hashes: String = row.get(2)?;
+        Ok(UserTotp {
+            user_name: row.get(0)?,
+            secret: row.get(1)?,
+            recovery_code_hashes: serde_json::from_str(recovery_code_hashes.as_str()).map_err(
+                |e| rusqlite::Error::FromSqlConversionFailure(2, Type::Text, Box::new(e)),
+            )?,
+            last_step: row.get(3)?,
+            created_time: row.get(4)?,
+        })
+    }
+
+    pub fn get_user_totp(&self, user_name: &str) -> Result<Option<UserTotp>, DfrError> {
+        let conn = self.pool.get()?;
+        let sql = format!(
+            "SELECT {} FROM user_totp WHERE user_name = ?",
+            USER_TOTP_COLUMNS
+        );
+        let mut stmt = conn.prepare(sql.as_str())?;
+        let mut user_totp_iter = stmt.query_map([user_name], Self::user_totp_from_row)?;
+        match user_totp_iter.next() {
+            Some(user_totp) => Ok(Some(user_totp?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Insert or replace the TOTP of the user
+    pub fn save_user_totp(&self, user_totp: &UserTotp) -> Result<(), DfrError> {
+        let conn = self.pool.get()?;
+        let sql = format!(
+            "INSERT OR REPLACE INTO user_totp ({}) VALUES (?1, ?2, ?3, ?4, ?5)",
+            USER_TOTP_COLUMNS
+        );
+        conn.execute(
+            sql.as_str(),
+            (
+                &user_totp.user_name,
+                &user_totp.secret,
+                serde_json::to_string(&user_totp.recovery_code_hashes)?,
+                user_totp.last_step,
+                &user_totp.created_time,
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Accept a TOTP code of the time step, return 0 if a code of this or a later step was
+    /// accepted before, so that a code can not be replayed
+    pub fn update_user_totp_last_step(
+        &self,
+        user_name: &str,
+        step: i64,
+    ) -> Result<usize, DfrError> {
+        let conn = self.pool.get()?;
+        let count = conn.execute(
+            "UPDATE user_totp SET last_step = ?1 WHERE user_name = ?2 AND last_step < ?1",
+            (step, user_name),
+        )?;
+        Ok(count)
+    }
+
+    /// Remove a used recovery code, return 0 if the code is not found
+    pub fn remove_user_totp_recovery_code(
+        &self,
+        user_name: &str,
+        recovery_code_hash: &str,
+    ) -> Result<usize, DfrError> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+        let user_totp = {
+            let sql = format!(
+                "SELECT {} FROM user_totp WHERE user_name = ?",
+                USER_TOTP_COLUMNS
+            );
+            let mut stmt = tx.prepare(sql.as_str())?;
+            let mut user_totp_iter = stmt.query_map([user_name], Self::user_totp_from_row)?;
+            match user_totp_iter.next() {
+                Some(user_totp) => user_totp?,
+                None => return Ok(0),
+            }
+        };
+        let recovery_code_hashes: Vec<&String> = user_totp
+            .recovery_code_hashes
+            .iter()
+            .filter(|hash| hash.as_str() != recovery_code_hash)
+            .collect();
+        if recovery_code_hashes.len() == user_totp.recovery_code_hashes.len() {
+            return Ok(0);
+        }
+        tx.execute(
+            "UPDATE user_totp SET recovery_code_hashes = ? WHERE user_name = ?",
+            (serde_json::to_string(&recovery_code_hashes)?, user_name),
+        )?;
+        tx.commit()?;
+        Ok(1)
+    }
+
+    pub fn remove_user_totp(&self, user_name: &str) -> Result<usize, DfrError> {
+        let conn = self.pool.get()?;
+        let count = conn.execute("DELETE FROM user_totp WHERE user_name = ?", [user_name])?;
+        Ok(count)
+    }
+
+    pub fn insert_scan_history(&self, scan_history: &ScanHistory) -> Result<(), DfrError> {
+        let conn = self.pool.get()?;
+        let sql = format!(
+            "INSERT INTO scan_history ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            SCAN_HISTORY_COLUMNS
+        );
+        conn.execute(
+            sql.as_str(),
+            (
+                scan_history.version,
+                &scan_history.scan_path,
+                &scan_history.start_time,
+                &scan_history.end_time,
+                scan_history.scanned_file_count,
+                scan_history.success,
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Last finished scan, none if no scan is recorded
+    pub fn get_last_scan_history(&self) -> Result<Option<ScanHistory>, DfrError> {
+        let conn = self.pool.get()?;
+        let sql = format!(
+            "SELECT {} FROM scan_history ORDER BY end_time DESC, rowid DESC LIMIT 1",
+            SCAN_HISTORY_COLUMNS
+        );
+        let mut stmt = conn.prepare(sql.as_str())?;
+        let mut scan_history_iter = stmt.query_map([], |row| {
+            Ok(ScanHistory {
+                version: row.get(0)?,
+                scan_path: row.get(1)?,
+                start_time: row.get(2)?,
+                end_time: row.get(3)?,
+                scanned_file_count: row.get(4)?,
+                success: row.get(5)?,
+            })
+        })?;
+        match scan_history_iter.next() {
+            Some(scan_history) => Ok(Some(scan_history?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Statistics of the indexed files, only files in or under the allowed paths are counted if
+    /// allowed paths is not empty. The statistics are cached until the next scan finishes, as
+    /// they need to read all files, except trash usage which is read each time.
+    pub fn get_file_stats(&self, allowed_paths: &[String]) -> Result<FileStats, DfrError> {
+        let last_scan = self.get_last_scan_history()?;
+        let last_scan_key = last_scan
+            .as_ref()
+            .map(|scan_history| (scan_history.version, scan_history.end_time));
+        let is_current = |file_stats: &FileStats| {
+            file_stats
+                .last_scan
+                .as_ref()
+                .map(|scan_history| (scan_history.version, scan_history.end_time))
+                == last_scan_key
+        };
+        let cached_file_stats = match self.stats_cache.lock() {
+            Ok(cache) => cache.get(allowed_paths).filter(|s| is_current(s)).cloned(),
+            Err(_) => None,
+        };
+        let mut file_stats = match cached_file_stats {
+            Some(file_stats) => file_stats,
+            None => {
+                let mut file_stats = self.compute_file_stats(allowed_paths)?;
+                file_stats.scan_version = last_scan.as_ref().map_or(0, |s| s.version);
+                file_stats.last_scan_duration_s = last_scan.as_ref().map(ScanHistory::duration_s);
+                file_stats.last_scan = last_scan;
+                if let Ok(mut cache) = self.stats_cache.lock() {
+                    // statistics of previous scans are never read again
+                    cache.retain(|_, s| is_current(s));
+                    cache.insert(allowed_paths.to_vec(), file_stats.clone());
+                }
+                file_stats
+            }
+        };
+        file_stats.trash = self.get_trash_stats(allowed_paths)?;
+        Ok(file_stats)
+    }
+
+    fn compute_file_stats(&self, allowed_paths: &[String]) -> Result<FileStats, DfrError> {
+        let conn = self.pool.get()?;
+        let mut inode_params: Vec<Arc<dyn ToSql>> = Vec::new();
+        let mut inode_filter_sql = String::new();
+        if !allowed_paths.is_empty() {
+            inode_filter_sql += " AND id IN (SELECT inode_info_id FROM file_info WHERE 1=1";
+            Self::push_dir_paths_filter(
+                "dir_path",
+                allowed_paths,
+                &mut inode_filter_sql,
+                &mut inode_params,
+            );
+            inode_filter_sql += ")";
+        }
+        let mut file_stats = FileStats::default();
+        let sql = format!(
+            "SELECT COALESCE(SUM(size), 0) FROM inode_info WHERE 1=1{}",
+            inode_filter_sql
+        );
+        file_stats.total_size =
+            conn.query_row(sql.as_str(), params_from_iter(inode_params.iter()), |row| {
+                row.get(0)
+            })?;
+        let sql = format!(
+            "SELECT COUNT(*), COALESCE(SUM(md5_count), 0), COALESCE(SUM(size * (md5_count - 1)), 0)
+            FROM (
+                SELECT COUNT(*) AS md5_count, MAX(size) AS size FROM inode_info
+                WHERE 1=1{} GROUP BY md5 HAVING COUNT(*) > 1
+            )",
+            inode_filter_sql
+        );
+        conn.query_row(sql.as_str(), params_from_iter(inode_params.iter()), |row| {
+            file_stats.duplicate_group_count = row.get(0)?;
+            file_stats.duplicate_file_count = row.get(1)?;
+            file_stats.redundant_size = row.get(2)?;
+            Ok(())
+        })?;
+
+        // files with the md5 count of their inode, md5 are counted the same as in list files
+        let mut file_params = inode_params.clone();
+        let mut file_query_sql = format!(
+            " FROM file_info AS a2
+            JOIN inode_info AS a1 ON a1.id = a2.inode_info_id
+            JOIN (SELECT md5, COUNT(*) AS md5_count FROM inode_info WHERE 1=1{} GROUP BY md5) AS a3
+            ON a3.md5 = a1.md5
+            WHERE 1=1",
+            inode_filter_sql
+        );
+        Self::push_dir_paths_filter(
+            "a2.dir_path",
+            allowed_paths,
+            &mut file_query_sql,
+            &mut file_params,
+        );
+        let sql = format!("SELECT COUNT(*){}", file_query_sql);
+        file_stats.total_file_count =
+            conn.query_row(sql.as_str(), params_from_iter(file_params.iter()), |row| {
+                row.get(0)
+            })?;
+        let query_buckets = |name_sql: &str, order_by: &str, limit: i64| {
+            let sql = format!(
+                "SELECT {} AS name, COUNT(*), COALESCE(SUM(a1.size), 0) AS total_size,
+                    SUM(CASE WHEN a3.md5_count > 1 THEN 1 ELSE 0 END),
+                    COALESCE(SUM(CASE WHEN a3.md5_count > 1 THEN a1.size ELSE 0 END), 0) AS duplicate_size
+                {} GROUP BY name ORDER BY {} LIMIT {}",
+                name_sql, file_query_sql, order_by, limit
+            );
+            let mut stmt = conn.prepare(sql.as_str())?;
+            let bucket_iter = stmt.query_map(params_from_iter(file_params.iter()), |row| {
+                Ok(StatsBucket {
+                    name: row.get(0)?,
+                    file_count: row.get(1)?,
+                    size: row.get(2)?,
+                    duplicate_file_count: row.get(3)?,
+                    duplicate_size: row.get(4)?,
+                })
+            })?;
+            let mut buckets = Vec::new();
+            for bucket in bucket_iter {
+                buckets.push(bucket?);
+            }
+            Ok::<_, DfrError>(buckets)
+        };
+        file_stats.extensions = query_buckets(
+            "COALESCE(NULLIF(a2.file_extension, ''), '(none)')",
+            "duplicate_size DESC, total_size DESC, name",
+            STATS_TOP_COUNT,
+        )?;
+        file_stats.size_buckets = query_buckets(STATS_SIZE_BUCKET_SQL, "MIN(a1.size)", i64::MAX)?;
+        file_stats.top_directories = query_buckets(
+            "a2.dir_path",
+            "duplicate_size DESC, total_size DESC, name",
+            STATS_TOP_COUNT,
+        )?;
+        Ok(file_stats)
+    }
+
+    /// Trash files restored to the allowed paths, all trash files if allowed paths is empty
+    fn get_trash_stats(&self, allowed_paths: &[String]) -> Result<TrashStats, DfrError> {
+        let conn = self.pool.get()?;
+        let mut params: Vec<Arc<dyn ToSql>> = Vec::new();
+        let mut sql =
+            String::from("SELECT COUNT(*), COALESCE(SUM(size), 0) FROM trash_info WHERE 1=1");
+        Self::push_dir_paths_filter("dir_path", allowed_paths, &mut sql, &mut params);
+        let trash_stats = conn.query_row(sql.as_str(), params_from_iter(params.iter()), |row| {
+            Ok(TrashStats {
+                file_count: row.get(0)?,
+                size: row.get(1)?,
+            })
+        })?;
+        Ok(trash_stats)
+    }
 }
@@ -0,0 +1,129 @@
+use std::{
+    fmt::Display,
+    path::{Component, Path},
+    str::FromStr,
+};
+
+use chrono::{DateTime, Local};
+use rusqlite::{
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+    ToSql,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// User role, a role has all permissions of the lower roles
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    /// List files and trash files only
+    Viewer,
+    /// Scan, move files to trash and restore trash files
+    Operator,
+    /// Delete files permanently, manage settings and users
+    Admin,
+}
+
+impl UserRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::Viewer => "viewer",
+            UserRole::Operator => "operator",
+            UserRole::Admin => "admin",
+        }
+    }
+}
+
+impl Display for UserRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for UserRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(UserRole::Viewer),
+            "operator" => Ok(UserRole::Operator),
+            "admin" => Ok(UserRole::Admin),
+            _ => Err(format!("Unknown user role: {}", s)),
+        }
+    }
+}
+
+impl ToSql for UserRole {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(self.as_str().into())
+    }
+}
+
+impl FromSql for UserRole {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
+
+/// User account
+#[derive(Serialize, ToSchema, Clone)]
+pub struct UserInfo {
+    /// User name, used to login
+    pub user_name: String,
+    /// Argon2id hash of the password in PHC string format, never serialized
+    #[serde(skip)]
+    pub password_hash: String,
+    /// User role
+    pub role: UserRole,
+    /// Dir paths the user is allowed to list, scan, delete and restore files in, all paths are
+    /// allowed if empty
+    pub allowed_paths: Vec<String>,
+    /// Created time
+    pub created_time: DateTime<Local>,
+    /// Modified time
+    pub modified_time: DateTime<Local>,
+}
+
+impl std::fmt::Debug for UserInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never print password hash
+        f.debug_struct("UserInfo")
+            .field("user_name", &self.user_name)
+            .field("role", &self.role)
+            .field("allowed_paths", &self.allowed_paths)
+            .field("created_time", &self.created_time)
+            .field("modified_time", &self.modified_time)
+            .finish_non_exhaustive()
+    }
+}
+
+impl UserInfo {
+    /// Check if the path is under one of the allowed paths of the user. Paths with `..` are never
+    /// allowed for users with allowed paths, as they may escape from the allowed paths.
+    pub fn is_path_allowed(&self, path: &Path) -> bool {
+        if self.allowed_paths.is_empty() {
+            return true;
+        }
+        if path
+            .components()
+            .any(|component| component == Component::ParentDir)
+        {
+            return false;
+        }
+        self.allowed_paths
+            .iter()
+            .any(|allowed_path| path.starts_with(allowed_path))
+    }
+}
+
+/// User list
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserInfoList {
+    /// User list
+    pub user_list: Vec<UserInfo>,
+    /// Total user count
+    pub total_count: u64,
+}
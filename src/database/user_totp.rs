@@ -0,0 +1,14 @@
+use chrono::{DateTime, Local};
+
+/// TOTP second factor of a user, only exists after the enrollment is confirmed
+#[derive(Clone)]
+pub struct UserTotp {
+    pub user_name: String,
+    /// Base32 encoded TOTP secret
+    pub secret: String,
+    /// SHA-256 hashes of the unused recovery codes
+    pub recovery_code_hashes: Vec<String>,
+    /// Time step of the last accepted code, a code can only be used once
+    pub last_step: i64,
+    pub created_time: DateTime<Local>,
+}
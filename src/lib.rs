@@ -3,45 +3,64 @@ pub mod database;
 pub mod model;
 pub mod utils;
 
-use std::{env, fs, ops::Deref, path::PathBuf};
+use std::{env, fs, ops::Deref, path::PathBuf, sync::Arc};
 
 use actix_server::Server;
-use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+use actix_session::{config::PersistentSession, storage::CookieSessionStore, SessionMiddleware};
 use actix_web::{
-    cookie::Key,
+    cookie::time::Duration,
     error,
-    middleware::{from_fn, Logger},
+    middleware::{from_fn, Condition, Logger},
     web::{self},
     App, HttpResponse, HttpServer,
 };
-use clap::Parser;
 use log::{info, warn};
 
 use controller::{
+    api_token::{create_api_token, list_api_tokens, revoke_api_token},
+    audio_fingerprint::list_similar_audio,
+    audit::{export_audit_logs, list_audit_logs},
+    dir_tree::query_dir_tree,
+    duplicate_dir::{delete_dir, list_duplicate_dirs},
+    export::export_duplicate_files,
     files::{delete_file, delete_files, list_files, query_list_settings},
-    login::{change_password, get_captcha, login_account, logout_account},
+    image_hash::list_similar_images,
+    login::{change_password, list_locked_logins, login_account, login_totp, logout_account},
+    oidc::{login_oidc, login_oidc_callback},
+    report::download_duplicate_report,
     scan::{query_scan_settings, query_scan_status, start_scan, stop_scan},
     settings::{query_settings, update_settings},
+    stats::query_file_stats,
+    totp::{confirm_totp, disable_totp, enroll_totp, query_totp_status},
     trash::{
-        delete_trash_file, delete_trash_files, list_trash_files, query_trash_list_settings,
-        setup_remove_trash_file_timer, restore_trash_file, restore_trash_files,
+        check_trash, delete_trash_file, delete_trash_files, list_trash_files,
+        query_trash_list_settings, setup_check_trash_timer, setup_remove_trash_file_timer,
+        restore_trash_file, restore_trash_files,
+    },
+    user::{
+        add_user, delete_user, get_current_user, get_notices, init_admin_user, list_users,
+        reject_anonymous_users, update_user,
     },
-    user::{get_current_user, get_notices, reject_anonymous_users},
 };
 use database::sqlite::PoolDatabaseManager;
 use model::{
+    cli::Command,
     common::{ErrorCode, RestResponse},
+    login::SharedLoginLimiter,
     scan::SharedScanStatus,
-    settings::{Args, Settings, UserSettings},
+    settings::{Args, Settings},
 };
 use tokio::sync::Mutex;
-use utils::{error::DfrError, network::check_ipv6_available};
+use utils::{
+    error::DfrError,
+    network::check_ipv6_available,
+    tls::{redirect_to_https, setup_reload_tls_timer, tls_server_config, ReloadableCertResolver},
+};
 use utoipa_actix_web::AppExt;
 use utoipa_rapidoc::RapiDoc;
 use utoipa_redoc::{Redoc, Servable as _};
 use utoipa_scalar::{Scalar, Servable as _};
 use utoipa_swagger_ui::SwaggerUi;
-use uuid::Uuid;
 
 pub struct SharedSettings(pub Mutex<Settings>);
 
@@ -58,8 +77,24 @@ impl Deref for SharedSettings {
     }
 }
 
-pub async fn run() -> Result<Server, DfrError> {
-    let args = Args::parse();
+/// Socket address for logs, with brackets around ipv6 addresses
+fn format_addr(addr: &str, port: u16) -> String {
+    if addr.contains(':') {
+        format!("[{}]:{}", addr, port)
+    } else {
+        format!("{}:{}", addr, port)
+    }
+}
+
+/// Run a headless subcommand instead of the web server
+pub async fn run_command(args: Args, command: Command) -> Result<(), DfrError> {
+    let settings = Settings::new(&args)?;
+    // only warnings by default, the results are printed to stdout
+    env_logger::init_from_env(env_logger::Env::new().default_filter_or("warn"));
+    controller::cli::run_command(settings, command, args.json).await
+}
+
+pub async fn run(args: Args) -> Result<Server, DfrError> {
     let settings = Settings::new(&args)?;
 
     env_logger::init_from_env(
@@ -87,32 +122,44 @@ pub async fn run() -> Result<Server, DfrError> {
     }
     info!("Trash path: {:?}", trash_path);
 
-    let secret_key = Key::generate();
-
     let database_manager = PoolDatabaseManager::new(&settings.system.db_path)?;
     database_manager.create_tables()?;
     // Create shared scan status for scan progress tracking
     let scan_status_data = web::Data::new(SharedScanStatus::new());
+    // Create shared login limiter against brute-force login attempts
+    let login_limiter = web::Data::new(SharedLoginLimiter::new());
     let shared_settings = web::Data::new(SharedSettings::from(settings.clone()));
 
     // check user and passwd
-    let passwd = {
-        let settings= shared_settings.lock().await;
-        settings.user.login_password.clone()
+    let (secret_key, session_system_settings) = {
+        let mut settings = shared_settings.lock().await;
+        settings.init_login_password()?;
+        init_admin_user(&database_manager, &settings.user)?;
+        (settings.init_session_key()?, settings.system.clone())
     };
-    if passwd == UserSettings::default().login_password {
-        warn!("Password need to change");
-        let random_uuid = Uuid::new_v4();
-        let uuid_str = String::from(random_uuid);
-        let new_password = &uuid_str[..6];
-        let mut settings= shared_settings.lock().await;
-        settings.user.login_password = String::from(new_password);
-        info!("New random password: {}", new_password);
-        settings.save()?;
-    }
+    // sessions are checked against idle and absolute timeouts on each request, the cookie only
+    // needs to live as long as the longest session
+    let session_ttl = session_system_settings
+        .session_remember_me_s
+        .max(session_system_settings.session_absolute_timeout_s);
 
     //setup remove trash file timer
     setup_remove_trash_file_timer(shared_settings.clone(), database_manager.clone()).await?;
+    //setup check trash file timer
+    setup_check_trash_timer(shared_settings.clone(), database_manager.clone()).await?;
+    // serve HTTPS if a certificate is configured, the certificate is reloaded when it is renewed
+    let tls_config = if settings.system.tls_cert_path.is_empty() {
+        None
+    } else {
+        let cert_resolver = Arc::new(ReloadableCertResolver::new(
+            settings.system.tls_cert_path.as_str(),
+            settings.system.tls_key_path.as_str(),
+        )?);
+        setup_reload_tls_timer(cert_resolver.clone(), settings.system.tls_reload_interval_s)
+            .await?;
+        Some(tls_server_config(&settings.system, cert_resolver)?)
+    };
+    let redirect_to_https_enabled = tls_config.is_some() && settings.system.tls_redirect_port != 0;
     // Start the server
     let mut http_server = HttpServer::new(move || {
         App::new()
@@ -121,6 +168,7 @@ pub async fn run() -> Result<Server, DfrError> {
             .app_data(web::Data::new(database_manager.clone()))
             .app_data(shared_settings.clone())
             .app_data(scan_status_data.clone())
+            .app_data(login_limiter.clone())
             .app_data(
                 web::JsonConfig::default()
                     .limit(4096 * 1024 << 2)
@@ -141,7 +189,9 @@ pub async fn run() -> Result<Server, DfrError> {
             .service(logout_account)
             .service(get_current_user)
             .service(get_notices)
-            .service(get_captcha)
+            .service(login_totp)
+            .service(login_oidc)
+            .service(login_oidc_callback)
             .service(
                 // need to login for these routes
                 utoipa_actix_web::scope("/api/dfr")
@@ -150,6 +200,14 @@ pub async fn run() -> Result<Server, DfrError> {
                     .service(stop_scan)
                     .service(query_scan_status)
                     .service(list_files)
+                    .service(export_duplicate_files)
+                    .service(download_duplicate_report)
+                    .service(query_file_stats)
+                    .service(list_duplicate_dirs)
+                    .service(delete_dir)
+                    .service(query_dir_tree)
+                    .service(list_similar_images)
+                    .service(list_similar_audio)
                     .service(delete_file)
                     .service(delete_files)
                     .service(list_trash_files)
@@ -157,12 +215,27 @@ pub async fn run() -> Result<Server, DfrError> {
                     .service(delete_trash_files)
                     .service(restore_trash_file)
                     .service(restore_trash_files)
+                    .service(check_trash)
                     .service(change_password)
                     .service(query_settings)
                     .service(update_settings)
                     .service(query_scan_settings)
                     .service(query_list_settings)
-                    .service(query_trash_list_settings),
+                    .service(query_trash_list_settings)
+                    .service(list_audit_logs)
+                    .service(export_audit_logs)
+                    .service(list_users)
+                    .service(add_user)
+                    .service(update_user)
+                    .service(delete_user)
+                    .service(list_api_tokens)
+                    .service(create_api_token)
+                    .service(revoke_api_token)
+                    .service(list_locked_logins)
+                    .service(query_totp_status)
+                    .service(enroll_totp)
+                    .service(confirm_totp)
+                    .service(disable_totp),
             )
             .openapi_service(|api| {
                 SwaggerUi::new("/swagger-ui/{_:.*}").url("/api/openapi.json", api)
@@ -173,30 +246,52 @@ pub async fn run() -> Result<Server, DfrError> {
             .into_app()
             .wrap(
                 SessionMiddleware::builder(CookieSessionStore::default(), secret_key.clone())
-                    .cookie_secure(false)
+                    .cookie_secure(session_system_settings.session_cookie_secure)
+                    .cookie_same_site(session_system_settings.session_cookie_same_site.into())
+                    .cookie_http_only(true)
+                    .session_lifecycle(
+                        PersistentSession::default()
+                            .session_ttl(Duration::seconds(session_ttl as i64)),
+                    )
                     .build(),
             )
+            .wrap(Condition::new(
+                redirect_to_https_enabled,
+                from_fn(redirect_to_https),
+            ))
             .service(
                 actix_files::Files::new("/", static_file_path.clone()).index_file("index.html"),
             )
     });
 
-    if settings.system.enable_ipv6 && check_ipv6_available() {
-        let addr = format!(
-            "{}:{}",
-            settings.system.listen_addr_ipv6, settings.system.port
-        );
-        http_server = http_server.bind(addr.as_str())?;
-        info!("Server started at http://{}", addr);
+    let listen_addr = if settings.system.enable_ipv6 && check_ipv6_available() {
+        settings.system.listen_addr_ipv6.as_str()
     } else {
-        http_server = http_server.bind((
-            settings.system.listen_addr_ipv4.as_str(),
-            settings.system.port,
-        ))?;
-        info!(
-            "Server started at http://{}:{}",
-            settings.system.listen_addr_ipv4, settings.system.port
-        );
+        settings.system.listen_addr_ipv4.as_str()
+    };
+    match tls_config {
+        Some(tls_config) => {
+            http_server =
+                http_server.bind_rustls_0_23((listen_addr, settings.system.port), tls_config)?;
+            info!(
+                "Server started at https://{}",
+                format_addr(listen_addr, settings.system.port)
+            );
+            if redirect_to_https_enabled {
+                http_server = http_server.bind((listen_addr, settings.system.tls_redirect_port))?;
+                info!(
+                    "Redirect http://{} to https",
+                    format_addr(listen_addr, settings.system.tls_redirect_port)
+                );
+            }
+        }
+        None => {
+            http_server = http_server.bind((listen_addr, settings.system.port))?;
+            info!(
+                "Server started at http://{}",
+                format_addr(listen_addr, settings.system.port)
+            );
+        }
     }
 
     Ok(http_server.run())
@@ -1,8 +1,22 @@
+use clap::Parser;
+use dup_file_remover::model::{cli::Command, settings::Args};
 use log::{error, info};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let run_result = dup_file_remover::run().await;
+    let args = Args::parse();
+    if let Some(command) = args
+        .command
+        .clone()
+        .filter(|c| !matches!(c, Command::Serve))
+    {
+        if let Err(e) = dup_file_remover::run_command(args, command).await {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let run_result = dup_file_remover::run(args).await;
     match run_result {
         Ok(server) => {
             info!("Server started successfully");
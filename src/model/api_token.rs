@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::database::api_token::TokenScope;
+
+/// Create api token request
+#[derive(Deserialize, ToSchema)]
+pub struct CreateApiTokenRequest {
+    /// Token name, to tell the tokens apart
+    pub name: String,
+    /// Token scopes
+    pub scopes: Vec<TokenScope>,
+    /// Days until the token expires, the token never expires if none
+    pub expire_days: Option<u32>,
+}
+
+/// Created api token, the token itself is only returned once
+#[derive(Serialize, ToSchema)]
+pub struct CreateApiTokenResult {
+    /// Token id
+    pub id: i64,
+    /// Token, sent as `Authorization: Bearer <token>` header
+    pub token: String,
+}
+
+/// Revoke api token request
+#[derive(Deserialize, ToSchema)]
+pub struct RevokeApiTokenRequest {
+    /// Token id
+    pub id: i64,
+}
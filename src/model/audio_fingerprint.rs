@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Similarity of tracks listed by default
+pub const DEFAULT_MIN_AUDIO_SIMILARITY: f64 = 0.85;
+
+/// Query parameters for listing similar audio tracks.
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams, ToSchema)]
+pub struct SimilarAudioListParams {
+    /// Page number, start from 1
+    pub page_no: i64,
+    /// Page count, must be greater than 0
+    pub page_count: i64,
+    /// Only list groups with a file whose dir path contains this
+    pub dir_path: Option<String>,
+    /// Minimum share of equal fingerprint bits of similar tracks, from 0 to 1, 0.85 by default
+    pub min_similarity: Option<f64>,
+}
+
+/// Audio content in a group of similar tracks, with all files having the content
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct SimilarAudio {
+    pub md5: String,
+    pub duration_s: f64,
+    pub codec: String,
+    /// Whether the codec keeps the decoded samples unchanged, e.g. flac, alac or pcm
+    pub lossless: bool,
+    /// Average bits per second of the file
+    pub bitrate: u64,
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub bit_depth: Option<u32>,
+    pub size: u64,
+    /// Share of equal fingerprint bits with the suggested keeper of the group
+    pub similarity: f64,
+    pub files: Vec<String>,
+}
+
+/// Tracks similar to each other, directly or through other tracks of the group, the suggested
+/// keeper first
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct SimilarAudioGroup {
+    /// Md5 of the track with the best quality, lossless first, then by bitrate and sample rate
+    pub keeper_md5: String,
+    pub tracks: Vec<SimilarAudio>,
+    /// Size of all files in the group
+    pub total_size: u64,
+}
+
+/// Similar audio groups with total count
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SimilarAudioGroupList {
+    pub similar_audio_group_list: Vec<SimilarAudioGroup>,
+    pub total_count: u64,
+}
@@ -0,0 +1,46 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::database::audit_log::AuditAction;
+
+/// Query parameters for listing audit logs.
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams, ToSchema)]
+pub struct AuditLogListParams {
+    /// Page number, start from 1
+    pub page_no: i64,
+    /// Page count, must be greater than 0
+    pub page_count: i64,
+    /// Optional time range filter for the action.
+    pub start_time: Option<DateTime<Local>>,
+    pub end_time: Option<DateTime<Local>>,
+    /// User name filtering
+    pub user_name: Option<String>,
+    /// Client ip filtering
+    pub client_ip: Option<String>,
+    /// Action filtering
+    pub action: Option<AuditAction>,
+    /// File path filtering
+    pub path: Option<String>,
+    /// File md5 filtering
+    pub md5: Option<String>,
+    /// Optional result filtering
+    pub success: Option<bool>,
+}
+
+impl Default for AuditLogListParams {
+    fn default() -> Self {
+        Self {
+            page_no: 1,
+            page_count: 20,
+            start_time: None,
+            end_time: None,
+            user_name: None,
+            client_ip: None,
+            action: None,
+            path: None,
+            md5: None,
+            success: None,
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Local};
+use clap::{Args as ClapArgs, Subcommand, ValueEnum};
+use serde::Serialize;
+
+use crate::{
+    database::{file_info::FileInfo, user_info::UserRole},
+    model::{
+        common::FailedFile,
+        export::ExportFormat,
+        report::DEFAULT_REPORT_TOP_COUNT,
+        settings::{FileHashType, ListSettings},
+    },
+};
+
+/// Subcommands of the binary, the web server is started if none is given
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Start the web server
+    Serve,
+    /// Scan paths for duplicate files
+    Scan(ScanArgs),
+    /// List duplicate files
+    List(ListArgs),
+    /// Export duplicate files grouped by md5
+    Export(ExportArgs),
+    /// Write a report of duplicate files as a single html file
+    Report(ReportArgs),
+    /// Remove duplicate files, keeping one file of each duplicate group chosen by the policy
+    Dedupe(DedupeArgs),
+    /// Manage trash files
+    #[command(subcommand)]
+    Trash(TrashCommand),
+    /// Manage users
+    #[command(subcommand)]
+    User(UserCommand),
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct ScanArgs {
+    /// Paths to scan
+    #[arg(required = true)]
+    pub paths: Vec<String>,
+    /// Only scan files with these extensions
+    #[arg(long, value_delimiter = ',')]
+    pub include_file_extensions: Option<Vec<String>>,
+    /// Minimum file size in bytes
+    #[arg(long)]
+    pub min_file_size: Option<u64>,
+    /// Maximum file size in bytes
+    #[arg(long)]
+    pub max_file_size: Option<u64>,
+    /// Paths to skip
+    #[arg(long)]
+    pub ignore_paths: Option<Vec<String>>,
+    /// Compute perceptual hashes of images to find similar images
+    #[arg(long)]
+    pub image_hash: bool,
+    /// Compute fingerprints of audio files to find similar tracks
+    #[arg(long)]
+    pub audio_fingerprint: bool,
+    /// Compute md5 of jpeg, png, mp3 and mp4 files without their metadata
+    #[arg(long)]
+    pub payload_hash: bool,
+    /// Index members of zip, tar and tar.gz files as virtual files, without extracting them
+    #[arg(long)]
+    pub archive_members: bool,
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct ListArgs {
+    /// Only list files whose dir path contains this
+    #[arg(long)]
+    pub dir_path: Option<String>,
+    /// Only list files whose name contains this
+    #[arg(long)]
+    pub file_name: Option<String>,
+    /// Minimum file size in bytes
+    #[arg(long)]
+    pub min_file_size: Option<i64>,
+    /// Minimum count of files with the same md5
+    #[arg(long, default_value_t = 2)]
+    pub min_md5_count: i64,
+    /// Page number, start from 1
+    #[arg(long, default_value_t = 1)]
+    pub page_no: i64,
+    /// Files per page
+    #[arg(long, default_value_t = 100)]
+    pub page_count: i64,
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct ExportArgs {
+    /// Export format
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    pub format: ExportFormat,
+    /// File to write to, stdout if not set
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+    #[command(flatten)]
+    pub filter: ListFilterArgs,
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct ReportArgs {
+    /// File to write to
+    #[arg(long, short, default_value = "duplicate_report.html")]
+    pub output: PathBuf,
+    /// Count of the biggest groups, directories and extensions listed
+    #[arg(long, default_value_t = DEFAULT_REPORT_TOP_COUNT)]
+    pub top_count: usize,
+    #[command(flatten)]
+    pub filter: ListFilterArgs,
+}
+
+/// Filters of duplicate files, the same as those of the list api
+#[derive(ClapArgs, Debug, Clone)]
+pub struct ListFilterArgs {
+    /// Only include files whose dir path contains this
+    #[arg(long)]
+    pub dir_path: Option<String>,
+    /// Only include files whose name contains this
+    #[arg(long)]
+    pub file_name: Option<String>,
+    /// Only include files with this extension
+    #[arg(long)]
+    pub file_extension: Option<String>,
+    /// Only include files with these extensions, comma separated
+    #[arg(long)]
+    pub file_extension_list: Option<String>,
+    /// Only include files with this md5
+    #[arg(long)]
+    pub md5: Option<String>,
+    /// Minimum file size in bytes
+    #[arg(long)]
+    pub min_file_size: Option<i64>,
+    /// Maximum file size in bytes
+    #[arg(long)]
+    pub max_file_size: Option<i64>,
+    /// Only include files created at or after this time, e.g. 2024-01-01T00:00:00+08:00
+    #[arg(long)]
+    pub start_created_time: Option<DateTime<Local>>,
+    /// Only include files created at or before this time
+    #[arg(long)]
+    pub end_created_time: Option<DateTime<Local>>,
+    /// Only include files modified at or after this time
+    #[arg(long)]
+    pub start_modified_time: Option<DateTime<Local>>,
+    /// Only include files modified at or before this time
+    #[arg(long)]
+    pub end_modified_time: Option<DateTime<Local>>,
+    /// Minimum count of files with the same md5
+    #[arg(long, default_value_t = 2)]
+    pub min_md5_count: i64,
+    /// Only include files with less files of the same md5
+    #[arg(long)]
+    pub max_md5_count: Option<i64>,
+    /// Order groups by file size instead of md5 count
+    #[arg(long)]
+    pub order_by_size: bool,
+    /// Order ascending instead of descending
+    #[arg(long)]
+    pub order_asc: bool,
+    /// Only include files in the dir path duplicating files outside of it
+    #[arg(long, requires = "dir_path")]
+    pub filter_dup_file_by_dir_path: bool,
+    /// Group files by md5 of their content without metadata, computed by `scan --payload-hash`
+    #[arg(long)]
+    pub group_by_payload: bool,
+}
+
+impl From<ListFilterArgs> for ListSettings {
+    fn from(args: ListFilterArgs) -> Self {
+        ListSettings {
+            dir_path: args.dir_path,
+            file_name: args.file_name,
+            file_extension: args.file_extension,
+            file_extension_list: args.file_extension_list,
+            md5: args.md5,
+            min_file_size: args.min_file_size,
+            max_file_size: args.max_file_size,
+            start_created_time: args.start_created_time,
+            end_created_time: args.end_created_time,
+            start_modified_time: args.start_modified_time,
+            end_modified_time: args.end_modified_time,
+            min_md5_count: Some(args.min_md5_count),
+            max_md5_count: args.max_md5_count,
+            order_by: args.order_by_size.then(|| String::from("size")),
+            order_asc: Some(args.order_asc),
+            filter_dup_file_by_dir_path: Some(args.filter_dup_file_by_dir_path),
+            group_by: args.group_by_payload.then_some(FileHashType::Payload),
+            ..Default::default()
+        }
+    }
+}
+
+/// Which file of a duplicate group is kept
+#[derive(ValueEnum, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DedupePolicy {
+    /// Keep the file modified first
+    KeepOldest,
+    /// Keep the file modified last
+    KeepNewest,
+    /// Keep the file with the shortest path
+    KeepShortestPath,
+    /// Keep the file with the longest path
+    KeepLongestPath,
+}
+
+impl DedupePolicy {
+    /// Index of the file to keep, ties are broken by the path so that the result is stable
+    pub fn select_file_to_keep(&self, files: &[FileInfo]) -> Option<usize> {
+        let by_path = |a: &FileInfo, b: &FileInfo| a.file_path.cmp(&b.file_path);
+        files
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                let ordering = match self {
+                    DedupePolicy::KeepOldest => a.inode_info.modified.cmp(&b.inode_info.modified),
+                    DedupePolicy::KeepNewest => b.inode_info.modified.cmp(&a.inode_info.modified),
+                    DedupePolicy::KeepShortestPath => a.file_path.len().cmp(&b.file_path.len()),
+                    DedupePolicy::KeepLongestPath => b.file_path.len().cmp(&a.file_path.len()),
+                };
+                ordering.then_with(|| by_path(a, b))
+            })
+            .map(|(index, _)| index)
+    }
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct DedupeArgs {
+    /// Which file of each duplicate group is kept
+    #[arg(long, value_enum)]
+    pub policy: DedupePolicy,
+    /// Only remove duplicates whose dir path contains this
+    #[arg(long)]
+    pub dir_path: Option<String>,
+    /// Delete files permanently instead of moving them to trash
+    #[arg(long)]
+    pub delete_permanently: bool,
+    /// Only print what would be removed
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum TrashCommand {
+    /// List trash files
+    List(TrashListArgs),
+    /// Restore trash files to their original paths
+    Restore {
+        /// Original paths of the files
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+    /// Delete trash files permanently
+    Purge {
+        /// Only purge files moved to trash more than these days ago
+        #[arg(long, required_unless_present = "all")]
+        older_than_days: Option<u32>,
+        /// Purge all trash files
+        #[arg(long, conflicts_with = "older_than_days")]
+        all: bool,
+    },
+}
+
+#[derive(ClapArgs, Debug, Clone)]
+pub struct TrashListArgs {
+    /// Only list files whose dir path contains this
+    #[arg(long)]
+    pub dir_path: Option<String>,
+    /// Page number, start from 1
+    #[arg(long, default_value_t = 1)]
+    pub page_no: i64,
+    /// Files per page
+    #[arg(long, default_value_t = 100)]
+    pub page_count: i64,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum UserCommand {
+    /// Add a user, a random password is generated unless it is read from stdin
+    Add {
+        user_name: String,
+        #[arg(long, default_value = "viewer")]
+        role: UserRole,
+        /// Paths the user is restricted to, all paths if empty
+        #[arg(long)]
+        allowed_paths: Vec<String>,
+        /// Read the password from the first line of stdin
+        #[arg(long)]
+        password_stdin: bool,
+    },
+}
+
+/// Result of a scan
+#[derive(Serialize, Debug)]
+pub struct ScanSummary {
+    pub scan_path: String,
+    pub scanned_file_count: usize,
+}
+
+/// Result of a dedupe
+#[derive(Serialize, Debug, Default)]
+pub struct DedupeSummary {
+    pub dry_run: bool,
+    /// Files kept, one of each duplicate group
+    pub kept_files: Vec<String>,
+    /// Files moved to trash or deleted
+    pub removed_files: Vec<String>,
+    pub removed_size: u64,
+    pub failed_files: Vec<FailedFile>,
+}
+
+/// Result of restoring trash files
+#[derive(Serialize, Debug, Default)]
+pub struct RestoreSummary {
+    pub restored_files: Vec<String>,
+    pub failed_files: Vec<FailedFile>,
+}
+
+/// Result of purging trash files
+#[derive(Serialize, Debug, Default)]
+pub struct PurgeSummary {
+    pub purged_files: Vec<String>,
+    pub purged_size: u64,
+    pub failed_files: Vec<FailedFile>,
+}
+
+/// Added user with the generated password
+#[derive(Serialize, Debug)]
+pub struct AddedUser {
+    pub user_name: String,
+    pub role: UserRole,
+    /// Generated password, none if the password is read from stdin
+    pub password: Option<String>,
+}
@@ -17,8 +17,23 @@ impl ErrorCode {
     pub const SUCCESS: ErrorCode = ErrorCode(0);
     pub const SYSTEM_ERROR: ErrorCode = ErrorCode(1);
     pub const FILE_PATH_NOT_FOUND: ErrorCode = ErrorCode(11);
+    pub const MEDIA_DECODE_FAILED: ErrorCode = ErrorCode(12);
+    pub const ARCHIVE_READ_FAILED: ErrorCode = ErrorCode(13);
     pub const NOT_ALLOW_DELETE_FILE: ErrorCode = ErrorCode(21);
     pub const FILE_CHANGED: ErrorCode = ErrorCode(22);
+    pub const TRASH_FILE_NOT_FOUND: ErrorCode = ErrorCode(31);
+    pub const TRASH_FILE_CORRUPTED: ErrorCode = ErrorCode(32);
+    pub const TRASH_CHECK_IN_PROGRESS: ErrorCode = ErrorCode(33);
+    pub const PERMISSION_DENIED: ErrorCode = ErrorCode(41);
+    pub const USER_NOT_FOUND: ErrorCode = ErrorCode(42);
+    pub const USER_ALREADY_EXISTS: ErrorCode = ErrorCode(43);
+    pub const NOT_ALLOW_CHANGE_USER: ErrorCode = ErrorCode(44);
+    pub const API_TOKEN_NOT_FOUND: ErrorCode = ErrorCode(45);
+    pub const LOGIN_FAILED: ErrorCode = ErrorCode(46);
+    pub const TOTP_ALREADY_ENABLED: ErrorCode = ErrorCode(47);
+    pub const TOTP_NOT_ENABLED: ErrorCode = ErrorCode(48);
+    pub const TOTP_INVALID_CODE: ErrorCode = ErrorCode(49);
+    pub const OIDC_LOGIN_FAILED: ErrorCode = ErrorCode(50);
 }
 
 impl Display for ErrorCode {
@@ -80,3 +95,10 @@ impl<T: ToSchema> RestResponse<T> {
         }
     }
 }
+
+/// File which could not be processed
+#[derive(Serialize, Debug, ToSchema)]
+pub struct FailedFile {
+    pub file_path: String,
+    pub error: String,
+}
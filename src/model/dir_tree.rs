@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Query parameters for browsing the directory tree.
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams, ToSchema)]
+pub struct DirTreeParams {
+    /// The directory to list, the root directory by default
+    pub dir_path: Option<String>,
+}
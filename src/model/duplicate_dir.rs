@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::model::common::FailedFile;
+
+/// Overlap of near-identical directories listed by default
+pub const DEFAULT_MIN_OVERLAP_PERCENT: f64 = 80.0;
+
+/// Query parameters for listing duplicate directories.
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams, ToSchema)]
+pub struct DuplicateDirListParams {
+    /// Page number, start from 1
+    pub page_no: i64,
+    /// Page count, must be greater than 0
+    pub page_count: i64,
+    /// Only list pairs with a directory whose path contains this
+    pub dir_path: Option<String>,
+    /// Minimum overlap of near-identical directories, 80 by default
+    pub min_overlap_percent: Option<f64>,
+}
+
+/// Two directories with the same or mostly the same files
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct DuplicateDirPair {
+    pub dir_path: String,
+    pub other_dir_path: String,
+    /// Both directories have the same files and subdirectories with the same names and content
+    pub identical: bool,
+    /// Files with the same relative path and md5 in both directories, in percent of the files in
+    /// either directory
+    pub overlap_percent: f64,
+    /// Files in the directory and its subdirectories
+    pub file_count: u64,
+    pub size: u64,
+    pub other_file_count: u64,
+    pub other_size: u64,
+    /// Files with the same relative path and md5 in both directories
+    pub shared_file_count: u64,
+    pub shared_size: u64,
+}
+
+/// Duplicate directory pairs with total count
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DuplicateDirList {
+    pub duplicate_dir_list: Vec<DuplicateDirPair>,
+    pub total_count: u64,
+}
+
+/// Request body for deleting all files of a directory.
+#[derive(Deserialize, Serialize, ToSchema, Clone, Debug)]
+pub struct DeleteDirRequest {
+    /// The directory path to be deleted, with its subdirectories
+    pub dir_path: String,
+    /// Whether to delete permanently or move to trash
+    pub delete_permanently: Option<bool>,
+    /// Delete the files even if some of them have no copy outside of the directory. This option
+    /// should be used with caution
+    pub force_delete: Option<bool>,
+}
+
+/// Result of deleting a directory
+#[derive(Serialize, Debug, Default, ToSchema)]
+pub struct DeleteDirResult {
+    pub removed_files: Vec<String>,
+    pub removed_size: u64,
+    pub failed_files: Vec<FailedFile>,
+}
@@ -0,0 +1,131 @@
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::database::file_info::FileInfoWithMd5Count;
+
+/// Format of exported duplicate files
+#[derive(
+    ValueEnum, Clone, Copy, Debug, Default, Deserialize, Serialize, ToSchema, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One row per file, with the columns of its group
+    #[default]
+    Csv,
+    /// Array of duplicate groups
+    Json,
+    /// One duplicate group per line
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Json => "application/json",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// Query parameters for exporting duplicate files, besides the list filters.
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams, ToSchema)]
+pub struct ExportParams {
+    /// Export format, csv by default
+    pub format: Option<ExportFormat>,
+}
+
+/// File of a duplicate group
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct DuplicateFile {
+    pub file_path: String,
+    pub dir_path: String,
+    pub file_name: String,
+    pub inode: u64,
+    pub dev_id: u64,
+    pub nlink: u64,
+    pub modified: DateTime<Local>,
+}
+
+/// Files with the same md5
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct DuplicateGroup {
+    pub md5: String,
+    pub size: u64,
+    /// Count of files with the md5, hard links of a file are counted once
+    pub md5_count: usize,
+    /// Bytes freed if only one file of the group is kept
+    pub reclaimable_size: u64,
+    /// Files matching the filters
+    pub files: Vec<DuplicateFile>,
+}
+
+impl DuplicateGroup {
+    pub fn new(md5: String, item: &FileInfoWithMd5Count) -> Self {
+        let size = item.file_info.inode_info.size;
+        DuplicateGroup {
+            md5,
+            size,
+            md5_count: item.md5_count,
+            reclaimable_size: size * item.md5_count.saturating_sub(1) as u64,
+            files: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, item: FileInfoWithMd5Count) {
+        let file_info = item.file_info;
+        self.files.push(DuplicateFile {
+            file_path: file_info.file_path,
+            dir_path: file_info.dir_path,
+            file_name: file_info.file_name,
+            inode: file_info.inode_info.inode,
+            dev_id: file_info.inode_info.dev_id,
+            nlink: file_info.inode_info.nlink,
+            modified: file_info.inode_info.modified,
+        });
+    }
+}
+
+/// Csv row of a duplicate file, with the columns of its group
+#[derive(Clone, Debug, Serialize)]
+pub struct DuplicateFileRow<'a> {
+    pub md5: &'a str,
+    pub size: u64,
+    pub md5_count: usize,
+    pub reclaimable_size: u64,
+    pub file_path: &'a str,
+    pub dir_path: &'a str,
+    pub file_name: &'a str,
+    pub inode: u64,
+    pub dev_id: u64,
+    pub nlink: u64,
+    pub modified: DateTime<Local>,
+}
+
+impl<'a> DuplicateFileRow<'a> {
+    pub fn new(group: &'a DuplicateGroup, file: &'a DuplicateFile) -> Self {
+        DuplicateFileRow {
+            md5: group.md5.as_str(),
+            size: group.size,
+            md5_count: group.md5_count,
+            reclaimable_size: group.reclaimable_size,
+            file_path: file.file_path.as_str(),
+            dir_path: file.dir_path.as_str(),
+            file_name: file.file_name.as_str(),
+            inode: file.inode,
+            dev_id: file.dev_id,
+            nlink: file.nlink,
+            modified: file.modified,
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Hamming distance of similar images listed by default
+pub const DEFAULT_MAX_IMAGE_DISTANCE: u32 = 8;
+
+/// Perceptual hash used to compare images
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, ToSchema, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageHashAlgorithm {
+    /// DCT based hash, robust against resizing and recompression
+    #[default]
+    Phash,
+    /// Gradient based hash, faster but more sensitive to edits
+    Dhash,
+}
+
+/// Query parameters for listing similar images.
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams, ToSchema)]
+pub struct SimilarImageListParams {
+    /// Page number, start from 1
+    pub page_no: i64,
+    /// Page count, must be greater than 0
+    pub page_count: i64,
+    /// Only list groups with a file whose dir path contains this
+    pub dir_path: Option<String>,
+    /// Maximum hamming distance of the 64 bit hashes of similar images, 8 by default
+    pub max_distance: Option<u32>,
+    /// Hash to compare, phash by default
+    pub algorithm: Option<ImageHashAlgorithm>,
+}
+
+/// Image content in a group of similar images, with all files having the content
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct SimilarImage {
+    pub md5: String,
+    pub width: u32,
+    pub height: u32,
+    pub size: u64,
+    /// Hamming distance to the first image of the group
+    pub distance: u32,
+    pub files: Vec<String>,
+}
+
+/// Images within the hamming distance of each other, directly or through other images of the
+/// group, the biggest image first
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct SimilarImageGroup {
+    pub images: Vec<SimilarImage>,
+    /// Size of all files in the group
+    pub total_size: u64,
+}
+
+/// Similar image groups with total count
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SimilarImageGroupList {
+    pub similar_image_group_list: Vec<SimilarImageGroup>,
+    pub total_count: u64,
+}
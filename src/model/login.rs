@@ -1,5 +1,15 @@
+use std::{
+    cmp::Reverse,
+    collections::{hash_map::Entry, HashMap},
+    ops::Deref,
+};
+
+use chrono::{DateTime, Local, TimeDelta};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use tokio::sync::Mutex;
+use utoipa::{IntoParams, ToSchema};
+
+use super::settings::SystemSettings;
 
 /// Login params
 #[derive(Deserialize, ToSchema)]
@@ -14,20 +24,36 @@ pub struct LoginParams {
     pub login_type: String,
 }
 
+/// Second factor of a login, after the password is verified
 #[derive(Deserialize, ToSchema)]
-pub struct FakeCaptchaParams {
-    pub phone: Option<String>,
+pub struct LoginTotpParams {
+    /// TOTP code of the authenticator app, or a recovery code
+    pub code: String,
 }
 
-#[derive(Serialize, ToSchema)]
-pub struct FakeCaptcha {
-    pub code: Option<u32>,
-    pub status: Option<String>,
+/// Params to start a login with the OpenID Connect identity provider
+#[derive(Deserialize, IntoParams)]
+pub struct OidcLoginParams {
+    /// Remember the session after the browser is closed
+    pub auto_login: Option<bool>,
+}
+
+/// Params of the identity provider redirecting back after the user is authenticated
+#[derive(Deserialize, IntoParams)]
+pub struct OidcCallbackParams {
+    /// Authorization code
+    pub code: Option<String>,
+    /// State to check the callback belongs to the login of this session
+    pub state: Option<String>,
+    /// Error code if the user is not authenticated
+    pub error: Option<String>,
+    pub error_description: Option<String>,
 }
 
 /// Login result
 #[derive(Serialize, ToSchema)]
 pub struct LoginResult {
+    /// `ok` if logged in, `totp` if the second factor is required
     pub status: String,
     #[serde(rename(serialize = "type"))]
     #[schema(rename = "type")]
@@ -48,3 +74,203 @@ pub struct PasswordParams {
     /// New password (optional)
     pub new_password: Option<String>,
 }
+
+/// Failed login attempts of a client ip or a user name
+#[derive(Debug, Clone)]
+pub struct LoginAttempts {
+    /// Number of consecutive failed attempts
+    pub failed_count: u32,
+    /// Time of the last failed attempt
+    pub last_failed_time: DateTime<Local>,
+    /// Login is rejected until this time
+    pub locked_until: DateTime<Local>,
+}
+
+/// Kind of a locked login
+#[derive(Debug, Serialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LockedLoginKind {
+    Ip,
+    User,
+}
+
+/// Client ip or user name whose login is temporarily rejected
+#[derive(Debug, Serialize, ToSchema, Clone)]
+pub struct LockedLogin {
+    pub kind: LockedLoginKind,
+    /// Client ip or user name
+    pub name: String,
+    /// Number of consecutive failed attempts
+    pub failed_count: u32,
+    pub last_failed_time: DateTime<Local>,
+    pub locked_until: DateTime<Local>,
+}
+
+/// Locked logins
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LockedLoginList {
+    pub locked_login_list: Vec<LockedLogin>,
+    pub total_count: usize,
+}
+
+/// Rate limiter of login attempts per client ip and per user name. Every failed attempt delays
+/// the next attempt exponentially, and too many failed attempts lock the login temporarily.
+#[derive(Debug, Default)]
+pub struct LoginLimiter {
+    ip_attempts: HashMap<String, LoginAttempts>,
+    user_attempts: HashMap<String, LoginAttempts>,
+}
+
+impl LoginLimiter {
+    /// Time to wait until the next login attempt of the ip and user is allowed, none if allowed now
+    pub fn retry_after(
+        &self,
+        ip: &str,
+        user_name: &str,
+        now: &DateTime<Local>,
+    ) -> Option<TimeDelta> {
+        [self.ip_attempts.get(ip), self.user_attempts.get(user_name)]
+            .into_iter()
+            .flatten()
+            .map(|attempts| attempts.locked_until - *now)
+            .filter(|wait| *wait > TimeDelta::zero())
+            .max()
+    }
+
+    /// Record a failed attempt, returns true if the ip or user is locked out by this attempt
+    pub fn record_failure(
+        &mut self,
+        ip: &str,
+        user_name: &str,
+        now: &DateTime<Local>,
+        settings: &SystemSettings,
+    ) -> bool {
+        self.remove_expired(now, settings);
+        let ip_locked = Self::add_failure(
+            self.ip_attempts.entry(ip.to_string()),
+            settings.login_max_failed_attempts_per_ip,
+            now,
+            settings,
+        );
+        let user_locked = Self::add_failure(
+            self.user_attempts.entry(user_name.to_string()),
+            settings.login_max_failed_attempts,
+            now,
+            settings,
+        );
+        ip_locked || user_locked
+    }
+
+    /// Forget failed attempts of the ip and user after a successful login
+    pub fn record_success(&mut self, ip: &str, user_name: &str) {
+        self.ip_attempts.remove(ip);
+        self.user_attempts.remove(user_name);
+    }
+
+    /// Ips and users which are locked out after too many failed attempts
+    pub fn list_locked(&self, now: &DateTime<Local>, settings: &SystemSettings) -> LockedLoginList {
+        let locked_ips = self.ip_attempts.iter().filter_map(|(ip, attempts)| {
+            Self::locked_login(
+                LockedLoginKind::Ip,
+                ip,
+                attempts,
+                settings.login_max_failed_attempts_per_ip,
+                now,
+            )
+        });
+        let locked_users = self
+            .user_attempts
+            .iter()
+            .filter_map(|(user_name, attempts)| {
+                Self::locked_login(
+                    LockedLoginKind::User,
+                    user_name,
+                    attempts,
+                    settings.login_max_failed_attempts,
+                    now,
+                )
+            });
+        let mut locked_login_list: Vec<LockedLogin> = locked_ips.chain(locked_users).collect();
+        locked_login_list.sort_by_key(|locked_login| Reverse(locked_login.locked_until));
+        let total_count = locked_login_list.len();
+        LockedLoginList {
+            locked_login_list,
+            total_count,
+        }
+    }
+
+    fn add_failure(
+        entry: Entry<'_, String, LoginAttempts>,
+        max_failed_attempts: u32,
+        now: &DateTime<Local>,
+        settings: &SystemSettings,
+    ) -> bool {
+        let attempts = entry.or_insert(LoginAttempts {
+            failed_count: 0,
+            last_failed_time: *now,
+            locked_until: *now,
+        });
+        attempts.failed_count += 1;
+        attempts.last_failed_time = *now;
+        let locked = max_failed_attempts > 0 && attempts.failed_count >= max_failed_attempts;
+        let wait_s = if locked {
+            settings.login_lockout_s
+        } else {
+            // 1, 2, 4, 8 ... times of the backoff base, but never longer than the lockout
+            let exponent = (attempts.failed_count - 1).min(16);
+            (settings.login_backoff_base_s << exponent).min(settings.login_lockout_s)
+        };
+        attempts.locked_until = *now + TimeDelta::seconds(wait_s as i64);
+        locked
+    }
+
+    fn locked_login(
+        kind: LockedLoginKind,
+        name: &str,
+        attempts: &LoginAttempts,
+        max_failed_attempts: u32,
+        now: &DateTime<Local>,
+    ) -> Option<LockedLogin> {
+        if max_failed_attempts == 0
+            || attempts.failed_count < max_failed_attempts
+            || attempts.locked_until <= *now
+        {
+            return None;
+        }
+        Some(LockedLogin {
+            kind,
+            name: name.to_string(),
+            failed_count: attempts.failed_count,
+            last_failed_time: attempts.last_failed_time,
+            locked_until: attempts.locked_until,
+        })
+    }
+
+    /// Failed attempts are forgotten once the lockout time has passed since the last failure,
+    /// which also keeps the maps small when scanners try many ips or user names
+    fn remove_expired(&mut self, now: &DateTime<Local>, settings: &SystemSettings) {
+        let lockout = TimeDelta::seconds(settings.login_lockout_s as i64);
+        let is_active = |attempts: &LoginAttempts| {
+            attempts.locked_until > *now || *now - attempts.last_failed_time < lockout
+        };
+        self.ip_attempts.retain(|_, attempts| is_active(attempts));
+        self.user_attempts.retain(|_, attempts| is_active(attempts));
+    }
+}
+
+#[derive(Default)]
+pub struct SharedLoginLimiter(pub Mutex<LoginLimiter>);
+
+impl SharedLoginLimiter {
+    pub fn new() -> Self {
+        SharedLoginLimiter(Mutex::new(LoginLimiter::default()))
+    }
+}
+
+impl Deref for SharedLoginLimiter {
+    type Target = Mutex<LoginLimiter>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
@@ -0,0 +1,47 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::model::export::DuplicateGroup;
+
+/// Count of the biggest groups, directories and extensions in a report by default
+pub const DEFAULT_REPORT_TOP_COUNT: usize = 20;
+
+/// Query parameters of the duplicate report, besides the list filters.
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams, ToSchema)]
+pub struct ReportParams {
+    /// Count of the biggest groups, directories and extensions listed, 20 by default
+    pub top_count: Option<usize>,
+}
+
+/// Totals of all duplicate groups
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ReportSummary {
+    pub group_count: usize,
+    pub file_count: usize,
+    /// Size of all files of the groups
+    pub duplicate_size: u64,
+    /// Bytes freed if only one file of each group is kept
+    pub reclaimable_size: u64,
+}
+
+/// Duplicate files in a directory or with an extension
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ReportBucket {
+    pub name: String,
+    pub file_count: usize,
+    pub duplicate_size: u64,
+}
+
+/// Report of the duplicate files matching the list filters
+#[derive(Clone, Debug, Serialize)]
+pub struct DuplicateReport {
+    pub generated_time: DateTime<Local>,
+    pub summary: ReportSummary,
+    /// Groups with the most reclaimable bytes
+    pub top_groups: Vec<DuplicateGroup>,
+    /// Directories with the most duplicate bytes
+    pub directories: Vec<ReportBucket>,
+    /// Extensions with the most duplicate bytes
+    pub extensions: Vec<ReportBucket>,
+}
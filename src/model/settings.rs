@@ -1,20 +1,42 @@
-use std::{collections::HashSet, fs, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs::{self, OpenOptions},
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+    path::PathBuf,
+};
 
 use ::serde::{Deserialize, Serialize};
+use actix_web::cookie::{Key, SameSite};
 use chrono::{DateTime, Local};
 use clap::Parser;
-use config::{Config, Environment, File};
-use log::{debug, info};
+use config::{Config, ConfigError, Environment, File};
+use log::{debug, info, warn};
 use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
 
-use crate::utils::error::DfrError;
+use crate::{
+    database::user_info::UserRole,
+    model::cli::Command,
+    utils::{
+        error::DfrError,
+        password::{hash_password, is_password_hash},
+    },
+};
 
+/// Find and remove duplicate files, with a web server or headless subcommands
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    /// Name of the person to greet
-    #[arg(short, long, default_value = "conf/config")]
+    /// Path of the config file, without the `.toml` extension
+    #[arg(short, long, default_value = "conf/config", global = true)]
     config_file_path: String,
+    /// Print results of subcommands as JSON
+    #[arg(long, global = true)]
+    pub json: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
 }
 /// System settings for the application. This struct is used to load and save settings from a configuration file.
 #[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
@@ -36,18 +58,163 @@ pub struct SystemSettings {
     pub log_level: String,
     /// interval in seconds to clear trash
     pub clear_trash_interval_s: u32,
+    /// interval in seconds to check trash files against their md5, 0 to disable
+    pub check_trash_interval_s: u32,
+    /// trash files without trash info are only removed by the trash check if they were moved to
+    /// trash before this many seconds, a file being deleted is moved before its trash info is
+    /// inserted
+    pub orphan_trash_grace_period_s: u32,
     /// trash path for deleted files
     pub trash_path: String,
+    /// move deleted files to freedesktop.org trash instead of trash path, so that desktop file managers can restore them
+    pub xdg_trash: bool,
+    /// compress files moved to trash with zstd
+    pub trash_compression: bool,
+    /// zstd compression level of trash files
+    pub trash_compression_level: i32,
+    /// file extensions which are not compressed in trash, e.g. already compressed media files
+    pub trash_compression_exclude_extensions: HashSet<String>,
+    /// seconds of inactivity after which a session expires, not applied to remembered sessions
+    pub session_idle_timeout_s: u64,
+    /// seconds after login after which a session expires regardless of activity
+    pub session_absolute_timeout_s: u64,
+    /// seconds a session lasts when logging in with auto login (remember me)
+    pub session_remember_me_s: u64,
+    /// only send the session cookie over https, takes effect after restart
+    pub session_cookie_secure: bool,
+    /// SameSite attribute of the session cookie, takes effect after restart
+    pub session_cookie_same_site: CookieSameSite,
+    /// failed login attempts of a user name before it is locked out, 0 to disable
+    pub login_max_failed_attempts: u32,
+    /// failed login attempts of a client ip before it is locked out, 0 to disable
+    pub login_max_failed_attempts_per_ip: u32,
+    /// seconds to wait after the first failed login attempt, doubled by each further failure
+    pub login_backoff_base_s: u64,
+    /// seconds a user name or client ip is locked out after too many failed login attempts
+    pub login_lockout_s: u64,
+    /// deleting files permanently requires a login verified with TOTP. Can not be combined with
+    /// `proxy_auth_header`, users logged in at the proxy are never verified with TOTP
+    pub totp_required_for_delete: bool,
+    /// header with the user name set by an authenticating reverse proxy, e.g. `X-Forwarded-User`.
+    /// Empty to disable, otherwise it replaces the login session
+    pub proxy_auth_header: String,
+    /// ip addresses or CIDR ranges of the reverse proxy, the header of other clients is ignored
+    pub proxy_auth_trusted_ips: Vec<String>,
+    /// role of users created on first access through the reverse proxy
+    pub proxy_auth_default_role: UserRole,
+    /// PEM certificate chain to serve HTTPS on the port, empty to serve plain HTTP. Takes
+    /// effect after restart
+    pub tls_cert_path: String,
+    /// PEM private key of the certificate
+    pub tls_key_path: String,
+    /// interval in seconds to reload the certificate and key when the files are changed, 0 to
+    /// disable
+    pub tls_reload_interval_s: u32,
+    /// port of a plain HTTP listener redirecting to HTTPS, 0 to disable
+    pub tls_redirect_port: u16,
+    /// PEM certificates of the CA of client certificates, empty to disable mutual TLS.
+    /// Otherwise clients must present a certificate issued by the CA
+    pub tls_client_ca_path: String,
 }
 
-/// User settings
-#[derive(Clone, Debug, Deserialize, Serialize)]
+/// SameSite attribute of the session cookie
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CookieSameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl From<CookieSameSite> for SameSite {
+    fn from(same_site: CookieSameSite) -> Self {
+        match same_site {
+            CookieSameSite::Strict => SameSite::Strict,
+            CookieSameSite::Lax => SameSite::Lax,
+            CookieSameSite::None => SameSite::None,
+        }
+    }
+}
+
+/// OpenID Connect settings, login with the authorization code flow and PKCE
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OidcSettings {
+    /// issuer url of the identity provider, empty to disable OpenID Connect login
+    pub issuer_url: String,
+    pub client_id: String,
+    /// client secret, empty for public clients
+    pub client_secret: String,
+    /// url of `/api/login/oidc/callback` as seen by browsers, registered at the identity provider
+    pub redirect_url: String,
+    /// scopes requested in addition to `openid`
+    pub scopes: Vec<String>,
+    /// claim used as user name, e.g. `preferred_username`, `email` or `sub`
+    pub username_claim: String,
+    /// claim with the groups or roles of the user, a string or a list of strings
+    pub role_claim: String,
+    /// role of each value of the role claim, the highest role of the values is used. The role
+    /// is updated on every login if not empty.
+    pub role_mapping: HashMap<String, UserRole>,
+    /// role of users whose role claim has no mapped value
+    pub default_role: UserRole,
+    /// reject login with local password, so that all users log in with the identity provider
+    pub disable_password_login: bool,
+}
+
+/// Session settings, the key which signs and encrypts session cookies is kept here so that
+/// sessions survive restarts
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SessionSettings {
+    /// hex encoded 64 bytes session key, generated on first start if empty
+    pub secret_key: String,
+}
+
+/// User settings, used to create the first admin user when there is no user in the database
+#[derive(Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct UserSettings {
     /// login user name
     pub login_user_name: String,
-    /// login password
-    pub login_password: String,
+    /// Argon2id hash of login password in PHC string format
+    pub login_password_hash: String,
+    /// plain text login password of old config files, only read to migrate it to `login_password_hash`
+    #[serde(skip_serializing)]
+    pub login_password: Option<String>,
+}
+
+impl fmt::Debug for OidcSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // never print client secret
+        f.debug_struct("OidcSettings")
+            .field("issuer_url", &self.issuer_url)
+            .field("client_id", &self.client_id)
+            .field("redirect_url", &self.redirect_url)
+            .field("scopes", &self.scopes)
+            .field("username_claim", &self.username_claim)
+            .field("role_claim", &self.role_claim)
+            .field("role_mapping", &self.role_mapping)
+            .field("default_role", &self.default_role)
+            .field("disable_password_login", &self.disable_password_login)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for SessionSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // never print session key
+        f.debug_struct("SessionSettings").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for UserSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // never print password or password hash
+        f.debug_struct("UserSettings")
+            .field("login_user_name", &self.login_user_name)
+            .finish_non_exhaustive()
+    }
 }
 
 /// Scan settings
@@ -64,6 +231,14 @@ pub struct ScanSettings {
     pub max_file_size: Option<u64>,
     /// Ignore path to ignore during scan. If not provided, no paths will be ignored.
     pub ignore_paths: Option<HashSet<String>>,
+    /// Compute perceptual hashes of images to find similar images. If not provided, images are not analysed.
+    pub image_hash: Option<bool>,
+    /// Compute fingerprints of audio files to find similar tracks. If not provided, audio files are not analysed.
+    pub audio_fingerprint: Option<bool>,
+    /// Compute md5 of jpeg, png, mp3 and mp4 files without their metadata, so that files can be grouped by content only. If not provided, it is not computed.
+    pub payload_hash: Option<bool>,
+    /// Index members of zip, tar and tar.gz files as virtual files like `backup.zip!/dir/file`, which are grouped with duplicates but never deleted. If not provided, archives are not read.
+    pub archive_members: Option<bool>,
 }
 
 /// Query parameters for listing files.
@@ -105,6 +280,19 @@ pub struct ListSettings {
 
     /// Optional filter for duplicate files in a specific directory path. If set, if files within this directory duplicate those outside of it, they will be displayed.
     pub filter_dup_file_by_dir_path: Option<bool>,
+    /// Optional hash files are grouped by, md5 by default. Md5 counts and filters are of the groups.
+    pub group_by: Option<FileHashType>,
+}
+
+/// Hash of the file content which duplicate files are grouped by
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileHashType {
+    /// Md5 of the whole file
+    #[default]
+    Md5,
+    /// Md5 of the file content without metadata, files without it are grouped by md5
+    Payload,
 }
 
 /// Query parameters for listing files.
@@ -153,6 +341,10 @@ pub struct Settings {
     pub system: SystemSettings,
     /// User settings
     pub user: UserSettings,
+    /// Session settings
+    pub session: SessionSettings,
+    /// OpenID Connect settings
+    pub oidc: OidcSettings,
     /// Scan settings
     pub scan: ScanSettings,
     /// List settings
@@ -172,7 +364,68 @@ impl Default for SystemSettings {
             listen_addr_ipv6: "::".to_string(),
             log_level: "info".to_string(),
             clear_trash_interval_s: 2592000, // 30 days in seconds
+            check_trash_interval_s: 86400,   // 1 day in seconds
+            orphan_trash_grace_period_s: 3600,
             trash_path: "data/.dfr_trash".to_string(),
+            xdg_trash: false,
+            trash_compression: false,
+            trash_compression_level: 3,
+            trash_compression_exclude_extensions: [
+                "7z", "aac", "avi", "bz2", "flac", "gif", "gz", "heic", "jpeg", "jpg", "m4a",
+                "mkv", "mov", "mp3", "mp4", "ogg", "png", "rar", "webm", "webp", "xz", "zip",
+                "zst",
+            ]
+            .iter()
+            .map(|ext| ext.to_string())
+            .collect(),
+            session_idle_timeout_s: 3600,      // 1 hour in seconds
+            session_absolute_timeout_s: 86400, // 1 day in seconds
+            session_remember_me_s: 2592000,    // 30 days in seconds
+            session_cookie_secure: false,
+            session_cookie_same_site: CookieSameSite::Lax,
+            login_max_failed_attempts: 5,
+            login_max_failed_attempts_per_ip: 20,
+            login_backoff_base_s: 1,
+            login_lockout_s: 900, // 15 minutes in seconds
+            totp_required_for_delete: false,
+            proxy_auth_header: "".to_string(),
+            proxy_auth_trusted_ips: vec![],
+            proxy_auth_default_role: UserRole::Viewer,
+            tls_cert_path: "".to_string(),
+            tls_key_path: "".to_string(),
+            tls_reload_interval_s: 60,
+            tls_redirect_port: 0,
+            tls_client_ca_path: "".to_string(),
+        }
+    }
+}
+
+impl SystemSettings {
+    /// Reject combinations of settings which can not work together
+    pub fn validate(&self) -> Result<(), DfrError> {
+        if self.totp_required_for_delete && !self.proxy_auth_header.is_empty() {
+            return Err(DfrError::ConfigError(ConfigError::Message(String::from(
+                "totp_required_for_delete can not be combined with proxy_auth_header, \
+                 users logged in at the reverse proxy are never verified with TOTP",
+            ))));
+        }
+        Ok(())
+    }
+}
+
+impl Default for OidcSettings {
+    fn default() -> Self {
+        Self {
+            issuer_url: "".to_string(),
+            client_id: "".to_string(),
+            client_secret: "".to_string(),
+            redirect_url: "".to_string(),
+            scopes: vec!["profile".to_string(), "email".to_string()],
+            username_claim: "preferred_username".to_string(),
+            role_claim: "groups".to_string(),
+            role_mapping: HashMap::new(),
+            default_role: UserRole::Viewer,
+            disable_password_login: false,
         }
     }
 }
@@ -181,7 +434,8 @@ impl Default for UserSettings {
     fn default() -> Self {
         Self {
             login_user_name: "admin".to_string(),
-            login_password: "".to_string(),
+            login_password_hash: "".to_string(),
+            login_password: None,
         }
     }
 }
@@ -194,6 +448,10 @@ impl Default for ScanSettings {
             min_file_size: None,
             max_file_size: None,
             ignore_paths: None,
+            image_hash: None,
+            audio_fingerprint: None,
+            payload_hash: None,
+            archive_members: None,
         }
     }
 }
@@ -219,6 +477,7 @@ impl Default for ListSettings {
             order_by: None,
             order_asc: None,
             filter_dup_file_by_dir_path: None,
+            group_by: None,
         }
     }
 }
@@ -260,6 +519,7 @@ impl Settings {
             .build()?;
         let mut settings = config.try_deserialize::<Settings>()?;
         settings.system.config_file_path = args.config_file_path.clone();
+        settings.system.validate()?;
         Ok(settings)
     }
 
@@ -277,4 +537,79 @@ impl Settings {
         fs::write(&config_file_path, toml_str)?;
         Ok(())
     }
+
+    /// Path of the file which keeps the random password generated on first start
+    pub fn initial_password_file_path(&self) -> PathBuf {
+        let mut file_path = PathBuf::from(self.system.config_file_path.as_str());
+        file_path.set_file_name("initial_password");
+        file_path
+    }
+
+    /// Make sure the login password is stored as Argon2id hash. Plain text password of old config
+    /// files is migrated to hash, and a random password is generated if there is no password yet.
+    pub fn init_login_password(&mut self) -> Result<(), DfrError> {
+        let plain_password = match self.user.login_password.take() {
+            Some(password) if !password.is_empty() => Some(password),
+            _ if !self.user.login_password_hash.is_empty()
+                && !is_password_hash(self.user.login_password_hash.as_str()) =>
+            {
+                Some(self.user.login_password_hash.clone())
+            }
+            _ => None,
+        };
+        if let Some(password) = plain_password {
+            info!("Migrate plain text login password to hash");
+            self.user.login_password_hash = hash_password(password.as_str())?;
+            return self.save();
+        }
+        if !self.user.login_password_hash.is_empty() {
+            return Ok(());
+        }
+
+        let random_uuid = Uuid::new_v4();
+        let uuid_str = random_uuid.simple().to_string();
+        let new_password = &uuid_str[..16];
+        let initial_password_file_path = self.initial_password_file_path();
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&initial_password_file_path)?;
+        writeln!(file, "{}", new_password)?;
+        warn!(
+            "Password need to change, new random password is written to {}",
+            initial_password_file_path.display()
+        );
+        self.user.login_password_hash = hash_password(new_password)?;
+        self.save()
+    }
+
+    /// Load the session key, a new one is generated and saved if there is no valid key yet
+    pub fn init_session_key(&mut self) -> Result<Key, DfrError> {
+        let secret_key = self.session.secret_key.as_str();
+        let key_bytes = (0..secret_key.len())
+            .step_by(2)
+            .map(|i| {
+                secret_key
+                    .get(i..i + 2)
+                    .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+            })
+            .collect::<Option<Vec<u8>>>();
+        if let Some(key) = key_bytes.and_then(|bytes| Key::try_from(bytes.as_slice()).ok()) {
+            return Ok(key);
+        }
+
+        if !secret_key.is_empty() {
+            warn!("Invalid session key, generate a new one");
+        }
+        let key = Key::generate();
+        self.session.secret_key = key
+            .master()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        self.save()?;
+        Ok(key)
+    }
 }
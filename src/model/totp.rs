@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// TOTP status of current user
+#[derive(Serialize, ToSchema)]
+pub struct TotpStatus {
+    /// TOTP is enrolled and required on login
+    pub enabled: bool,
+    /// Number of unused recovery codes
+    pub recovery_code_count: usize,
+}
+
+/// Started TOTP enrollment, the secret is only enabled after it is confirmed with a code
+#[derive(Serialize, ToSchema)]
+pub struct TotpEnrollResult {
+    /// Base32 encoded secret, for authenticator apps which can not scan QR codes
+    pub secret: String,
+    /// `otpauth://` provisioning uri
+    pub provisioning_uri: String,
+    /// Provisioning uri as SVG QR code
+    pub qr_code_svg: String,
+}
+
+/// Code of the authenticator app
+#[derive(Deserialize, ToSchema)]
+pub struct ConfirmTotpRequest {
+    pub code: String,
+}
+
+/// Recovery codes, only returned once. Each code can be used once instead of a TOTP code.
+#[derive(Serialize, ToSchema)]
+pub struct TotpRecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Disable TOTP request
+#[derive(Deserialize, ToSchema)]
+pub struct DisableTotpRequest {
+    /// Password of current user
+    pub password: String,
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
@@ -49,4 +50,33 @@ pub struct RestoreTrashFilePath {
 pub struct RestoreTrashFilesRequest {
     /// The directory path of file to be restore
     pub files: Vec<RestoreTrashFilePath>,
-}
\ No newline at end of file
+}
+
+/// Result of restoring a trash file
+#[derive(Deserialize, Serialize, ToSchema, Clone, Debug)]
+pub struct RestoreTrashFileResult {
+    /// Attributes which could not be restored, e.g. `created`, `owner` or `xattr:user.tags`
+    pub unrestored_attributes: Vec<String>,
+}
+
+/// Result of checking trash files against trash info
+#[derive(Deserialize, Serialize, ToSchema, Clone, Debug)]
+pub struct TrashCheckResult {
+    /// Start time of the check
+    pub start_time: DateTime<Local>,
+    /// End time of the check
+    pub end_time: DateTime<Local>,
+    /// Number of trash files which were hashed
+    pub checked_count: usize,
+    /// Md5 of trash files whose content does not match their name
+    pub corrupted_files: Vec<String>,
+    /// Md5 of trash files without any trash info, they are removed
+    pub orphan_files: Vec<String>,
+    /// Md5 of trash info whose trash file is missing
+    pub missing_files: Vec<String>,
+    /// Files in trash path which are not named by md5, they are kept
+    pub unknown_files: Vec<String>,
+    /// Temporary files of interrupted compressions older than the orphan grace period, they are
+    /// removed
+    pub stale_tmp_files: Vec<String>,
+}
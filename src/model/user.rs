@@ -1,6 +1,8 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::database::user_info::UserRole;
+
 #[derive(Serialize, Debug, ToSchema)]
 pub struct LabelKey {
     pub label: Option<String>,
@@ -90,3 +92,38 @@ pub struct UserRespone<T> {
     pub error_message: String,
     pub success: bool,
 }
+
+/// Add user request
+#[derive(Deserialize, ToSchema)]
+pub struct AddUserRequest {
+    /// User name
+    pub user_name: String,
+    /// Password
+    pub password: String,
+    /// User role
+    pub role: UserRole,
+    /// Dir paths the user is allowed to access, all paths are allowed if none or empty
+    pub allowed_paths: Option<Vec<String>>,
+}
+
+/// Update user request, only the given fields are updated
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateUserRequest {
+    /// User name
+    pub user_name: String,
+    /// New password (optional)
+    pub password: Option<String>,
+    /// New role (optional)
+    pub role: Option<UserRole>,
+    /// New allowed dir paths (optional), empty list to allow all paths
+    pub allowed_paths: Option<Vec<String>>,
+    /// Disable TOTP of the user (optional), e.g. when the authenticator and recovery codes are lost
+    pub reset_totp: Option<bool>,
+}
+
+/// Delete user request
+#[derive(Deserialize, ToSchema)]
+pub struct DeleteUserRequest {
+    /// User name
+    pub user_name: String,
+}
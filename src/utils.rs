@@ -1,2 +1,15 @@
+pub mod compress;
 pub mod error;
 pub mod network;
+pub mod password;
+pub mod token;
+pub mod xdg_trash;
+pub mod totp;
+pub mod oidc;
+pub mod tls;
+pub mod report;
+pub mod duplicate_dir;
+pub mod image_hash;
+pub mod audio_fingerprint;
+pub mod payload_hash;
+pub mod archive;
\ No newline at end of file
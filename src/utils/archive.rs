@@ -0,0 +1,135 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::{Component, Path},
+};
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use flate2::read::GzDecoder;
+use log::warn;
+use md5::{Digest, Md5};
+
+use crate::{model::common::ErrorCode, utils::error::DfrError};
+
+/// Extensions of the archives whose members are indexed
+pub const ARCHIVE_EXTENSIONS: [&str; 4] = ["tar", "tar.gz", "tgz", "zip"];
+
+/// Regular file in an archive, read without extracting it
+#[derive(Debug, Clone)]
+pub struct ArchiveMember {
+    /// Path of the member in the archive, without leading `/` and `..`
+    pub path: String,
+    pub md5: String,
+    pub size: u64,
+    pub modified: Option<DateTime<Local>>,
+}
+
+pub fn is_archive_file(file_name: &str) -> bool {
+    let file_name = file_name.to_lowercase();
+    ARCHIVE_EXTENSIONS
+        .iter()
+        .any(|extension| file_name.ends_with(&format!(".{}", extension)))
+}
+
+/// Read the regular files of a zip, tar or tar.gz archive and calculate their md5. Members which
+/// can not be read, e.g. encrypted ones, are skipped.
+pub fn read_archive_members(file_path: &str) -> Result<Vec<ArchiveMember>, DfrError> {
+    let file_name = file_path.to_lowercase();
+    let result = if file_name.ends_with(".zip") {
+        read_zip_members(File::open(file_path)?)
+    } else if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+        read_tar_members(GzDecoder::new(BufReader::new(File::open(file_path)?)))
+    } else {
+        read_tar_members(BufReader::new(File::open(file_path)?))
+    };
+    match result {
+        Ok(members) => Ok(members),
+        Err(e) => DfrError::custom_error(
+            ErrorCode::ARCHIVE_READ_FAILED,
+            format!("Failed to read archive {}: {}", file_path, e),
+        ),
+    }
+}
+
+/// Path of the member with only normal components, none if nothing is left
+fn member_path(path: &Path) -> Option<String> {
+    let components = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if components.is_empty() {
+        return None;
+    }
+    Some(components.join("/"))
+}
+
+/// Md5 and size of the content of the reader
+fn content_md5<R: Read>(reader: &mut R) -> io::Result<(String, u64)> {
+    let mut hasher = Md5::new();
+    let size = io::copy(reader, &mut hasher)?;
+    Ok((format!("{:x}", hasher.finalize()), size))
+}
+
+fn read_zip_members(file: File) -> io::Result<Vec<ArchiveMember>> {
+    let mut archive = zip::ZipArchive::new(BufReader::new(file))?;
+    let mut members = Vec::new();
+    for index in 0..archive.len() {
+        let mut zip_file = match archive.by_index(index) {
+            Ok(zip_file) => zip_file,
+            Err(e) => {
+                warn!("Skip member {} of zip archive: {}", index, e);
+                continue;
+            }
+        };
+        if !zip_file.is_file() {
+            continue;
+        }
+        let Some(path) = member_path(&zip_file.mangled_name()) else {
+            continue;
+        };
+        // times in zip files have no time zone, they are local times
+        let modified = zip_file
+            .last_modified()
+            .and_then(|modified| NaiveDateTime::try_from(modified).ok())
+            .and_then(|modified| Local.from_local_datetime(&modified).earliest());
+        let (md5, size) = content_md5(&mut zip_file)?;
+        members.push(ArchiveMember {
+            path,
+            md5,
+            size,
+            modified,
+        });
+    }
+    Ok(members)
+}
+
+fn read_tar_members<R: Read>(reader: R) -> io::Result<Vec<ArchiveMember>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut members = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let Some(path) = member_path(&entry.path()?) else {
+            continue;
+        };
+        let modified = entry
+            .header()
+            .mtime()
+            .ok()
+            .and_then(|mtime| DateTime::from_timestamp(mtime as i64, 0))
+            .map(DateTime::<Local>::from);
+        let (md5, size) = content_md5(&mut entry)?;
+        members.push(ArchiveMember {
+            path,
+            md5,
+            size,
+            modified,
+        });
+    }
+    Ok(members)
+}
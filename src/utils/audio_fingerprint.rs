@@ -0,0 +1,408 @@
+use std::{collections::HashMap, fs::File, io::ErrorKind};
+
+use rustfft::{num_complex::Complex, FftPlanner};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error as SymphoniaError,
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+use crate::{
+    database::audio_fingerprint::{AudioFingerprint, AudioFingerprintFile},
+    model::{
+        audio_fingerprint::{SimilarAudio, SimilarAudioGroup},
+        common::ErrorCode,
+    },
+    utils::{duplicate_dir::join_path, error::DfrError, image_hash::find_root},
+};
+
+/// Extensions of the audio formats which are decoded
+pub const AUDIO_EXTENSIONS: [&str; 7] = ["aac", "flac", "m4a", "mp3", "oga", "ogg", "wav"];
+
+/// Sample rate the audio is resampled to before the fingerprint is computed
+const SAMPLE_RATE: u32 = 11025;
+const FRAME_SIZE: usize = 4096;
+/// Frames overlap by two thirds, one frame is about 1/8 second
+const FRAME_HOP: usize = 1365;
+const MIN_FREQUENCY: f64 = 28.0;
+const MAX_FREQUENCY: f64 = 3520.0;
+/// Only the start of a track is fingerprinted, as copies of a song differ at most in silence
+const MAX_FINGERPRINT_S: usize = 120;
+/// Bits of each sub-fingerprint which compare chroma bins
+const FINGERPRINT_BITS: usize = 30;
+/// Frames the fingerprints are shifted against each other, for silence added at the start
+const MAX_FRAME_OFFSET: isize = 16;
+/// Frames two fingerprints have to overlap by to be compared, about 5 seconds
+const MIN_OVERLAP_FRAMES: usize = 40;
+/// Tracks whose durations differ by more are not compared
+const MAX_DURATION_DIFF_S: f64 = 5.0;
+
+pub fn is_audio_file(file_name: &str) -> bool {
+    match file_name.rsplit_once('.') {
+        Some((_, extension)) => AUDIO_EXTENSIONS.contains(&extension.to_lowercase().as_str()),
+        None => false,
+    }
+}
+
+/// Whether the codec keeps the decoded samples unchanged
+pub fn is_lossless_codec(codec: &str) -> bool {
+    codec == "flac" || codec == "alac" || codec.starts_with("pcm")
+}
+
+/// Decoded audio, downmixed to mono
+struct DecodedAudio {
+    codec: String,
+    sample_rate: u32,
+    channels: u32,
+    bit_depth: Option<u32>,
+    duration_s: f64,
+    /// Samples of the start of the track at the sample rate of the file
+    samples: Vec<f32>,
+}
+
+fn decode_audio(file_path: &str) -> Result<DecodedAudio, SymphoniaError> {
+    let source = MediaSourceStream::new(Box::new(File::open(file_path)?), Default::default());
+    let mut hint = Hint::new();
+    if let Some((_, extension)) = file_path.rsplit_once('.') {
+        hint.with_extension(extension);
+    }
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        source,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    let mut format = probed.format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or(SymphoniaError::Unsupported("no audio track"))?;
+    let track_id = track.id;
+    let codec_params = track.codec_params.clone();
+    let codecs = symphonia::default::get_codecs();
+    let mut decoder = codecs.make(&codec_params, &DecoderOptions::default())?;
+    let mut decoded_audio = DecodedAudio {
+        codec: codecs
+            .get_codec(codec_params.codec)
+            .map_or("unknown", |descriptor| descriptor.short_name)
+            .to_string(),
+        sample_rate: codec_params.sample_rate.unwrap_or_default(),
+        channels: codec_params
+            .channels
+            .map_or(0, |channels| channels.count() as u32),
+        bit_depth: codec_params.bits_per_sample,
+        duration_s: 0.0,
+        samples: Vec::new(),
+    };
+    let mut frame_count = 0;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // a corrupted packet is skipped, the same as by players
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(e),
+        };
+        let spec = *decoded.spec();
+        decoded_audio.sample_rate = spec.rate;
+        decoded_audio.channels = spec.channels.count() as u32;
+        frame_count += decoded.frames() as u64;
+        let max_sample_count = MAX_FINGERPRINT_S * spec.rate as usize;
+        if decoded_audio.samples.len() >= max_sample_count {
+            if codec_params.n_frames.is_some() {
+                break;
+            }
+            // the whole track is decoded for its duration
+            continue;
+        }
+        let mut sample_buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        sample_buffer.copy_interleaved_ref(decoded);
+        let channels = spec.channels.count().max(1);
+        for frame in sample_buffer.samples().chunks(channels) {
+            decoded_audio
+                .samples
+                .push(frame.iter().sum::<f32>() / channels as f32);
+        }
+    }
+    if decoded_audio.sample_rate > 0 {
+        let frame_count = codec_params.n_frames.unwrap_or(frame_count);
+        decoded_audio.duration_s = frame_count as f64 / decoded_audio.sample_rate as f64;
+    }
+    Ok(decoded_audio)
+}
+
+/// Resample to the sample rate of the fingerprint, samples are averaged when downsampling so
+/// that frequencies above the new rate do not fold back
+fn resample(samples: &[f32], sample_rate: u32) -> Vec<f32> {
+    if sample_rate == SAMPLE_RATE || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let ratio = sample_rate as f64 / SAMPLE_RATE as f64;
+    let sample_count = (samples.len() as f64 / ratio) as usize;
+    (0..sample_count)
+        .map(|index| {
+            let position = index as f64 * ratio;
+            let start = position as usize;
+            if ratio > 1.0 {
+                let end = (((index + 1) as f64 * ratio) as usize).clamp(start + 1, samples.len());
+                samples[start..end].iter().sum::<f32>() / (end - start) as f32
+            } else {
+                let fraction = (position - start as f64) as f32;
+                let next = samples.get(start + 1).copied().unwrap_or(samples[start]);
+                samples[start] * (1.0 - fraction) + next * fraction
+            }
+        })
+        .collect()
+}
+
+/// Energy of the 12 semitones of each frame, normalized so that the volume does not matter
+fn chroma_frames(samples: &[f32]) -> Vec<[f32; 12]> {
+    let fft = FftPlanner::<f32>::new().plan_fft_forward(FRAME_SIZE);
+    let window = (0..FRAME_SIZE)
+        .map(|index| {
+            0.5 - 0.5 * (2.0 * std::f32::consts::PI * index as f32 / FRAME_SIZE as f32).cos()
+        })
+        .collect::<Vec<_>>();
+    let notes = (0..FRAME_SIZE / 2)
+        .map(|bin| {
+            let frequency = bin as f64 * SAMPLE_RATE as f64 / FRAME_SIZE as f64;
+            if !(MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+                return None;
+            }
+            let octave = (frequency / 27.5).log2();
+            Some(((octave - octave.floor()) * 12.0) as usize % 12)
+        })
+        .collect::<Vec<_>>();
+    let mut buffer = vec![Complex::default(); FRAME_SIZE];
+    let mut frames = Vec::new();
+    let mut start = 0;
+    while start + FRAME_SIZE <= samples.len() {
+        for (index, value) in buffer.iter_mut().enumerate() {
+            *value = Complex::new(samples[start + index] * window[index], 0.0);
+        }
+        fft.process(&mut buffer);
+        let mut chroma = [0.0; 12];
+        for (bin, note) in notes.iter().enumerate() {
+            if let Some(note) = note {
+                chroma[*note] += buffer[bin].norm_sqr();
+            }
+        }
+        let norm = chroma.iter().map(|value| value * value).sum::<f32>().sqrt();
+        if norm > 1e-6 {
+            chroma.iter_mut().for_each(|value| *value /= norm);
+        }
+        frames.push(chroma);
+        start += FRAME_HOP;
+    }
+    frames
+}
+
+/// Sub-fingerprint per frame, comparing the chroma of semitones a half step, a minor third and a
+/// tritone apart, averaged over 3 frames so that codec noise flips fewer bits
+fn fingerprint_frames(chroma_frames: &[[f32; 12]]) -> Vec<u32> {
+    (0..chroma_frames.len())
+        .map(|index| {
+            let frames =
+                &chroma_frames[index.saturating_sub(1)..(index + 2).min(chroma_frames.len())];
+            let mut chroma = [0.0; 12];
+            for frame in frames.iter() {
+                for (value, frame_value) in chroma.iter_mut().zip(frame.iter()) {
+                    *value += frame_value;
+                }
+            }
+            let mut sub_fingerprint = 0;
+            let mut bit = 0;
+            for (interval, note_count) in [(1, 12), (3, 12), (6, 6)] {
+                for note in 0..note_count {
+                    if chroma[note] > chroma[(note + interval) % 12] {
+                        sub_fingerprint |= 1 << bit;
+                    }
+                    bit += 1;
+                }
+            }
+            sub_fingerprint
+        })
+        .collect()
+}
+
+/// Decode the audio file and compute its fingerprint, the bitrate is the average of the file
+pub fn compute_audio_fingerprint(
+    file_path: &str,
+    md5: &str,
+    file_size: u64,
+) -> Result<AudioFingerprint, DfrError> {
+    let decoded_audio = match decode_audio(file_path) {
+        Ok(decoded_audio) => decoded_audio,
+        Err(e) => {
+            return DfrError::custom_error(
+                ErrorCode::MEDIA_DECODE_FAILED,
+                format!("Failed to decode audio {}: {}", file_path, e),
+            )
+        }
+    };
+    let samples = resample(&decoded_audio.samples, decoded_audio.sample_rate);
+    let lossless = is_lossless_codec(&decoded_audio.codec);
+    let bitrate = if decoded_audio.duration_s > 0.0 {
+        (file_size as f64 * 8.0 / decoded_audio.duration_s) as u64
+    } else {
+        0
+    };
+    Ok(AudioFingerprint {
+        md5: md5.to_string(),
+        duration_s: decoded_audio.duration_s,
+        codec: decoded_audio.codec,
+        bitrate,
+        sample_rate: decoded_audio.sample_rate,
+        channels: decoded_audio.channels,
+        bit_depth: decoded_audio.bit_depth.filter(|_| lossless),
+        fingerprint: fingerprint_frames(&chroma_frames(&samples)),
+    })
+}
+
+/// Share of equal bits of the fingerprints, at the best alignment of the fingerprints
+pub fn fingerprint_similarity(fingerprint: &[u32], other_fingerprint: &[u32]) -> f64 {
+    let min_overlap = MIN_OVERLAP_FRAMES
+        .min(fingerprint.len())
+        .min(other_fingerprint.len())
+        .max(1);
+    let mut best_similarity: f64 = 0.0;
+    for offset in -MAX_FRAME_OFFSET..=MAX_FRAME_OFFSET {
+        let (start, other_start) = if offset >= 0 {
+            (offset as usize, 0)
+        } else {
+            (0, offset.unsigned_abs())
+        };
+        if start >= fingerprint.len() || other_start >= other_fingerprint.len() {
+            continue;
+        }
+        let overlap = (fingerprint.len() - start).min(other_fingerprint.len() - other_start);
+        if overlap < min_overlap {
+            continue;
+        }
+        let error_bits: u32 = fingerprint[start..start + overlap]
+            .iter()
+            .zip(other_fingerprint[other_start..other_start + overlap].iter())
+            .map(|(sub_fingerprint, other_sub_fingerprint)| {
+                (sub_fingerprint ^ other_sub_fingerprint).count_ones()
+            })
+            .sum();
+        let similarity = 1.0 - error_bits as f64 / (overlap * FINGERPRINT_BITS) as f64;
+        best_similarity = best_similarity.max(similarity);
+    }
+    best_similarity
+}
+
+/// Group tracks with similar fingerprints, files with the same md5 are one track. Only tracks
+/// of about the same duration are compared. Only groups of more than one track are returned,
+/// the biggest total size first.
+pub fn find_similar_audio(
+    files: &[AudioFingerprintFile],
+    min_similarity: f64,
+) -> Vec<SimilarAudioGroup> {
+    let mut tracks: Vec<SimilarAudio> = Vec::new();
+    let mut fingerprints = Vec::new();
+    let mut track_indexes: HashMap<&str, usize> = HashMap::new();
+    for file in files.iter() {
+        let audio_fingerprint = &file.audio_fingerprint;
+        let index = *track_indexes
+            .entry(audio_fingerprint.md5.as_str())
+            .or_insert_with(|| {
+                tracks.push(SimilarAudio {
+                    md5: audio_fingerprint.md5.clone(),
+                    duration_s: audio_fingerprint.duration_s,
+                    codec: audio_fingerprint.codec.clone(),
+                    lossless: is_lossless_codec(&audio_fingerprint.codec),
+                    bitrate: audio_fingerprint.bitrate,
+                    sample_rate: audio_fingerprint.sample_rate,
+                    channels: audio_fingerprint.channels,
+                    bit_depth: audio_fingerprint.bit_depth,
+                    size: file.size,
+                    similarity: 1.0,
+                    files: Vec::new(),
+                });
+                fingerprints.push(audio_fingerprint.fingerprint.as_slice());
+                tracks.len() - 1
+            });
+        tracks[index]
+            .files
+            .push(join_path(&file.dir_path, &file.file_name));
+    }
+
+    let mut by_duration = (0..tracks.len()).collect::<Vec<_>>();
+    by_duration.sort_by(|a, b| tracks[*a].duration_s.total_cmp(&tracks[*b].duration_s));
+    let mut parents = (0..tracks.len()).collect::<Vec<_>>();
+    for (position, index) in by_duration.iter().enumerate() {
+        for other_index in by_duration[position + 1..].iter() {
+            if tracks[*other_index].duration_s - tracks[*index].duration_s > MAX_DURATION_DIFF_S {
+                break;
+            }
+            if fingerprint_similarity(fingerprints[*index], fingerprints[*other_index])
+                >= min_similarity
+            {
+                let (root, other_root) = (
+                    find_root(&mut parents, *index),
+                    find_root(&mut parents, *other_index),
+                );
+                parents[root] = other_root;
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for index in 0..tracks.len() {
+        let root = find_root(&mut parents, index);
+        groups.entry(root).or_default().push(index);
+    }
+    let mut similar_audio_groups = groups
+        .into_values()
+        .filter(|indexes| indexes.len() > 1)
+        .map(|mut indexes| {
+            indexes.sort_by(|a, b| {
+                let (track, other_track) = (&tracks[*a], &tracks[*b]);
+                other_track
+                    .lossless
+                    .cmp(&track.lossless)
+                    .then_with(|| other_track.bitrate.cmp(&track.bitrate))
+                    .then_with(|| other_track.sample_rate.cmp(&track.sample_rate))
+                    .then_with(|| other_track.bit_depth.cmp(&track.bit_depth))
+                    .then_with(|| other_track.size.cmp(&track.size))
+                    .then_with(|| track.md5.cmp(&other_track.md5))
+            });
+            let keeper_fingerprint = fingerprints[indexes[0]];
+            let tracks = indexes
+                .into_iter()
+                .map(|index| SimilarAudio {
+                    similarity: fingerprint_similarity(keeper_fingerprint, fingerprints[index]),
+                    ..tracks[index].clone()
+                })
+                .collect::<Vec<_>>();
+            let total_size = tracks
+                .iter()
+                .map(|track| track.size * track.files.len() as u64)
+                .sum();
+            SimilarAudioGroup {
+                keeper_md5: tracks[0].md5.clone(),
+                tracks,
+                total_size,
+            }
+        })
+        .collect::<Vec<_>>();
+    similar_audio_groups.sort_by(|a, b| {
+        b.total_size
+            .cmp(&a.total_size)
+            .then_with(|| a.keeper_md5.cmp(&b.keeper_md5))
+    });
+    similar_audio_groups
+}
@@ -0,0 +1,42 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read},
+    path::Path,
+};
+
+use md5::{Digest, Md5};
+
+use crate::utils::error::DfrError;
+
+/// Compress file with zstd, return the compressed size
+pub fn compress_file(src: &Path, dst: &Path, level: i32) -> Result<u64, DfrError> {
+    let reader = BufReader::new(File::open(src)?);
+    let mut writer = BufWriter::new(File::create(dst)?);
+    zstd::stream::copy_encode(reader, &mut writer, level)?;
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    Ok(fs::metadata(dst)?.len())
+}
+
+/// Decompress zstd compressed file, return the decompressed size
+pub fn decompress_file(src: &Path, dst: &Path) -> Result<u64, DfrError> {
+    let mut decoder = zstd::stream::Decoder::new(File::open(src)?)?;
+    let mut writer = BufWriter::new(File::create(dst)?);
+    let size = io::copy(&mut decoder, &mut writer)?;
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    Ok(size)
+}
+
+/// Calculate md5 of the decompressed content of zstd compressed file
+pub fn decompressed_md5(src: &Path) -> Result<String, DfrError> {
+    let mut decoder = zstd::stream::Decoder::new(File::open(src)?)?;
+    let mut hasher = Md5::new();
+    let mut buffer = [0; 65536];
+    loop {
+        let n = decoder.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
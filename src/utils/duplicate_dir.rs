@@ -0,0 +1,283 @@
+use std::collections::{HashMap, HashSet};
+
+use md5::{Digest, Md5};
+
+use crate::{database::file_info::FileHash, model::duplicate_dir::DuplicateDirPair};
+
+/// Directories sharing a file which are compared with each other, so that a file copied to many
+/// directories does not make every pair of them a candidate
+const MAX_CANDIDATE_DIR_COUNT: usize = 16;
+
+/// Path of a file or directory in the directory
+pub fn join_path(dir_path: &str, name: &str) -> String {
+    if dir_path.ends_with('/') {
+        format!("{}{}", dir_path, name)
+    } else {
+        format!("{}/{}", dir_path, name)
+    }
+}
+
+/// Parent directory, none for the root directory
+pub fn parent_dir(dir_path: &str) -> Option<&str> {
+    match dir_path.rfind('/') {
+        Some(0) if dir_path.len() > 1 => Some("/"),
+        Some(0) | None => None,
+        Some(index) => Some(&dir_path[..index]),
+    }
+}
+
+fn dir_name(dir_path: &str) -> &str {
+    dir_path.rsplit('/').next().unwrap_or_default()
+}
+
+/// Whether the path is the directory or in it
+pub fn is_in_dir(path: &str, dir_path: &str) -> bool {
+    path == dir_path || path.starts_with(join_path(dir_path, "").as_str())
+}
+
+/// Directory with the fingerprint of its content
+#[derive(Default)]
+struct DirNode {
+    /// Indexes of the files directly in the directory
+    files: Vec<usize>,
+    sub_dirs: Vec<String>,
+    /// Hash of the names and hashes of the files and subdirectories
+    fingerprint: String,
+    file_count: u64,
+    size: u64,
+}
+
+/// Directory tree of the files, with a fingerprint per directory computed from the names and
+/// hashes of its children, so that directories with the same content have the same fingerprint
+struct DirTree<'a> {
+    files: &'a [FileHash],
+    file_paths: HashMap<String, usize>,
+    dirs: HashMap<String, DirNode>,
+}
+
+impl<'a> DirTree<'a> {
+    fn new(files: &'a [FileHash]) -> Self {
+        let mut file_paths = HashMap::new();
+        let mut dirs: HashMap<String, DirNode> = HashMap::new();
+        for (index, file) in files.iter().enumerate() {
+            file_paths.insert(join_path(&file.dir_path, &file.file_name), index);
+            dirs.entry(file.dir_path.clone())
+                .or_default()
+                .files
+                .push(index);
+        }
+        // add the ancestors of the directories
+        let mut dir_paths = dirs.keys().cloned().collect::<Vec<_>>();
+        while let Some(dir_path) = dir_paths.pop() {
+            let Some(parent_path) = parent_dir(&dir_path) else {
+                continue;
+            };
+            let parent_path = parent_path.to_string();
+            let is_new = !dirs.contains_key(&parent_path);
+            dirs.entry(parent_path.clone())
+                .or_default()
+                .sub_dirs
+                .push(dir_path);
+            if is_new {
+                dir_paths.push(parent_path);
+            }
+        }
+
+        // children are hashed before their parents
+        let mut dir_paths = dirs.keys().cloned().collect::<Vec<_>>();
+        dir_paths.sort_by_key(|dir_path| std::cmp::Reverse(dir_path.matches('/').count()));
+        for dir_path in dir_paths {
+            let node = &dirs[&dir_path];
+            let mut entries = Vec::new();
+            let mut file_count = 0;
+            let mut size = 0;
+            for index in node.files.iter() {
+                let file = &files[*index];
+                entries.push((file.file_name.as_str(), 'f', file.md5.as_str()));
+                file_count += 1;
+                size += file.size;
+            }
+            for sub_dir_path in node.sub_dirs.iter() {
+                let sub_dir = &dirs[sub_dir_path];
+                entries.push((dir_name(sub_dir_path), 'd', sub_dir.fingerprint.as_str()));
+                file_count += sub_dir.file_count;
+                size += sub_dir.size;
+            }
+            entries.sort();
+            let mut hasher = Md5::new();
+            for (name, kind, hash) in entries {
+                hasher.update(format!("{}\0{}\0{}\n", kind, name, hash).as_bytes());
+            }
+            let fingerprint = format!("{:x}", hasher.finalize());
+            let node = dirs.get_mut(&dir_path).expect("directory is added");
+            node.fingerprint = fingerprint;
+            node.file_count = file_count;
+            node.size = size;
+        }
+        DirTree {
+            files,
+            file_paths,
+            dirs,
+        }
+    }
+
+    /// Whether the parents of both directories are identical, then the pair is covered by them
+    fn is_parent_identical(&self, dir_path: &str, other_dir_path: &str) -> bool {
+        match (parent_dir(dir_path), parent_dir(other_dir_path)) {
+            (Some(parent_path), Some(other_parent_path)) if parent_path != other_parent_path => {
+                self.dirs[parent_path].fingerprint == self.dirs[other_parent_path].fingerprint
+            }
+            _ => false,
+        }
+    }
+
+    /// Visit the files of the directory and its subdirectories, with the path relative to it
+    fn visit_files(
+        &self,
+        dir_path: &str,
+        relative_path: &str,
+        visit: &mut impl FnMut(&str, &FileHash),
+    ) {
+        let node = &self.dirs[dir_path];
+        for index in node.files.iter() {
+            let file = &self.files[*index];
+            visit(join_path(relative_path, &file.file_name).as_str(), file);
+        }
+        for sub_dir_path in node.sub_dirs.iter() {
+            let relative_path = join_path(relative_path, dir_name(sub_dir_path));
+            self.visit_files(sub_dir_path, relative_path.as_str(), visit);
+        }
+    }
+
+    fn compare(&self, dir_path: &str, other_dir_path: &str) -> DuplicateDirPair {
+        let node = &self.dirs[dir_path];
+        let other_node = &self.dirs[other_dir_path];
+        let identical = node.fingerprint == other_node.fingerprint;
+        let (mut shared_file_count, mut shared_size) = (0, 0);
+        if identical {
+            (shared_file_count, shared_size) = (node.file_count, node.size);
+        } else {
+            self.visit_files(dir_path, "", &mut |relative_path, file| {
+                let other_path =
+                    format!("{}{}", other_dir_path.trim_end_matches('/'), relative_path);
+                if let Some(other_index) = self.file_paths.get(&other_path) {
+                    if self.files[*other_index].md5 == file.md5 {
+                        shared_file_count += 1;
+                        shared_size += file.size;
+                    }
+                }
+            });
+        }
+        let total_file_count = node.file_count + other_node.file_count - shared_file_count;
+        DuplicateDirPair {
+            dir_path: dir_path.to_string(),
+            other_dir_path: other_dir_path.to_string(),
+            identical,
+            overlap_percent: shared_file_count as f64 * 100.0 / total_file_count.max(1) as f64,
+            file_count: node.file_count,
+            size: node.size,
+            other_file_count: other_node.file_count,
+            other_size: other_node.size,
+            shared_file_count,
+            shared_size,
+        }
+    }
+}
+
+fn ordered_pair(dir_path: &str, other_dir_path: &str) -> (String, String) {
+    if dir_path <= other_dir_path {
+        (dir_path.to_string(), other_dir_path.to_string())
+    } else {
+        (other_dir_path.to_string(), dir_path.to_string())
+    }
+}
+
+/// Find identical and near-identical directory pairs, biggest shared size first. Pairs whose
+/// parents are reported as well are left out, as removing the parent covers them.
+pub fn find_duplicate_dirs(files: &[FileHash], min_overlap_percent: f64) -> Vec<DuplicateDirPair> {
+    let tree = DirTree::new(files);
+
+    // directories with a file of the same name and md5 are candidates, and so are their parents
+    // as long as the directories have the same name, e.g. Photos/2019 and Backup/Photos/2019
+    let mut dirs_by_file: HashMap<(&str, &str), Vec<&str>> = HashMap::new();
+    for file in files.iter() {
+        let dirs = dirs_by_file
+            .entry((file.file_name.as_str(), file.md5.as_str()))
+            .or_default();
+        if dirs.len() < MAX_CANDIDATE_DIR_COUNT {
+            dirs.push(file.dir_path.as_str());
+        }
+    }
+    let mut candidates = HashSet::new();
+    for dirs in dirs_by_file.values() {
+        for (index, dir_path) in dirs.iter().enumerate() {
+            for other_dir_path in dirs[index + 1..].iter() {
+                let (mut dir_path, mut other_dir_path) = ordered_pair(dir_path, other_dir_path);
+                loop {
+                    if is_in_dir(&dir_path, &other_dir_path)
+                        || is_in_dir(&other_dir_path, &dir_path)
+                    {
+                        break;
+                    }
+                    let same_name = dir_name(&dir_path) == dir_name(&other_dir_path);
+                    if !candidates.insert((dir_path.clone(), other_dir_path.clone())) || !same_name
+                    {
+                        break;
+                    }
+                    match (parent_dir(&dir_path), parent_dir(&other_dir_path)) {
+                        (Some(parent_path), Some(other_parent_path)) => {
+                            (dir_path, other_dir_path) =
+                                ordered_pair(parent_path, other_parent_path);
+                        }
+                        _ => break,
+                    }
+                }
+            }
+        }
+    }
+
+    // identical directories which do not share a file name, e.g. renamed files, are found by
+    // their fingerprint
+    let mut dirs_by_fingerprint: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (dir_path, node) in tree.dirs.iter() {
+        dirs_by_fingerprint
+            .entry(node.fingerprint.as_str())
+            .or_default()
+            .push(dir_path.as_str());
+    }
+    for dirs in dirs_by_fingerprint.values_mut() {
+        dirs.sort();
+        for other_dir_path in dirs.iter().skip(1) {
+            candidates.insert(ordered_pair(dirs[0], other_dir_path));
+        }
+    }
+
+    let mut duplicate_dirs = candidates
+        .into_iter()
+        .filter(|(dir_path, other_dir_path)| !tree.is_parent_identical(dir_path, other_dir_path))
+        .map(|(dir_path, other_dir_path)| tree.compare(&dir_path, &other_dir_path))
+        .filter(|pair| {
+            pair.shared_file_count > 0
+                && (pair.identical || pair.overlap_percent >= min_overlap_percent)
+        })
+        .collect::<Vec<_>>();
+    let reported_pairs = duplicate_dirs
+        .iter()
+        .map(|pair| (pair.dir_path.clone(), pair.other_dir_path.clone()))
+        .collect::<HashSet<_>>();
+    duplicate_dirs.retain(|pair| {
+        match (parent_dir(&pair.dir_path), parent_dir(&pair.other_dir_path)) {
+            (Some(parent_path), Some(other_parent_path)) => {
+                !reported_pairs.contains(&ordered_pair(parent_path, other_parent_path))
+            }
+            _ => true,
+        }
+    });
+    duplicate_dirs.sort_by(|a, b| {
+        b.shared_size
+            .cmp(&a.shared_size)
+            .then_with(|| a.dir_path.cmp(&b.dir_path))
+            .then_with(|| a.other_dir_path.cmp(&b.other_dir_path))
+    });
+    duplicate_dirs
+}
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+
+use image::{imageops::FilterType, DynamicImage};
+
+use crate::{
+    database::image_hash::{ImageHash, ImageHashFile},
+    model::{
+        common::ErrorCode,
+        image_hash::{ImageHashAlgorithm, SimilarImage, SimilarImageGroup},
+    },
+    utils::{duplicate_dir::join_path, error::DfrError},
+};
+
+/// Extensions of the image formats which are decoded
+pub const IMAGE_EXTENSIONS: [&str; 8] = ["bmp", "gif", "jpeg", "jpg", "png", "tif", "tiff", "webp"];
+
+/// Size of the thumbnail the DCT of the perceptual hash is computed from
+const PHASH_SIZE: usize = 32;
+/// Frequencies of each dimension kept in the perceptual hash
+const PHASH_FREQUENCY_COUNT: usize = 8;
+
+pub fn is_image_file(file_name: &str) -> bool {
+    match file_name.rsplit_once('.') {
+        Some((_, extension)) => IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()),
+        None => false,
+    }
+}
+
+pub fn hamming_distance(hash: u64, other_hash: u64) -> u32 {
+    (hash ^ other_hash).count_ones()
+}
+
+/// Decode the image and compute its perceptual hashes
+pub fn compute_image_hash(file_path: &str, md5: &str) -> Result<ImageHash, DfrError> {
+    let image = match image::open(file_path) {
+        Ok(image) => image,
+        Err(e) => {
+            return DfrError::custom_error(
+                ErrorCode::MEDIA_DECODE_FAILED,
+                format!("Failed to decode image {}: {}", file_path, e),
+            )
+        }
+    };
+    Ok(ImageHash {
+        md5: md5.to_string(),
+        dhash: dhash(&image),
+        phash: phash(&image),
+        width: image.width(),
+        height: image.height(),
+    })
+}
+
+pub fn dhash(image: &DynamicImage) -> u64 {
+    let thumbnail = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            let bit = thumbnail.get_pixel(x, y)[0] < thumbnail.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | bit as u64;
+        }
+    }
+    hash
+}
+
+pub fn phash(image: &DynamicImage) -> u64 {
+    let thumbnail = image
+        .resize_exact(PHASH_SIZE as u32, PHASH_SIZE as u32, FilterType::Triangle)
+        .to_luma8();
+    let pixels = thumbnail
+        .pixels()
+        .map(|pixel| pixel[0] as f64)
+        .collect::<Vec<_>>();
+    // the 2D DCT is computed by rows then columns, only for the kept frequencies
+    let cosines = (0..PHASH_FREQUENCY_COUNT)
+        .map(|frequency| {
+            (0..PHASH_SIZE)
+                .map(|index| {
+                    (std::f64::consts::PI * frequency as f64 * (2 * index + 1) as f64
+                        / (2 * PHASH_SIZE) as f64)
+                        .cos()
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut row_dct = vec![[0.0; PHASH_FREQUENCY_COUNT]; PHASH_SIZE];
+    for (y, row) in row_dct.iter_mut().enumerate() {
+        for (u, value) in row.iter_mut().enumerate() {
+            *value = (0..PHASH_SIZE)
+                .map(|x| pixels[y * PHASH_SIZE + x] * cosines[u][x])
+                .sum();
+        }
+    }
+    let mut coefficients = Vec::with_capacity(PHASH_FREQUENCY_COUNT * PHASH_FREQUENCY_COUNT);
+    for column_cosines in cosines.iter() {
+        for u in 0..PHASH_FREQUENCY_COUNT {
+            coefficients.push(
+                row_dct
+                    .iter()
+                    .zip(column_cosines.iter())
+                    .map(|(row, cosine)| row[u] * cosine)
+                    .sum::<f64>(),
+            );
+        }
+    }
+    // the DC coefficient is the average brightness, it is left out of the median
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    coefficients.iter().fold(0, |hash, coefficient| {
+        (hash << 1) | (*coefficient > median) as u64
+    })
+}
+
+/// BK-tree of hashes, so that the hashes within a hamming distance are found without comparing
+/// all pairs
+struct BkTree {
+    /// Hash, index of the hash and children by their distance to the hash
+    nodes: Vec<(u64, usize, HashMap<u32, usize>)>,
+}
+
+impl BkTree {
+    fn new() -> Self {
+        BkTree { nodes: Vec::new() }
+    }
+
+    fn insert(&mut self, hash: u64, index: usize) {
+        let new_node = self.nodes.len();
+        if new_node > 0 {
+            let mut node = 0;
+            loop {
+                let distance = hamming_distance(self.nodes[node].0, hash);
+                match self.nodes[node].2.get(&distance) {
+                    Some(child) => node = *child,
+                    None => {
+                        self.nodes[node].2.insert(distance, new_node);
+                        break;
+                    }
+                }
+            }
+        }
+        self.nodes.push((hash, index, HashMap::new()));
+    }
+
+    /// Indexes of the hashes within the distance
+    fn find(&self, hash: u64, max_distance: u32) -> Vec<usize> {
+        let mut indexes = Vec::new();
+        let mut nodes = if self.nodes.is_empty() {
+            vec![]
+        } else {
+            vec![0]
+        };
+        while let Some(node) = nodes.pop() {
+            let (node_hash, index, children) = &self.nodes[node];
+            let distance = hamming_distance(*node_hash, hash);
+            if distance <= max_distance {
+                indexes.push(*index);
+            }
+            for (child_distance, child) in children.iter() {
+                if child_distance.abs_diff(distance) <= max_distance {
+                    nodes.push(*child);
+                }
+            }
+        }
+        indexes
+    }
+}
+
+/// Root of the set of the index in a union-find forest, the path to it is compressed
+pub(crate) fn find_root(parents: &mut [usize], index: usize) -> usize {
+    let mut root = index;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    let mut index = index;
+    while parents[index] != root {
+        (parents[index], index) = (root, parents[index]);
+    }
+    root
+}
+
+/// Group images within the hamming distance of each other, files with the same md5 are one
+/// image. Only groups of more than one image are returned, the biggest total size first.
+pub fn find_similar_images(
+    files: &[ImageHashFile],
+    max_distance: u32,
+    algorithm: ImageHashAlgorithm,
+) -> Vec<SimilarImageGroup> {
+    let mut images: Vec<SimilarImage> = Vec::new();
+    let mut hashes = Vec::new();
+    let mut image_indexes: HashMap<&str, usize> = HashMap::new();
+    for file in files.iter() {
+        let image_hash = &file.image_hash;
+        let index = *image_indexes
+            .entry(image_hash.md5.as_str())
+            .or_insert_with(|| {
+                images.push(SimilarImage {
+                    md5: image_hash.md5.clone(),
+                    width: image_hash.width,
+                    height: image_hash.height,
+                    size: file.size,
+                    distance: 0,
+                    files: Vec::new(),
+                });
+                hashes.push(match algorithm {
+                    ImageHashAlgorithm::Phash => image_hash.phash,
+                    ImageHashAlgorithm::Dhash => image_hash.dhash,
+                });
+                images.len() - 1
+            });
+        images[index]
+            .files
+            .push(join_path(&file.dir_path, &file.file_name));
+    }
+
+    let mut tree = BkTree::new();
+    let mut parents = (0..images.len()).collect::<Vec<_>>();
+    for (index, hash) in hashes.iter().enumerate() {
+        for other_index in tree.find(*hash, max_distance) {
+            let (root, other_root) = (
+                find_root(&mut parents, index),
+                find_root(&mut parents, other_index),
+            );
+            parents[root] = other_root;
+        }
+        tree.insert(*hash, index);
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for index in 0..images.len() {
+        let root = find_root(&mut parents, index);
+        groups.entry(root).or_default().push(index);
+    }
+    let mut similar_image_groups = groups
+        .into_values()
+        .filter(|indexes| indexes.len() > 1)
+        .map(|mut indexes| {
+            // the biggest image is the first, as it is most likely the original
+            indexes.sort_by_key(|index| {
+                let image = &images[*index];
+                (
+                    std::cmp::Reverse(image.width as u64 * image.height as u64),
+                    std::cmp::Reverse(image.size),
+                    image.md5.clone(),
+                )
+            });
+            let first_hash = hashes[indexes[0]];
+            let images = indexes
+                .into_iter()
+                .map(|index| SimilarImage {
+                    distance: hamming_distance(first_hash, hashes[index]),
+                    ..images[index].clone()
+                })
+                .collect::<Vec<_>>();
+            let total_size = images
+                .iter()
+                .map(|image| image.size * image.files.len() as u64)
+                .sum();
+            SimilarImageGroup { images, total_size }
+        })
+        .collect::<Vec<_>>();
+    similar_image_groups.sort_by(|a, b| {
+        b.total_size
+            .cmp(&a.total_size)
+            .then_with(|| a.images[0].md5.cmp(&b.images[0].md5))
+    });
+    similar_image_groups
+}
@@ -0,0 +1,81 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde_json::Value;
+
+use crate::{
+    database::user_info::UserRole, model::common::ErrorCode, model::settings::OidcSettings,
+    utils::error::DfrError,
+};
+
+/// Authentication methods of the `amr` claim which prove a second factor
+const MFA_AUTH_METHODS: [&str; 4] = ["mfa", "otp", "hwk", "swk"];
+
+/// User authenticated by the identity provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OidcUser {
+    pub user_name: String,
+    /// Role granted by the role claim, none if no role mapping is configured
+    pub role: Option<UserRole>,
+    /// The identity provider authenticated the user with a second factor
+    pub mfa: bool,
+}
+
+/// Decode the claims of a JWT without verifying it, the signature must be verified before
+pub fn decode_jwt_claims(token: &str) -> Result<Value, DfrError> {
+    let Some(payload) = token.split('.').nth(1) else {
+        return DfrError::custom_error(
+            ErrorCode::OIDC_LOGIN_FAILED,
+            String::from("Invalid ID token"),
+        );
+    };
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .or_else(|e| DfrError::custom_error(ErrorCode::OIDC_LOGIN_FAILED, e.to_string()))?;
+    serde_json::from_slice(&payload)
+        .or_else(|e| DfrError::custom_error(ErrorCode::OIDC_LOGIN_FAILED, e.to_string()))
+}
+
+/// Values of a claim which is a string or a list of strings
+fn claim_values(claims: &Value, claim: &str) -> Vec<String> {
+    match claims.get(claim) {
+        Some(Value::String(value)) => vec![value.clone()],
+        Some(Value::Array(values)) => values
+            .iter()
+            .filter_map(|value| value.as_str().map(String::from))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Map the verified claims of the ID token to a user name and role
+pub fn map_oidc_claims(claims: &Value, settings: &OidcSettings) -> Result<OidcUser, DfrError> {
+    let user_name = match claims.get(settings.username_claim.as_str()) {
+        Some(Value::String(user_name)) if !user_name.trim().is_empty() => {
+            user_name.trim().to_string()
+        }
+        _ => {
+            return DfrError::custom_error(
+                ErrorCode::OIDC_LOGIN_FAILED,
+                format!("Claim {} is missing in ID token", settings.username_claim),
+            )
+        }
+    };
+    let role = if settings.role_mapping.is_empty() {
+        None
+    } else {
+        let role = claim_values(claims, settings.role_claim.as_str())
+            .iter()
+            .filter_map(|value| settings.role_mapping.get(value))
+            .max()
+            .copied()
+            .unwrap_or(settings.default_role);
+        Some(role)
+    };
+    let mfa = claim_values(claims, "amr")
+        .iter()
+        .any(|method| MFA_AUTH_METHODS.contains(&method.as_str()));
+    Ok(OidcUser {
+        user_name,
+        role,
+        mfa,
+    })
+}
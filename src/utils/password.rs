@@ -0,0 +1,38 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use log::error;
+
+use crate::{model::common::ErrorCode, utils::error::DfrError};
+
+/// Hash password with Argon2id, return the hash in PHC string format
+pub fn hash_password(password: &str) -> Result<String, DfrError> {
+    let salt = SaltString::generate(&mut OsRng);
+    match Argon2::default().hash_password(password.as_bytes(), &salt) {
+        Ok(password_hash) => Ok(password_hash.to_string()),
+        Err(e) => DfrError::custom_error(
+            ErrorCode::SYSTEM_ERROR,
+            format!("Failed to hash password: {}", e),
+        ),
+    }
+}
+
+/// Verify password against the hash in PHC string format, the hash output is compared in constant time
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    let parsed_hash = match PasswordHash::new(password_hash) {
+        Ok(parsed_hash) => parsed_hash,
+        Err(e) => {
+            error!("Invalid password hash: {}", e);
+            return false;
+        }
+    };
+    Argon2::default()
+        .verify_password(password.as_bytes(), &parsed_hash)
+        .is_ok()
+}
+
+/// Check if the value is a password hash in PHC string format
+pub fn is_password_hash(value: &str) -> bool {
+    PasswordHash::new(value).is_ok()
+}
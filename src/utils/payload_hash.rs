@@ -0,0 +1,215 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+};
+
+use md5::{Digest, Md5};
+
+use crate::{model::common::ErrorCode, utils::error::DfrError};
+
+/// Extensions of the formats whose metadata is stripped from the payload hash
+pub const PAYLOAD_HASH_EXTENSIONS: [&str; 8] =
+    ["jpeg", "jpg", "m4a", "m4v", "mov", "mp3", "mp4", "png"];
+
+/// JPEG segments of EXIF and XMP (APP1), IPTC (APP13) and comments (COM)
+const JPEG_METADATA_MARKERS: [u8; 3] = [0xE1, 0xED, 0xFE];
+/// PNG chunks of EXIF, text, XMP (iTXt) and the modification time
+const PNG_METADATA_CHUNKS: [&[u8; 4]; 5] = [b"eXIf", b"iTXt", b"tEXt", b"zTXt", b"tIME"];
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+/// Size of the ID3v1 tag at the end of an mp3 file
+const ID3V1_SIZE: u64 = 128;
+
+pub fn is_payload_hash_file(file_name: &str) -> bool {
+    match file_name.rsplit_once('.') {
+        Some((_, extension)) => {
+            PAYLOAD_HASH_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+        }
+        None => false,
+    }
+}
+
+/// Calculate md5 of the file content without its metadata, so that copies of a photo or a song
+/// only differing in tags have the same payload md5. None if the format is not supported.
+pub fn payload_md5(file_path: &str) -> Result<Option<String>, DfrError> {
+    let Some((_, extension)) = file_path.rsplit_once('.') else {
+        return Ok(None);
+    };
+    let mut reader = BufReader::new(File::open(file_path)?);
+    let mut hasher = Md5::new();
+    let result = match extension.to_lowercase().as_str() {
+        "jpeg" | "jpg" => hash_jpeg_payload(&mut reader, &mut hasher),
+        "png" => hash_png_payload(&mut reader, &mut hasher),
+        "mp3" => hash_mp3_payload(&mut reader, &mut hasher),
+        "m4a" | "m4v" | "mov" | "mp4" => hash_mp4_payload(&mut reader, &mut hasher),
+        _ => return Ok(None),
+    };
+    match result {
+        Ok(()) => Ok(Some(format!("{:x}", hasher.finalize()))),
+        Err(e)
+            if e.kind() == io::ErrorKind::InvalidData
+                || e.kind() == io::ErrorKind::UnexpectedEof =>
+        {
+            DfrError::custom_error(
+                ErrorCode::MEDIA_DECODE_FAILED,
+                format!("Failed to parse {}: {}", file_path, e),
+            )
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Hash the next bytes of the reader, the reader must have them all
+fn hash_bytes<R: Read>(reader: &mut R, hasher: &mut Md5, size: u64) -> io::Result<()> {
+    let copied = io::copy(&mut reader.by_ref().take(size), hasher)?;
+    if copied < size {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+    let mut buffer = [0; 2];
+    reader.read_exact(&mut buffer)?;
+    Ok(u16::from_be_bytes(buffer))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buffer = [0; 4];
+    reader.read_exact(&mut buffer)?;
+    Ok(u32::from_be_bytes(buffer))
+}
+
+/// Hash the segments before the image data except the metadata ones, then the image data
+fn hash_jpeg_payload<R: Read + Seek>(reader: &mut R, hasher: &mut Md5) -> io::Result<()> {
+    if read_u16(reader)? != 0xFFD8 {
+        return Err(invalid_data("missing JPEG start of image"));
+    }
+    hasher.update([0xFF, 0xD8]);
+    loop {
+        let mut marker = [0; 2];
+        reader.read_exact(&mut marker)?;
+        if marker[0] != 0xFF {
+            return Err(invalid_data("invalid JPEG marker"));
+        }
+        // markers may be padded with fill bytes
+        while marker[1] == 0xFF {
+            reader.read_exact(&mut marker[1..])?;
+        }
+        let length = read_u16(reader)?;
+        if length < 2 {
+            return Err(invalid_data("invalid JPEG segment length"));
+        }
+        if JPEG_METADATA_MARKERS.contains(&marker[1]) {
+            reader.seek(SeekFrom::Current(length as i64 - 2))?;
+            continue;
+        }
+        hasher.update(marker);
+        hasher.update(length.to_be_bytes());
+        if marker[1] == 0xDA {
+            // start of scan, the entropy coded data up to the end of the file has no metadata
+            io::copy(reader, hasher)?;
+            return Ok(());
+        }
+        hash_bytes(reader, hasher, length as u64 - 2)?;
+    }
+}
+
+/// Hash type and data of the chunks except the metadata ones, up to the image end
+fn hash_png_payload<R: Read + Seek>(reader: &mut R, hasher: &mut Md5) -> io::Result<()> {
+    let mut signature = [0; 8];
+    reader.read_exact(&mut signature)?;
+    if signature != PNG_SIGNATURE {
+        return Err(invalid_data("missing PNG signature"));
+    }
+    loop {
+        let length = read_u32(reader)?;
+        let mut chunk_type = [0; 4];
+        reader.read_exact(&mut chunk_type)?;
+        if PNG_METADATA_CHUNKS.contains(&&chunk_type) {
+            // data and crc
+            reader.seek(SeekFrom::Current(length as i64 + 4))?;
+            continue;
+        }
+        hasher.update(chunk_type);
+        hash_bytes(reader, hasher, length as u64)?;
+        reader.seek(SeekFrom::Current(4))?;
+        if &chunk_type == b"IEND" {
+            return Ok(());
+        }
+    }
+}
+
+/// Hash the audio frames between the ID3v2 tags at the start and the ID3v1 tag at the end
+fn hash_mp3_payload<R: Read + Seek>(reader: &mut R, hasher: &mut Md5) -> io::Result<()> {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    let mut end = file_size;
+    if file_size >= ID3V1_SIZE {
+        reader.seek(SeekFrom::End(-(ID3V1_SIZE as i64)))?;
+        let mut tag = [0; 3];
+        reader.read_exact(&mut tag)?;
+        if &tag == b"TAG" {
+            end -= ID3V1_SIZE;
+        }
+    }
+    let mut start = 0;
+    // an ID3v2 tag is a 10 bytes header and a syncsafe size, there may be more than one
+    while start + 10 <= end {
+        reader.seek(SeekFrom::Start(start))?;
+        let mut header = [0; 10];
+        reader.read_exact(&mut header)?;
+        if &header[..3] != b"ID3" {
+            break;
+        }
+        if header[6..].iter().any(|byte| byte & 0x80 != 0) {
+            return Err(invalid_data("invalid ID3v2 tag size"));
+        }
+        let size = header[6..]
+            .iter()
+            .fold(0u64, |size, byte| (size << 7) | *byte as u64);
+        // the footer flag adds a copy of the header at the end of the tag
+        let footer_size = if header[5] & 0x10 != 0 { 10 } else { 0 };
+        start = (start + 10 + size + footer_size).min(end);
+    }
+    reader.seek(SeekFrom::Start(start))?;
+    hash_bytes(reader, hasher, end - start)
+}
+
+/// Hash the media data boxes, which leaves out the metadata in `moov/udta` and `meta`. The sample
+/// tables are left out too, as their offsets change if the metadata before the media data grows.
+fn hash_mp4_payload<R: Read + Seek>(reader: &mut R, hasher: &mut Md5) -> io::Result<()> {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    let mut position = 0;
+    let mut has_media_data = false;
+    while position < file_size {
+        reader.seek(SeekFrom::Start(position))?;
+        let mut box_size = read_u32(reader)? as u64;
+        let mut box_type = [0; 4];
+        reader.read_exact(&mut box_type)?;
+        let mut header_size = 8;
+        if box_size == 1 {
+            let mut large_size = [0; 8];
+            reader.read_exact(&mut large_size)?;
+            box_size = u64::from_be_bytes(large_size);
+            header_size = 16;
+        } else if box_size == 0 {
+            // the last box extends to the end of the file
+            box_size = file_size - position;
+        }
+        if box_size < header_size || box_size > file_size - position {
+            return Err(invalid_data("invalid MP4 box size"));
+        }
+        if &box_type == b"mdat" {
+            hash_bytes(reader, hasher, box_size - header_size)?;
+            has_media_data = true;
+        }
+        position += box_size;
+    }
+    if !has_media_data {
+        return Err(invalid_data("missing MP4 media data"));
+    }
+    Ok(())
+}
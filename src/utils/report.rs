@@ -0,0 +1,178 @@
+use std::fmt::Write;
+
+use crate::model::{
+    export::DuplicateGroup,
+    report::{DuplicateReport, ReportBucket},
+};
+
+/// Inline style, the report is a single file which is readable offline, e.g. as mail attachment
+const REPORT_STYLE: &str = "
+body { font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; margin: 2em auto; max-width: 1100px; color: #222; padding: 0 1em; }
+h1 { margin-bottom: 0.2em; }
+.generated { color: #666; margin-top: 0; }
+.totals { display: flex; flex-wrap: wrap; gap: 1em; }
+.total { border: 1px solid #ddd; border-radius: 6px; padding: 0.8em 1.2em; min-width: 180px; }
+.total .value { font-size: 1.6em; font-weight: bold; }
+.total .label { color: #666; }
+table { border-collapse: collapse; width: 100%; margin: 0.5em 0 1em; }
+th, td { text-align: left; padding: 0.3em 0.6em; border-bottom: 1px solid #eee; word-break: break-all; }
+th { background: #f5f5f5; }
+td.number, th.number { text-align: right; white-space: nowrap; word-break: normal; }
+details { border: 1px solid #ddd; border-radius: 6px; margin: 0.4em 0; padding: 0.4em 0.8em; }
+summary { cursor: pointer; }
+summary .md5 { color: #666; font-family: monospace; }
+";
+
+/// Escape text to be put in html elements or attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Human readable size, e.g. 1.5 GiB
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", size)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn write_total(html: &mut String, label: &str, value: &str) {
+    let _ = write!(
+        html,
+        "<div class=\"total\"><div class=\"value\">{}</div><div class=\"label\">{}</div></div>",
+        escape_html(value),
+        escape_html(label)
+    );
+}
+
+fn write_group(html: &mut String, group: &DuplicateGroup) {
+    let _ = write!(
+        html,
+        "<details><summary><strong>{}</strong> reclaimable, {} files of {} <span class=\"md5\">{}</span></summary>",
+        format_size(group.reclaimable_size),
+        group.md5_count,
+        format_size(group.size),
+        escape_html(group.md5.as_str())
+    );
+    html.push_str(
+        "<table><tr><th>Path</th><th class=\"number\">Inode</th><th class=\"number\">Links</th><th class=\"number\">Modified</th></tr>",
+    );
+    for file in group.files.iter() {
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>",
+            escape_html(file.file_path.as_str()),
+            file.inode,
+            file.nlink,
+            file.modified.format("%Y-%m-%d %H:%M:%S")
+        );
+    }
+    html.push_str("</table></details>\n");
+}
+
+fn write_buckets(html: &mut String, title: &str, name_header: &str, buckets: &[ReportBucket]) {
+    let _ = writeln!(html, "<h2>{}</h2>", escape_html(title));
+    if buckets.is_empty() {
+        html.push_str("<p>No duplicate files.</p>\n");
+        return;
+    }
+    let _ = write!(
+        html,
+        "<table><tr><th>{}</th><th class=\"number\">Files</th><th class=\"number\">Size</th></tr>",
+        escape_html(name_header)
+    );
+    for bucket in buckets {
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>",
+            escape_html(bucket.name.as_str()),
+            bucket.file_count,
+            format_size(bucket.duplicate_size)
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+/// Render the report as a self-contained html page, without scripts or external resources
+pub fn render_html_report(report: &DuplicateReport) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    html.push_str("<title>Duplicate File Report</title>\n<style>");
+    html.push_str(REPORT_STYLE);
+    html.push_str("</style>\n</head>\n<body>\n<h1>Duplicate File Report</h1>\n");
+    let _ = writeln!(
+        html,
+        "<p class=\"generated\">Generated at {}</p>",
+        report.generated_time.format("%Y-%m-%d %H:%M:%S %:z")
+    );
+
+    html.push_str("<h2>Summary</h2>\n<div class=\"totals\">");
+    let summary = &report.summary;
+    write_total(
+        &mut html,
+        "Reclaimable",
+        format_size(summary.reclaimable_size).as_str(),
+    );
+    write_total(
+        &mut html,
+        "Duplicate groups",
+        summary.group_count.to_string().as_str(),
+    );
+    write_total(
+        &mut html,
+        "Duplicate files",
+        summary.file_count.to_string().as_str(),
+    );
+    write_total(
+        &mut html,
+        "Size of duplicate files",
+        format_size(summary.duplicate_size).as_str(),
+    );
+    html.push_str("</div>\n");
+
+    let _ = writeln!(
+        html,
+        "<h2>Top {} Biggest Duplicate Groups</h2>",
+        report.top_groups.len()
+    );
+    if report.top_groups.is_empty() {
+        html.push_str("<p>No duplicate files.</p>\n");
+    }
+    for group in report.top_groups.iter() {
+        write_group(&mut html, group);
+    }
+    write_buckets(
+        &mut html,
+        "Duplicates by Directory",
+        "Directory",
+        &report.directories,
+    );
+    write_buckets(
+        &mut html,
+        "Duplicates by Extension",
+        "Extension",
+        &report.extensions,
+    );
+    html.push_str("</body>\n</html>\n");
+    html
+}
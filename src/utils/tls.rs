@@ -0,0 +1,207 @@
+use std::{
+    fs,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header,
+    middleware::Next,
+    web, HttpResponse,
+};
+use log::{error, info};
+use rustls::{
+    crypto::ring,
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier},
+    sign::CertifiedKey,
+    RootCertStore, ServerConfig,
+};
+use tokio::time::{self, Instant, MissedTickBehavior};
+
+use crate::{
+    model::{common::ErrorCode, settings::SystemSettings},
+    utils::error::DfrError,
+    SharedSettings,
+};
+
+fn tls_error<T>(path: &str, message: impl ToString) -> Result<T, DfrError> {
+    DfrError::custom_error(
+        ErrorCode::SYSTEM_ERROR,
+        format!("Failed to load {}: {}", path, message.to_string()),
+    )
+}
+
+/// Load the PEM certificates of the file
+pub fn load_certificates(path: &str) -> Result<Vec<CertificateDer<'static>>, DfrError> {
+    let certificates = CertificateDer::pem_file_iter(path)
+        .or_else(|e| tls_error(path, e))?
+        .collect::<Result<Vec<_>, _>>()
+        .or_else(|e| tls_error(path, e))?;
+    if certificates.is_empty() {
+        return tls_error(path, "no certificate found");
+    }
+    Ok(certificates)
+}
+
+/// Load the certificate chain and private key
+pub fn load_certified_key(cert_path: &str, key_path: &str) -> Result<CertifiedKey, DfrError> {
+    let certificates = load_certificates(cert_path)?;
+    let key = PrivateKeyDer::from_pem_file(key_path).or_else(|e| tls_error(key_path, e))?;
+    let signing_key = ring::sign::any_supported_type(&key).or_else(|e| tls_error(key_path, e))?;
+    let certified_key = CertifiedKey::new(certificates, signing_key);
+    // make sure the key belongs to the certificate
+    certified_key
+        .keys_match()
+        .or_else(|e| tls_error(key_path, e))?;
+    Ok(certified_key)
+}
+
+/// Latest modification time of the files, to detect rotated certificates
+fn modified_time(paths: &[&str]) -> Option<SystemTime> {
+    paths
+        .iter()
+        .filter_map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+        .max()
+}
+
+/// Server certificate which is reloaded when the certificate or key file is changed, so that
+/// renewed certificates take effect without restart
+#[derive(Debug)]
+pub struct ReloadableCertResolver {
+    cert_path: String,
+    key_path: String,
+    certified_key: RwLock<(Arc<CertifiedKey>, Option<SystemTime>)>,
+}
+
+impl ReloadableCertResolver {
+    pub fn new(cert_path: &str, key_path: &str) -> Result<Self, DfrError> {
+        let modified = modified_time(&[cert_path, key_path]);
+        let certified_key = load_certified_key(cert_path, key_path)?;
+        Ok(Self {
+            cert_path: cert_path.to_string(),
+            key_path: key_path.to_string(),
+            certified_key: RwLock::new((Arc::new(certified_key), modified)),
+        })
+    }
+
+    /// Reload the certificate if the files are changed, return whether it is reloaded. The
+    /// current certificate is kept if the new one is invalid, e.g. only one file is written yet.
+    pub fn reload(&self) -> Result<bool, DfrError> {
+        let modified = modified_time(&[self.cert_path.as_str(), self.key_path.as_str()]);
+        let last_modified = match self.certified_key.read() {
+            Ok(certified_key) => certified_key.1,
+            Err(e) => return tls_error(self.cert_path.as_str(), e),
+        };
+        if modified == last_modified {
+            return Ok(false);
+        }
+        let certified_key = load_certified_key(self.cert_path.as_str(), self.key_path.as_str())?;
+        match self.certified_key.write() {
+            Ok(mut current) => *current = (Arc::new(certified_key), modified),
+            Err(e) => return tls_error(self.cert_path.as_str(), e),
+        }
+        Ok(true)
+    }
+
+    /// Current certificate and key
+    pub fn certified_key(&self) -> Option<Arc<CertifiedKey>> {
+        self.certified_key
+            .read()
+            .ok()
+            .map(|certified_key| certified_key.0.clone())
+    }
+}
+
+impl ResolvesServerCert for ReloadableCertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.certified_key()
+    }
+}
+
+/// TLS config of the server, with client certificate verification if a client CA is set
+pub fn tls_server_config(
+    settings: &SystemSettings,
+    cert_resolver: Arc<ReloadableCertResolver>,
+) -> Result<ServerConfig, DfrError> {
+    let provider = Arc::new(ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .or_else(|e| tls_error(settings.tls_cert_path.as_str(), e))?;
+    let builder = if settings.tls_client_ca_path.is_empty() {
+        builder.with_no_client_auth()
+    } else {
+        let mut roots = RootCertStore::empty();
+        for certificate in load_certificates(settings.tls_client_ca_path.as_str())? {
+            roots
+                .add(certificate)
+                .or_else(|e| tls_error(settings.tls_client_ca_path.as_str(), e))?;
+        }
+        let client_verifier =
+            WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .or_else(|e| tls_error(settings.tls_client_ca_path.as_str(), e))?;
+        builder.with_client_cert_verifier(client_verifier)
+    };
+    Ok(builder.with_cert_resolver(cert_resolver))
+}
+
+pub async fn setup_reload_tls_timer(
+    cert_resolver: Arc<ReloadableCertResolver>,
+    interval_s: u32,
+) -> Result<(), DfrError> {
+    if interval_s == 0 {
+        return Ok(());
+    }
+    tokio::spawn(async move {
+        info!("Start to setup reloading TLS certificate timer");
+        let interval = Duration::from_secs(interval_s as u64);
+        let mut intv = time::interval_at(Instant::now() + interval, interval);
+        intv.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            intv.tick().await;
+            match cert_resolver.reload() {
+                Ok(true) => info!("TLS certificate {} is reloaded", cert_resolver.cert_path),
+                Ok(false) => {}
+                Err(e) => error!("Failed to reload TLS certificate: {:?}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Middleware to redirect requests of the plain HTTP listener to HTTPS
+pub async fn redirect_to_https(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    if req.app_config().secure() {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+    let https_port = match req.app_data::<web::Data<SharedSettings>>() {
+        Some(settings) => settings.lock().await.system.port,
+        None => 443,
+    };
+    let host = req.connection_info().host().to_string();
+    let location = https_url(host.as_str(), https_port, req.uri().to_string().as_str());
+    let response = HttpResponse::PermanentRedirect()
+        .insert_header((header::LOCATION, location))
+        .finish();
+    Ok(req.into_response(response).map_into_right_body())
+}
+
+/// HTTPS url of the host with the port replaced by the HTTPS port
+pub fn https_url(host: &str, https_port: u16, path_and_query: &str) -> String {
+    // strip the port, keeping brackets of ipv6 addresses
+    let host_name = match host.rfind(':') {
+        Some(index) if !host[index..].contains(']') => &host[..index],
+        _ => host,
+    };
+    if https_port == 443 {
+        format!("https://{}{}", host_name, path_and_query)
+    } else {
+        format!("https://{}:{}{}", host_name, https_port, path_and_query)
+    }
+}
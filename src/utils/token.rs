@@ -0,0 +1,19 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+/// Prefix of api tokens, makes leaked tokens easy to recognize
+pub const API_TOKEN_PREFIX: &str = "dfr_";
+
+/// Generate a random api token with 256 bits of entropy
+pub fn generate_api_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}{}", API_TOKEN_PREFIX, token)
+}
+
+/// SHA-256 hash of an api token. Api tokens are random enough that a slow password hash is not
+/// needed, and the fast hash allows looking up tokens by hash.
+pub fn hash_api_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
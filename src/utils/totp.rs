@@ -0,0 +1,118 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base32::Alphabet;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use qrcode::{render::svg, QrCode};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::{model::common::ErrorCode, utils::error::DfrError};
+
+/// Issuer shown in authenticator apps
+pub const TOTP_ISSUER: &str = "DupFileRemover";
+/// Number of digits of a TOTP code
+pub const TOTP_DIGITS: u32 = 6;
+/// Seconds a TOTP code is valid
+pub const TOTP_PERIOD_S: i64 = 30;
+/// Codes of the previous and next time step are accepted as well, to allow for clock drift
+pub const TOTP_SKEW_STEPS: i64 = 1;
+/// Number of recovery codes generated on enrollment
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+const BASE32_ALPHABET: Alphabet = Alphabet::Rfc4648 { padding: false };
+
+/// Generate a random base32 encoded TOTP secret of 160 bits, as recommended by RFC 4226
+pub fn generate_totp_secret() -> String {
+    let mut bytes = [0u8; 20];
+    OsRng.fill_bytes(&mut bytes);
+    base32::encode(BASE32_ALPHABET, &bytes)
+}
+
+/// Time step of a unix timestamp
+pub fn totp_step(timestamp: i64) -> i64 {
+    timestamp.div_euclid(TOTP_PERIOD_S)
+}
+
+/// HOTP code of RFC 4226 for the time step, with HMAC-SHA1 as used by common authenticator apps
+pub fn totp_code(secret: &str, step: i64) -> Result<String, DfrError> {
+    let Some(key) = base32::decode(BASE32_ALPHABET, secret) else {
+        return DfrError::custom_error(
+            ErrorCode::SYSTEM_ERROR,
+            String::from("Invalid TOTP secret"),
+        );
+    };
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key)
+        .or_else(|e| DfrError::custom_error(ErrorCode::SYSTEM_ERROR, e.to_string()))?;
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    // dynamic truncation
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    Ok(format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_DIGITS),
+        width = TOTP_DIGITS as usize
+    ))
+}
+
+/// Verify a TOTP code at the timestamp, return the matched time step
+pub fn verify_totp(secret: &str, code: &str, timestamp: i64) -> Result<Option<i64>, DfrError> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+    let current_step = totp_step(timestamp);
+    for step in current_step - TOTP_SKEW_STEPS..=current_step + TOTP_SKEW_STEPS {
+        if totp_code(secret, step)? == code {
+            return Ok(Some(step));
+        }
+    }
+    Ok(None)
+}
+
+/// `otpauth://` uri to provision authenticator apps, usually shown as QR code
+pub fn totp_provisioning_uri(secret: &str, user_name: &str) -> String {
+    let issuer = utf8_percent_encode(TOTP_ISSUER, NON_ALPHANUMERIC).to_string();
+    let account = utf8_percent_encode(user_name, NON_ALPHANUMERIC).to_string();
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer, account, secret, issuer, TOTP_DIGITS, TOTP_PERIOD_S
+    )
+}
+
+/// Render the provisioning uri as SVG QR code
+pub fn totp_qr_code_svg(provisioning_uri: &str) -> Result<String, DfrError> {
+    let qr_code = QrCode::new(provisioning_uri.as_bytes())
+        .or_else(|e| DfrError::custom_error(ErrorCode::SYSTEM_ERROR, e.to_string()))?;
+    Ok(qr_code
+        .render::<svg::Color>()
+        .min_dimensions(200, 200)
+        .build())
+}
+
+/// Generate random recovery codes in the form of `xxxxx-xxxxx`
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 5];
+            OsRng.fill_bytes(&mut bytes);
+            let code: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect()
+}
+
+/// SHA-256 hash of a recovery code, case and dashes are ignored
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    format!("{:x}", Sha256::digest(normalized.as_bytes()))
+}
@@ -0,0 +1,181 @@
+use std::{
+    env,
+    fs::{self, OpenOptions, Permissions},
+    io::{ErrorKind, Write},
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use chrono::Local;
+use log::{info, warn};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use crate::utils::error::DfrError;
+
+/// Characters escaped in the `Path` key of trash info files
+const PATH_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+const TRASH_INFO_EXTENSION: &str = "trashinfo";
+
+/// Get the home trash directory, `$XDG_DATA_HOME/Trash`
+fn home_trash_dir() -> Option<PathBuf> {
+    if let Ok(data_home) = env::var("XDG_DATA_HOME") {
+        if !data_home.is_empty() {
+            return Some(PathBuf::from(data_home).join("Trash"));
+        }
+    }
+    let home = env::var("HOME").ok()?;
+    Some(PathBuf::from(home).join(".local/share/Trash"))
+}
+
+/// Get the top directory of the mount point containing path
+fn top_dir(path: &Path) -> Result<PathBuf, DfrError> {
+    let dev = fs::metadata(path)?.dev();
+    let mut top_dir = path.to_path_buf();
+    while let Some(parent) = top_dir.parent() {
+        if fs::metadata(parent)?.dev() != dev {
+            break;
+        }
+        top_dir = parent.to_path_buf();
+    }
+    Ok(top_dir)
+}
+
+/// Get the trash directory for file, the home trash is used if the file is on the same device,
+/// otherwise `$topdir/.Trash/$uid` or `$topdir/.Trash-$uid` is used.
+fn trash_dir_for(file_path: &Path) -> Result<PathBuf, DfrError> {
+    let file_dev = fs::metadata(file_path)?.dev();
+    if let Some(home_trash_dir) = home_trash_dir() {
+        fs::create_dir_all(&home_trash_dir)?;
+        if fs::metadata(&home_trash_dir)?.dev() == file_dev {
+            return Ok(home_trash_dir);
+        }
+    }
+    let uid = unsafe { libc::getuid() };
+    let top_dir = top_dir(file_path)?;
+    // $topdir/.Trash must be a sticky directory and not a symbolic link
+    let shared_trash_dir = top_dir.join(".Trash");
+    if let Ok(metadata) = fs::symlink_metadata(&shared_trash_dir) {
+        if metadata.is_dir() && metadata.mode() & libc::S_ISVTX != 0 {
+            return Ok(shared_trash_dir.join(uid.to_string()));
+        }
+        warn!(
+            "Ignore {:?}, it is not a sticky directory",
+            shared_trash_dir
+        );
+    }
+    Ok(top_dir.join(format!(".Trash-{}", uid)))
+}
+
+/// Create `files` and `info` sub directories of trash directory
+fn create_trash_dir(trash_dir: &Path) -> Result<(), DfrError> {
+    if !trash_dir.exists() {
+        fs::create_dir_all(trash_dir)?;
+        fs::set_permissions(trash_dir, Permissions::from_mode(0o700))?;
+    }
+    fs::create_dir_all(trash_dir.join("files"))?;
+    fs::create_dir_all(trash_dir.join("info"))?;
+    Ok(())
+}
+
+/// Get trash info file path of a file in trash
+pub fn trash_info_path(trash_file_path: &Path) -> Option<PathBuf> {
+    let trash_dir = trash_file_path.parent()?.parent()?;
+    let mut info_file_name = trash_file_path.file_name()?.to_os_string();
+    info_file_name.push(".");
+    info_file_name.push(TRASH_INFO_EXTENSION);
+    Some(trash_dir.join("info").join(info_file_name))
+}
+
+/// Write trash info file, the name is reserved by creating the file exclusively
+fn create_trash_info(info_path: &Path, file_path: &Path) -> std::io::Result<()> {
+    let mut info_file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(info_path)?;
+    let content = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        utf8_percent_encode(file_path.to_string_lossy().as_ref(), PATH_ENCODE_SET),
+        Local::now().format("%Y-%m-%dT%H:%M:%S")
+    );
+    info_file.write_all(content.as_bytes())?;
+    info_file.sync_all()
+}
+
+/// Move file to freedesktop.org trash, return the path of the file in trash.
+/// See https://specifications.freedesktop.org/trash-spec/latest/
+pub fn move_to_xdg_trash(file_path: &Path) -> Result<PathBuf, DfrError> {
+    let trash_dir = trash_dir_for(file_path)?;
+    create_trash_dir(&trash_dir)?;
+    let file_name = file_path
+        .file_name()
+        .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidInput, "File name is empty"))?;
+    let stem = file_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = file_path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let mut index = 1;
+    loop {
+        let trash_file_name = if index == 1 {
+            file_name.to_string_lossy().to_string()
+        } else {
+            format!("{}.{}{}", stem, index, extension)
+        };
+        index += 1;
+        let trash_file_path = trash_dir.join("files").join(trash_file_name.as_str());
+        if trash_file_path.exists() {
+            continue;
+        }
+        let info_path = trash_info_path(&trash_file_path).unwrap();
+        match create_trash_info(&info_path, file_path) {
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+        if let Err(e) = fs::rename(file_path, &trash_file_path) {
+            let _ = fs::remove_file(&info_path);
+            return Err(e.into());
+        }
+        info!("Move file {:?} to trash {:?}", file_path, trash_file_path);
+        return Ok(trash_file_path);
+    }
+}
+
+/// Move file in freedesktop.org trash back to its original path and remove its trash info file
+pub fn restore_from_xdg_trash(trash_file_path: &Path, origin_path: &Path) -> Result<(), DfrError> {
+    if let Err(e) = fs::rename(trash_file_path, origin_path) {
+        warn!(
+            "Failed to rename file: {:?}, try to copy and delete",
+            e
+        );
+        fs::copy(trash_file_path, origin_path)?;
+        fs::remove_file(trash_file_path)?;
+    }
+    remove_trash_info(trash_file_path)
+}
+
+/// Remove file in freedesktop.org trash and its trash info file
+pub fn remove_from_xdg_trash(trash_file_path: &Path) -> Result<(), DfrError> {
+    if trash_file_path.exists() {
+        fs::remove_file(trash_file_path)?;
+    }
+    remove_trash_info(trash_file_path)
+}
+
+fn remove_trash_info(trash_file_path: &Path) -> Result<(), DfrError> {
+    if let Some(info_path) = trash_info_path(trash_file_path) {
+        if info_path.exists() {
+            fs::remove_file(info_path)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use std::{env, fs, path::PathBuf};
+
+use chrono::Local;
+use dup_file_remover::{
+    database::{file_info::FileInfo, sqlite::PoolDatabaseManager},
+    utils::error::DfrError,
+};
+
+/// Database in a new temporary directory, files are written to the directory and their file
+/// info is inserted without a scan
+pub struct TestFileDb {
+    pub test_path: PathBuf,
+    pub db: PoolDatabaseManager,
+}
+
+impl TestFileDb {
+    pub fn new(test_name: &str) -> Result<Self, DfrError> {
+        let test_path = env::temp_dir().join(test_name);
+        let _ = fs::remove_dir_all(&test_path);
+        fs::create_dir_all(&test_path)?;
+        let db = PoolDatabaseManager::new(test_path.join("db.sqlite").to_str().unwrap())?;
+        db.create_tables()?;
+        Ok(TestFileDb { test_path, db })
+    }
+
+    /// Write a file relative to the test path and insert its file info, the content is used
+    /// as md5 so that files with the same content are duplicates
+    pub fn insert_file(&self, file_path: &str, content: &str) -> Result<(), DfrError> {
+        let file_path = self.test_path.join(file_path);
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&file_path, content)?;
+        let mut file_info = FileInfo::new(file_path.to_str().unwrap(), 1, Local::now())?;
+        file_info.inode_info.md5 = Some(content.to_string());
+        self.db.insert_file_info(&file_info)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use std::{env, fs};
+
+use actix_web::{http::StatusCode, middleware::from_fn, test, web, App};
+use chrono::Local;
+use dup_file_remover::{
+    controller::{stats::query_file_stats, user::reject_anonymous_users},
+    database::{
+        api_token::{ApiToken, TokenScope},
+        sqlite::PoolDatabaseManager,
+        user_info::{UserInfo, UserRole},
+    },
+    model::settings::Settings,
+    utils::{error::DfrError, token::hash_api_token},
+    SharedSettings,
+};
+
+#[actix_web::test]
+async fn test_api_token_read_scope() -> Result<(), DfrError> {
+    let test_path = env::temp_dir().join("dfr_test_api_token");
+    let _ = fs::remove_dir_all(&test_path);
+    fs::create_dir_all(&test_path)?;
+    let db = PoolDatabaseManager::new(test_path.join("db.sqlite").to_str().unwrap())?;
+    db.create_tables()?;
+    let now = Local::now();
+    db.insert_user(&UserInfo {
+        user_name: String::from("viewer"),
+        password_hash: String::new(),
+        role: UserRole::Viewer,
+        allowed_paths: vec![],
+        created_time: now,
+        modified_time: now,
+    })?;
+    for (token, scopes) in [
+        ("dfr_read", vec![TokenScope::Read]),
+        ("dfr_none", vec![]),
+        ("dfr_scan", vec![TokenScope::Scan]),
+    ] {
+        db.insert_api_token(&ApiToken {
+            id: 0,
+            user_name: String::from("viewer"),
+            name: token.to_string(),
+            token_hash: hash_api_token(token),
+            scopes,
+            created_time: now,
+            expire_time: None,
+            last_used_time: None,
+            revoked: false,
+        })?;
+    }
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(SharedSettings::from(Settings::default())))
+            .service(
+                web::scope("/api/dfr")
+                    .wrap(from_fn(reject_anonymous_users))
+                    .service(query_file_stats),
+            ),
+    )
+    .await;
+    // only tokens with read scope can read, other scopes do not include it
+    for (token, status) in [
+        ("dfr_read", StatusCode::OK),
+        ("dfr_none", StatusCode::FORBIDDEN),
+        ("dfr_scan", StatusCode::FORBIDDEN),
+        ("dfr_unknown", StatusCode::UNAUTHORIZED),
+    ] {
+        let req = test::TestRequest::get()
+            .uri("/api/dfr/stats")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let resp_status = match test::try_call_service(&app, req).await {
+            Ok(resp) => resp.status(),
+            Err(e) => e.as_response_error().status_code(),
+        };
+        assert_eq!(resp_status, status, "token {}", token);
+    }
+
+    fs::remove_dir_all(&test_path)?;
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use std::{env, fs, io::Write, path::PathBuf};
+
+use dup_file_remover::{
+    controller::{files::_delete_file, scan::scan_all_files},
+    database::sqlite::PoolDatabaseManager,
+    model::{
+        files::DeleteFileRequest,
+        scan::SharedScanStatus,
+        settings::{ListSettings, ScanSettings, Settings},
+    },
+    utils::{
+        archive::{is_archive_file, read_archive_members},
+        error::DfrError,
+    },
+    SharedSettings,
+};
+use flate2::{write::GzEncoder, Compression};
+
+#[tokio::test]
+async fn test_archive_members() -> Result<(), DfrError> {
+    let test_path = env::temp_dir().join("dfr_test_archive");
+    let _ = fs::remove_dir_all(&test_path);
+    let data_path = test_path.join("data");
+    fs::create_dir_all(&data_path)?;
+    assert!(is_archive_file("backup.TAR.GZ"));
+    assert!(!is_archive_file("backup.gz"));
+
+    // a photo, a zip with a copy of it and a tar.gz with another copy
+    fs::write(data_path.join("photo.jpg"), "photo content")?;
+    let mut zip_writer = zip::ZipWriter::new(fs::File::create(data_path.join("backup.zip"))?);
+    let options = zip::write::SimpleFileOptions::default();
+    zip_writer.add_directory("photos", options).unwrap();
+    zip_writer.start_file("photos/photo.jpg", options).unwrap();
+    zip_writer.write_all(b"photo content")?;
+    zip_writer.start_file("../notes.txt", options).unwrap();
+    zip_writer.write_all(b"notes")?;
+    zip_writer.finish().unwrap();
+    let mut tar_builder = tar::Builder::new(GzEncoder::new(
+        fs::File::create(data_path.join("old.tar.gz"))?,
+        Compression::default(),
+    ));
+    let mut header = tar::Header::new_gnu();
+    header.set_size(13);
+    header.set_mode(0o644);
+    header.set_mtime(1700000000);
+    tar_builder.append_data(&mut header, "photo.jpg", "photo content".as_bytes())?;
+    tar_builder.into_inner()?.finish()?;
+
+    let members = read_archive_members(data_path.join("backup.zip").to_str().unwrap())?;
+    let paths = members
+        .iter()
+        .map(|member| member.path.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(paths, vec!["photos/photo.jpg", "notes.txt"]);
+    assert_eq!(members[0].size, 13);
+    let members = read_archive_members(data_path.join("old.tar.gz").to_str().unwrap())?;
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0].modified.unwrap().timestamp(), 1700000000);
+
+    let db = PoolDatabaseManager::new(test_path.join("db.sqlite").to_str().unwrap())?;
+    db.create_tables()?;
+    let scan_request = ScanSettings {
+        scan_path: data_path.to_string_lossy().to_string(),
+        archive_members: Some(true),
+        ..Default::default()
+    };
+    // the members are kept by a scan of the unchanged archives
+    for _ in 0..2 {
+        scan_all_files(
+            &scan_request,
+            &db,
+            &SharedScanStatus::new(),
+            test_path.join("trash"),
+        )
+        .await?;
+        let file_info_list = db.list_files(&ListSettings::default(), &[])?;
+        assert_eq!(file_info_list.total_count, 3);
+        assert_eq!(file_info_list.file_info_list[0].md5_count, 3);
+    }
+    let data_path = fs::canonicalize(&data_path)?;
+    let zip_path = data_path.join("backup.zip").to_string_lossy().to_string();
+    let member = db.get_file_by_path(&format!("{}!/photos", zip_path), "photo.jpg")?;
+    assert_eq!(member.file_path, format!("{}!/photos/photo.jpg", zip_path));
+    assert_eq!(member.archive_path, Some(zip_path.clone()));
+    assert_eq!(member.inode_info.dev_id, 0);
+
+    // members are never deleted, even if forced
+    let settings = SharedSettings::from(Settings::default());
+    let delete_file_request = DeleteFileRequest {
+        dir_path: member.dir_path.clone(),
+        file_name: member.file_name.clone(),
+        delete_permanently: Some(true),
+        force_delete: Some(true),
+    };
+    match _delete_file(delete_file_request, &db, &settings).await {
+        Err(DfrError::CustomError(e)) => {
+            assert!(e.message.contains("is a member of archive"))
+        }
+        _ => panic!("archive member must not be deleted"),
+    }
+    assert!(PathBuf::from(&zip_path).exists());
+
+    // the photo has copies in the archives, so it can be deleted
+    let delete_file_request = DeleteFileRequest {
+        dir_path: data_path.to_string_lossy().to_string(),
+        file_name: String::from("photo.jpg"),
+        delete_permanently: Some(true),
+        force_delete: Some(false),
+    };
+    _delete_file(delete_file_request, &db, &settings).await?;
+    assert!(!data_path.join("photo.jpg").exists());
+
+    // members are removed with their archive
+    db.remove_file_by_path(&data_path.to_string_lossy(), "backup.zip")?;
+    assert!(db
+        .get_file_by_path(&format!("{}!/photos", zip_path), "photo.jpg")
+        .is_err());
+    let file_info_list = db.list_files(&ListSettings::default(), &[])?;
+    assert_eq!(file_info_list.total_count, 0);
+
+    fs::remove_dir_all(&test_path)?;
+    Ok(())
+}
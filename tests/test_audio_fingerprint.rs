@@ -0,0 +1,129 @@
+use std::{env, fs, path::Path};
+
+use dup_file_remover::{
+    database::audio_fingerprint::AudioFingerprintFile,
+    utils::{
+        audio_fingerprint::{
+            compute_audio_fingerprint, find_similar_audio, fingerprint_similarity, is_audio_file,
+        },
+        error::DfrError,
+    },
+};
+
+/// Write a 16 bit PCM wav file of a melody, one note per half second
+fn write_melody(
+    file_path: &Path,
+    notes: &[f64],
+    sample_rate: u32,
+    channels: u16,
+    volume: f64,
+    leading_silence_s: f64,
+) -> Result<u64, DfrError> {
+    let mut samples = vec![0i16; (leading_silence_s * sample_rate as f64) as usize];
+    let note_samples = sample_rate as usize / 2;
+    for (index, note) in notes.iter().enumerate() {
+        let frequency = 440.0 * 2f64.powf((note - 69.0) / 12.0);
+        for sample in 0..note_samples {
+            let time = (index * note_samples + sample) as f64 / sample_rate as f64;
+            let value = (2.0 * std::f64::consts::PI * frequency * time).sin()
+                + 0.5 * (4.0 * std::f64::consts::PI * frequency * time).sin();
+            samples.push((value * volume * 16000.0) as i16);
+        }
+    }
+    let data_size = (samples.len() * 2 * channels as usize) as u32;
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_size).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * 2 * channels as u32).to_le_bytes());
+    bytes.extend_from_slice(&(2 * channels).to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_size.to_le_bytes());
+    for sample in samples {
+        for _ in 0..channels {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+    }
+    fs::write(file_path, &bytes)?;
+    Ok(bytes.len() as u64)
+}
+
+#[test]
+fn test_similar_audio() -> Result<(), DfrError> {
+    let test_path = env::temp_dir().join("dfr_test_audio_fingerprint");
+    let _ = fs::remove_dir_all(&test_path);
+    fs::create_dir_all(&test_path)?;
+    assert!(is_audio_file("Song.FLAC"));
+    assert!(!is_audio_file("cover.jpg"));
+
+    let melody = (0..40)
+        .map(|index| [60.0, 64.0, 67.0, 72.0, 69.0, 65.0][index % 6])
+        .collect::<Vec<_>>();
+    let other_melody = (0..40)
+        .map(|index| [62.0, 61.0, 70.0, 66.0, 63.0][index % 5])
+        .collect::<Vec<_>>();
+    // the original, a quieter mono copy with a lower sample rate and silence at the start, and
+    // another song of the same length
+    let tracks = [
+        (
+            "original.wav",
+            write_melody(&test_path.join("original.wav"), &melody, 44100, 2, 1.0, 0.0)?,
+        ),
+        (
+            "copy.wav",
+            write_melody(&test_path.join("copy.wav"), &melody, 22050, 1, 0.4, 0.5)?,
+        ),
+        (
+            "other.wav",
+            write_melody(
+                &test_path.join("other.wav"),
+                &other_melody,
+                44100,
+                2,
+                1.0,
+                0.0,
+            )?,
+        ),
+    ];
+    let mut files = Vec::new();
+    for (file_name, size) in tracks {
+        let file_path = test_path.join(file_name).to_string_lossy().to_string();
+        files.push(AudioFingerprintFile {
+            audio_fingerprint: compute_audio_fingerprint(&file_path, file_name, size)?,
+            dir_path: test_path.to_string_lossy().to_string(),
+            file_name: file_name.to_string(),
+            size,
+        });
+    }
+    let original = &files[0].audio_fingerprint;
+    assert_eq!(original.codec, "pcm_s16le");
+    assert_eq!(original.sample_rate, 44100);
+    assert_eq!(original.channels, 2);
+    assert_eq!(original.bit_depth, Some(16));
+    assert!((original.duration_s - 20.0).abs() < 0.01);
+    let copy = &files[1].audio_fingerprint;
+    let other = &files[2].audio_fingerprint;
+    assert!(fingerprint_similarity(&original.fingerprint, &copy.fingerprint) > 0.9);
+    assert!(fingerprint_similarity(&original.fingerprint, &other.fingerprint) < 0.8);
+
+    // the stereo original with the higher bitrate is the suggested keeper
+    let groups = find_similar_audio(&files, 0.85);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].keeper_md5, "original.wav");
+    assert_eq!(groups[0].tracks.len(), 2);
+    assert!(groups[0].tracks[0].lossless);
+    assert_eq!(groups[0].tracks[1].md5, "copy.wav");
+
+    // files which are not audio are not decoded
+    let text_path = test_path.join("fake.mp3");
+    fs::write(&text_path, "not audio")?;
+    assert!(compute_audio_fingerprint(text_path.to_str().unwrap(), "fake", 9).is_err());
+
+    fs::remove_dir_all(&test_path)?;
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use chrono::{Local, TimeDelta};
+use clap::Parser;
+use dup_file_remover::{
+    database::file_info::{FileInfo, InodeInfo},
+    model::{
+        cli::{Command, DedupePolicy, TrashCommand},
+        settings::Args,
+    },
+};
+
+fn file_info(file_path: &str, modified_days_ago: i64) -> FileInfo {
+    let (dir_path, file_name) = file_path.rsplit_once('/').unwrap();
+    let now = Local::now();
+    FileInfo {
+        inode_info: InodeInfo {
+            inode: 0,
+            dev_id: 0,
+            permissions: 0o644,
+            nlink: 1,
+            uid: 0,
+            gid: 0,
+            created: now,
+            modified: now - TimeDelta::days(modified_days_ago),
+            md5: Some(String::from("b1946ac92492d2347c6235b4d2611184")),
+            payload_md5: None,
+            size: 6,
+        },
+        dir_path: dir_path.to_string(),
+        file_path: file_path.to_string(),
+        file_name: file_name.to_string(),
+        file_extension: None,
+        version: 0,
+        scan_time: now,
+        archive_path: None,
+    }
+}
+
+#[test]
+fn test_dedupe_policy() {
+    let files = vec![
+        file_info("/data/photos/2024/a.jpg", 10),
+        file_info("/data/b.jpg", 5),
+        file_info("/data/photos/c.jpg", 20),
+        file_info("/data/a.jpg", 5),
+    ];
+    assert_eq!(
+        DedupePolicy::KeepOldest.select_file_to_keep(&files),
+        Some(2)
+    );
+    // ties are broken by the path
+    assert_eq!(
+        DedupePolicy::KeepNewest.select_file_to_keep(&files),
+        Some(3)
+    );
+    assert_eq!(
+        DedupePolicy::KeepShortestPath.select_file_to_keep(&files),
+        Some(3)
+    );
+    assert_eq!(
+        DedupePolicy::KeepLongestPath.select_file_to_keep(&files),
+        Some(0)
+    );
+    assert_eq!(DedupePolicy::KeepOldest.select_file_to_keep(&[]), None);
+}
+
+#[test]
+fn test_parse_subcommands() {
+    let args = Args::try_parse_from(["dfr"]).unwrap();
+    assert!(args.command.is_none());
+    let args =
+        Args::try_parse_from(["dfr", "dedupe", "--policy", "keep-oldest", "--json"]).unwrap();
+    assert!(args.json);
+    assert!(matches!(
+        args.command,
+        Some(Command::Dedupe(dedupe_args)) if dedupe_args.policy == DedupePolicy::KeepOldest
+    ));
+    // purging all trash files must be explicit
+    assert!(Args::try_parse_from(["dfr", "trash", "purge"]).is_err());
+    let args = Args::try_parse_from(["dfr", "trash", "purge", "--older-than-days", "30"]).unwrap();
+    assert!(matches!(
+        args.command,
+        Some(Command::Trash(TrashCommand::Purge {
+            older_than_days: Some(30),
+            all: false
+        }))
+    ));
+}
@@ -0,0 +1,31 @@
+use std::{env, fs};
+
+use md5::{Digest, Md5};
+
+use dup_file_remover::utils::{
+    compress::{compress_file, decompress_file, decompressed_md5},
+    error::DfrError,
+};
+
+#[test]
+fn test_compress_round_trip() -> Result<(), DfrError> {
+    let dir = env::temp_dir().join("dfr_test_compress");
+    fs::create_dir_all(&dir)?;
+    let src = dir.join("origin.txt");
+    let compressed = dir.join("origin.txt.zst");
+    let restored = dir.join("restored.txt");
+    let content = "duplicate file remover\n".repeat(1000);
+    fs::write(&src, content.as_bytes())?;
+
+    let compressed_size = compress_file(&src, &compressed, 3)?;
+    assert!(compressed_size < content.len() as u64);
+    assert_eq!(
+        decompressed_md5(&compressed)?,
+        format!("{:x}", Md5::digest(content.as_bytes()))
+    );
+    assert_eq!(decompress_file(&compressed, &restored)?, content.len() as u64);
+    assert_eq!(fs::read_to_string(&restored)?, content);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
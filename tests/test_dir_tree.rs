@@ -0,0 +1,54 @@
+mod commons;
+
+use std::fs;
+
+use commons::TestFileDb;
+use dup_file_remover::utils::error::DfrError;
+
+#[test]
+fn test_dir_tree() -> Result<(), DfrError> {
+    let test_file_db = TestFileDb::new("dfr_test_dir_tree")?;
+    let (test_path, db) = (&test_file_db.test_path, &test_file_db.db);
+    let insert_file = |file_path, content| test_file_db.insert_file(file_path, content);
+    insert_file("photos/a.jpg", "aaaa")?;
+    insert_file("photos/2019/b.jpg", "bbbbbb")?;
+    insert_file("photos/2019/c.jpg", "cc")?;
+    insert_file("photos-old/b.jpg", "bbbbbb")?;
+    insert_file("docs/a.txt", "aaaa")?;
+    insert_file("top.txt", "t")?;
+
+    let dir_path = test_path.to_string_lossy().to_string();
+    let dir_tree = db.get_dir_tree(&dir_path, &[])?;
+    assert_eq!(dir_tree.total.file_count, 6);
+    assert_eq!(dir_tree.total.size, 23);
+    assert_eq!(dir_tree.total.duplicate_file_count, 4);
+    assert_eq!(dir_tree.total.duplicate_size, 20);
+    assert_eq!(dir_tree.files.file_count, 1);
+    assert_eq!(dir_tree.files.duplicate_file_count, 0);
+    // subdirectories with the most duplicate bytes first, photos-old is not part of photos
+    let names = dir_tree
+        .sub_dirs
+        .iter()
+        .map(|e| e.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["photos", "photos-old", "docs"]);
+    assert_eq!(dir_tree.sub_dirs[0].file_count, 3);
+    assert_eq!(dir_tree.sub_dirs[0].duplicate_size, 10);
+
+    // drill down to a subdirectory
+    let dir_tree = db.get_dir_tree(&dir_tree.sub_dirs[0].dir_path, &[])?;
+    assert_eq!(dir_tree.parent_dir_path, Some(dir_path.clone()));
+    assert_eq!(dir_tree.files.size, 4);
+    assert_eq!(dir_tree.sub_dirs.len(), 1);
+    assert_eq!(dir_tree.sub_dirs[0].name, "2019");
+    assert_eq!(dir_tree.sub_dirs[0].duplicate_file_count, 1);
+
+    // only files and duplicates under the allowed paths are counted
+    let allowed_paths = vec![test_path.join("photos").to_string_lossy().to_string()];
+    let dir_tree = db.get_dir_tree(&dir_path, &allowed_paths)?;
+    assert_eq!(dir_tree.total.file_count, 3);
+    assert_eq!(dir_tree.total.duplicate_file_count, 0);
+
+    fs::remove_dir_all(test_path)?;
+    Ok(())
+}
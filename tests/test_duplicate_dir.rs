@@ -0,0 +1,48 @@
+use dup_file_remover::{database::file_info::FileHash, utils::duplicate_dir::find_duplicate_dirs};
+
+fn file_hash(dir_path: &str, file_name: &str, md5: &str) -> FileHash {
+    FileHash {
+        dir_path: dir_path.to_string(),
+        file_name: file_name.to_string(),
+        md5: md5.to_string(),
+        size: 100,
+    }
+}
+
+#[test]
+fn test_find_duplicate_dirs() {
+    let mut files = vec![
+        file_hash("/data/Photos/2019", "a.jpg", "aaaa"),
+        file_hash("/data/Photos/2019/summer", "b.jpg", "bbbb"),
+        file_hash("/data/Backup/Photos/2019", "a.jpg", "aaaa"),
+        file_hash("/data/Backup/Photos/2019/summer", "b.jpg", "bbbb"),
+        file_hash("/data/Docs", "c.txt", "cccc"),
+    ];
+
+    // only the top pair of identical trees is reported, not their subdirectories
+    let duplicate_dirs = find_duplicate_dirs(&files, 80.0);
+    assert_eq!(duplicate_dirs.len(), 1);
+    assert_eq!(duplicate_dirs[0].dir_path, "/data/Backup/Photos");
+    assert_eq!(duplicate_dirs[0].other_dir_path, "/data/Photos");
+    assert!(duplicate_dirs[0].identical);
+    assert_eq!(duplicate_dirs[0].shared_file_count, 2);
+    assert_eq!(duplicate_dirs[0].shared_size, 200);
+
+    // a near-identical tree is reported as long as the overlap reaches the threshold
+    for file_name in ["d.jpg", "e.jpg", "f.jpg"] {
+        files.push(file_hash("/data/Photos/2019", file_name, file_name));
+        files.push(file_hash("/data/Backup/Photos/2019", file_name, file_name));
+    }
+    files.push(file_hash("/data/Photos/2019", "g.jpg", "gggg"));
+    let duplicate_dirs = find_duplicate_dirs(&files, 80.0);
+    assert_eq!(duplicate_dirs.len(), 1);
+    assert!(!duplicate_dirs[0].identical);
+    assert_eq!(duplicate_dirs[0].shared_file_count, 5);
+    assert_eq!(duplicate_dirs[0].overlap_percent, 5.0 * 100.0 / 6.0);
+
+    // below the threshold only the identical subdirectories are left
+    let duplicate_dirs = find_duplicate_dirs(&files, 90.0);
+    assert_eq!(duplicate_dirs.len(), 1);
+    assert_eq!(duplicate_dirs[0].dir_path, "/data/Backup/Photos/2019/summer");
+    assert!(duplicate_dirs[0].identical);
+}
@@ -0,0 +1,119 @@
+use std::{env, fs};
+
+use chrono::Local;
+use dup_file_remover::{
+    controller::export::DuplicateExporter,
+    database::{file_info::FileInfo, sqlite::PoolDatabaseManager},
+    model::{
+        export::{DuplicateGroup, ExportFormat},
+        settings::{FileHashType, ListSettings},
+    },
+    utils::error::DfrError,
+};
+
+fn export(
+    db: &PoolDatabaseManager,
+    query_list_params: ListSettings,
+    format: ExportFormat,
+) -> Result<String, DfrError> {
+    let mut exporter = DuplicateExporter::new(db.clone(), query_list_params, Vec::new(), format);
+    let mut data = Vec::new();
+    while let Some(chunk) = exporter.next_chunk()? {
+        data.extend(chunk);
+    }
+    Ok(String::from_utf8(data).unwrap())
+}
+
+#[test]
+fn test_export_duplicate_files() -> Result<(), DfrError> {
+    let test_path = env::temp_dir().join("dfr_test_export");
+    let _ = fs::remove_dir_all(&test_path);
+    fs::create_dir_all(&test_path)?;
+    let db = PoolDatabaseManager::new(test_path.join("db.sqlite").to_str().unwrap())?;
+    db.create_tables()?;
+    // three copies of a, two copies of b and a unique c
+    for (file_name, content) in [
+        ("a1.txt", "aaaa"),
+        ("b1.txt", "bb"),
+        ("a2.txt", "aaaa"),
+        ("c1.txt", "c"),
+        ("b2.txt", "bb"),
+        ("a3.txt", "aaaa"),
+    ] {
+        let file_path = test_path.join(file_name);
+        fs::write(&file_path, content)?;
+        let mut file_info = FileInfo::new(file_path.to_str().unwrap(), 1, Local::now())?;
+        file_info.inode_info.md5 = Some(content.to_string());
+        db.insert_file_info(&file_info)?;
+    }
+
+    let ndjson = export(&db, ListSettings::default(), ExportFormat::Ndjson)?;
+    let groups = ndjson
+        .lines()
+        .map(serde_json::from_str::<DuplicateGroup>)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].md5, "aaaa");
+    assert_eq!(groups[0].md5_count, 3);
+    assert_eq!(groups[0].files.len(), 3);
+    assert_eq!(groups[0].reclaimable_size, 8);
+    assert_eq!(groups[1].md5, "bb");
+    assert_eq!(groups[1].reclaimable_size, 2);
+
+    let json = export(&db, ListSettings::default(), ExportFormat::Json)?;
+    let json_groups: Vec<DuplicateGroup> = serde_json::from_str(json.as_str())?;
+    assert_eq!(json_groups.len(), 2);
+
+    // filters are applied, and the header is written once
+    let query_list_params = ListSettings {
+        file_name: Some(String::from("b")),
+        ..Default::default()
+    };
+    let csv = export(&db, query_list_params, ExportFormat::Csv)?;
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("md5,size,md5_count,reclaimable_size,file_path"));
+    assert!(lines[1].starts_with("bb,2,2,2,"));
+
+    let empty = export(
+        &db,
+        ListSettings {
+            min_md5_count: Some(4),
+            ..Default::default()
+        },
+        ExportFormat::Json,
+    )?;
+    assert_eq!(empty.trim(), "[\n]");
+
+    // files with the same payload may differ in size, ordering by size must not split the group
+    for (file_name, content, payload_md5) in [
+        ("p1.jpg", "p".repeat(30), "pppp"),
+        ("q1.jpg", "q".repeat(20), "qqqq"),
+        ("q2.jpg", "q".repeat(20), "qqqq"),
+        ("p2.jpg", "p".repeat(10), "pppp"),
+    ] {
+        let file_path = test_path.join(file_name);
+        fs::write(&file_path, content.as_str())?;
+        let mut file_info = FileInfo::new(file_path.to_str().unwrap(), 1, Local::now())?;
+        file_info.inode_info.md5 = Some(content.clone());
+        file_info.inode_info.payload_md5 = Some(payload_md5.to_string());
+        db.insert_file_info(&file_info)?;
+    }
+    let query_list_params = ListSettings {
+        group_by: Some(FileHashType::Payload),
+        order_by: Some(String::from("size")),
+        ..Default::default()
+    };
+    let ndjson = export(&db, query_list_params, ExportFormat::Ndjson)?;
+    let groups = ndjson
+        .lines()
+        .map(serde_json::from_str::<DuplicateGroup>)
+        .collect::<Result<Vec<_>, _>>()?;
+    // files without payload md5 are grouped by md5
+    assert_eq!(groups.len(), 4);
+    assert_eq!(groups[2].md5, "pppp");
+    assert_eq!(groups[2].files.len(), 2);
+
+    fs::remove_dir_all(&test_path)?;
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use std::{env, fs};
+
+use dup_file_remover::{
+    database::image_hash::ImageHashFile,
+    model::image_hash::ImageHashAlgorithm,
+    utils::{
+        error::DfrError,
+        image_hash::{compute_image_hash, find_similar_images, hamming_distance, is_image_file},
+    },
+};
+use image::{imageops::FilterType, DynamicImage, RgbImage};
+
+#[test]
+fn test_similar_images() -> Result<(), DfrError> {
+    let test_path = env::temp_dir().join("dfr_test_image_hash");
+    let _ = fs::remove_dir_all(&test_path);
+    fs::create_dir_all(&test_path)?;
+    assert!(is_image_file("IMG_0001.JPG"));
+    assert!(!is_image_file("notes.txt"));
+
+    // a photo, a resized copy of it and an unrelated picture
+    let photo = DynamicImage::ImageRgb8(RgbImage::from_fn(256, 192, |x, y| {
+        let value = ((x * 255 / 256) as u8) ^ ((y / 48 * 60) as u8);
+        image::Rgb([value, (y * 255 / 192) as u8, 128])
+    }));
+    let other = DynamicImage::ImageRgb8(RgbImage::from_fn(256, 192, |x, y| {
+        let value = if (x / 32 + y / 32) % 2 == 0 { 230 } else { 20 };
+        image::Rgb([value, value, value])
+    }));
+    let save = |name: &str, image: &DynamicImage| -> Result<String, DfrError> {
+        let file_path = test_path.join(name).to_string_lossy().to_string();
+        image.save(&file_path).expect("image is saved");
+        Ok(file_path)
+    };
+    let files = [
+        (save("photo.png", &photo)?, "photo", 256 * 192 * 3),
+        (
+            save(
+                "photo_small.png",
+                &photo.resize(128, 96, FilterType::Lanczos3),
+            )?,
+            "photo_small",
+            128 * 96 * 3,
+        ),
+        (save("other.png", &other)?, "other", 256 * 192 * 3),
+    ];
+    let mut image_hash_files = Vec::new();
+    for (file_path, md5, size) in files.iter() {
+        let (dir_path, file_name) = file_path.rsplit_once('/').unwrap();
+        image_hash_files.push(ImageHashFile {
+            image_hash: compute_image_hash(file_path, md5)?,
+            dir_path: dir_path.to_string(),
+            file_name: file_name.to_string(),
+            size: *size,
+        });
+    }
+    let hashes = image_hash_files
+        .iter()
+        .map(|file| file.image_hash.phash)
+        .collect::<Vec<_>>();
+    assert!(hamming_distance(hashes[0], hashes[1]) < 8);
+    assert!(hamming_distance(hashes[0], hashes[2]) > 16);
+    assert_eq!(image_hash_files[1].image_hash.width, 128);
+
+    let groups = find_similar_images(&image_hash_files, 8, ImageHashAlgorithm::Phash);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].images.len(), 2);
+    assert_eq!(groups[0].images[0].md5, "photo");
+    assert_eq!(groups[0].images[1].md5, "photo_small");
+    assert_eq!(groups[0].total_size, 256 * 192 * 3 + 128 * 96 * 3);
+    let groups = find_similar_images(&image_hash_files, 0, ImageHashAlgorithm::Dhash);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].images[1].distance, 0);
+
+    // files which are not images are not decoded
+    let text_path = test_path.join("fake.jpg");
+    fs::write(&text_path, "not an image")?;
+    assert!(compute_image_hash(text_path.to_str().unwrap(), "fake").is_err());
+
+    fs::remove_dir_all(&test_path)?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use chrono::{Local, TimeDelta};
+
+use dup_file_remover::model::{
+    login::{LockedLoginKind, LoginLimiter},
+    settings::SystemSettings,
+};
+
+#[test]
+fn test_login_limiter() {
+    let settings = SystemSettings {
+        login_max_failed_attempts: 3,
+        login_max_failed_attempts_per_ip: 10,
+        login_backoff_base_s: 1,
+        login_lockout_s: 60,
+        ..Default::default()
+    };
+    let mut limiter = LoginLimiter::default();
+    let mut now = Local::now();
+    assert!(limiter.retry_after("10.0.0.1", "admin", &now).is_none());
+
+    // exponential backoff before lockout
+    assert!(!limiter.record_failure("10.0.0.1", "admin", &now, &settings));
+    assert_eq!(
+        limiter.retry_after("10.0.0.1", "admin", &now),
+        Some(TimeDelta::seconds(1))
+    );
+    now += TimeDelta::seconds(1);
+    assert!(limiter.retry_after("10.0.0.1", "admin", &now).is_none());
+    assert!(!limiter.record_failure("10.0.0.1", "admin", &now, &settings));
+    assert_eq!(
+        limiter.retry_after("10.0.0.2", "admin", &now),
+        Some(TimeDelta::seconds(2))
+    );
+    assert!(limiter.retry_after("10.0.0.2", "guest", &now).is_none());
+
+    // user is locked out, other ips are rejected as well
+    now += TimeDelta::seconds(2);
+    assert!(limiter.record_failure("10.0.0.1", "admin", &now, &settings));
+    assert_eq!(
+        limiter.retry_after("10.0.0.2", "admin", &now),
+        Some(TimeDelta::seconds(60))
+    );
+    let locked_list = limiter.list_locked(&now, &settings);
+    assert_eq!(locked_list.total_count, 1);
+    assert_eq!(locked_list.locked_login_list[0].kind, LockedLoginKind::User);
+    assert_eq!(locked_list.locked_login_list[0].name, "admin");
+
+    // lockout expires
+    now += TimeDelta::seconds(60);
+    assert!(limiter.retry_after("10.0.0.1", "admin", &now).is_none());
+    assert_eq!(limiter.list_locked(&now, &settings).total_count, 0);
+
+    // success forgets failed attempts
+    limiter.record_success("10.0.0.1", "admin");
+    assert!(!limiter.record_failure("10.0.0.1", "admin", &now, &settings));
+    assert_eq!(
+        limiter.retry_after("10.0.0.1", "admin", &now),
+        Some(TimeDelta::seconds(1))
+    );
+}
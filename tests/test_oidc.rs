@@ -0,0 +1,259 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+
+use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+use actix_web::{
+    cookie::{Cookie, Key},
+    http::{header, StatusCode},
+    test::{call_service, init_service, read_body, TestRequest},
+    web, App, HttpResponse, HttpServer,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Local;
+use dup_file_remover::{
+    controller::oidc::{login_oidc, login_oidc_callback},
+    database::{
+        sqlite::PoolDatabaseManager,
+        user_info::{UserInfo, UserRole},
+    },
+    model::{
+        login::SharedLoginLimiter,
+        settings::{OidcSettings, Settings},
+    },
+    utils::{
+        error::DfrError,
+        oidc::{decode_jwt_claims, map_oidc_claims},
+        password::hash_password,
+    },
+    SharedSettings,
+};
+use hmac::{Hmac, Mac};
+use openidconnect::url::Url;
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+const CLIENT_ID: &str = "dfr";
+const CLIENT_SECRET: &str = "mock client secret";
+
+#[test]
+fn test_map_oidc_claims() {
+    let claims = json!({
+        "sub": "248289761001",
+        "preferred_username": "alice",
+        "groups": ["staff", "dfr-operators"],
+        "amr": ["pwd", "otp"],
+    });
+    let token = format!(
+        "eyJhbGciOiJSUzI1NiJ9.{}.c2lnbmF0dXJl",
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+    assert_eq!(decode_jwt_claims(&token).unwrap(), claims);
+    assert!(decode_jwt_claims("invalid").is_err());
+
+    // without role mapping the role is managed locally
+    let mut settings = OidcSettings::default();
+    let oidc_user = map_oidc_claims(&claims, &settings).unwrap();
+    assert_eq!(oidc_user.user_name, "alice");
+    assert_eq!(oidc_user.role, None);
+    assert!(oidc_user.mfa);
+
+    settings.role_mapping = HashMap::from([
+        (String::from("dfr-operators"), UserRole::Operator),
+        (String::from("dfr-admins"), UserRole::Admin),
+    ]);
+    let oidc_user = map_oidc_claims(&claims, &settings).unwrap();
+    assert_eq!(oidc_user.role, Some(UserRole::Operator));
+
+    // a single group as string, the highest role wins
+    let claims = json!({
+        "email": "bob@example.com",
+        "groups": "dfr-admins",
+    });
+    settings.username_claim = String::from("email");
+    let oidc_user = map_oidc_claims(&claims, &settings).unwrap();
+    assert_eq!(oidc_user.user_name, "bob@example.com");
+    assert_eq!(oidc_user.role, Some(UserRole::Admin));
+    assert!(!oidc_user.mfa);
+
+    let claims = json!({ "email": "carol@example.com" });
+    let oidc_user = map_oidc_claims(&claims, &settings).unwrap();
+    assert_eq!(oidc_user.role, Some(UserRole::Viewer));
+
+    let claims = json!({ "sub": "248289761001" });
+    assert!(map_oidc_claims(&claims, &settings).is_err());
+}
+
+/// HS256 id token signed with the client secret, as the mock provider has no keys
+fn sign_id_token(claims: &Value) -> String {
+    let header = URL_SAFE_NO_PAD.encode(json!({ "alg": "HS256", "typ": "JWT" }).to_string());
+    let payload = URL_SAFE_NO_PAD.encode(claims.to_string());
+    let mut mac = Hmac::<Sha256>::new_from_slice(CLIENT_SECRET.as_bytes()).unwrap();
+    mac.update(format!("{}.{}", header, payload).as_bytes());
+    let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+    format!("{}.{}.{}", header, payload, signature)
+}
+
+/// Start a mock identity provider, whose token endpoint returns an id token with the user claims
+/// and the nonce passed as authorization code. Return the issuer url.
+fn start_mock_provider(user_claims: Arc<Mutex<Value>>) -> std::io::Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let issuer = format!("http://{}", listener.local_addr()?);
+    let issuer_data = issuer.clone();
+    let server = HttpServer::new(move || {
+        let issuer = issuer_data.clone();
+        let user_claims = user_claims.clone();
+        let metadata = json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{}/authorize", issuer),
+            "token_endpoint": format!("{}/token", issuer),
+            "jwks_uri": format!("{}/jwks", issuer),
+            "response_types_supported": ["code"],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": ["HS256"],
+        });
+        App::new()
+            .route(
+                "/.well-known/openid-configuration",
+                web::get().to(move || {
+                    let metadata = metadata.clone();
+                    async move { HttpResponse::Ok().json(metadata) }
+                }),
+            )
+            .route(
+                "/jwks",
+                web::get().to(|| async { HttpResponse::Ok().json(json!({ "keys": [] })) }),
+            )
+            .route(
+                "/token",
+                web::post().to(move |form: web::Form<HashMap<String, String>>| {
+                    let mut claims = user_claims.lock().unwrap().clone();
+                    let now = Local::now().timestamp();
+                    claims["iss"] = json!(issuer);
+                    claims["aud"] = json!(CLIENT_ID);
+                    claims["iat"] = json!(now);
+                    claims["exp"] = json!(now + 300);
+                    claims["nonce"] = json!(form.get("code"));
+                    async move {
+                        HttpResponse::Ok().json(json!({
+                            "access_token": "mock access token",
+                            "token_type": "Bearer",
+                            "id_token": sign_id_token(&claims),
+                        }))
+                    }
+                }),
+            )
+    })
+    .workers(1)
+    .listen(listener)?
+    .run();
+    actix_web::rt::spawn(server);
+    Ok(issuer)
+}
+
+#[actix_web::test]
+async fn test_oidc_callback() -> Result<(), DfrError> {
+    let test_path = env::temp_dir().join("dfr_test_oidc");
+    let _ = fs::remove_dir_all(&test_path);
+    fs::create_dir_all(&test_path)?;
+    let db = PoolDatabaseManager::new(test_path.join("db.sqlite").to_str().unwrap())?;
+    db.create_tables()?;
+    let now = Local::now();
+    db.insert_user(&UserInfo {
+        user_name: String::from("admin"),
+        password_hash: hash_password("admin password")?,
+        role: UserRole::Admin,
+        allowed_paths: vec![],
+        created_time: now,
+        modified_time: now,
+    })?;
+
+    let user_claims = Arc::new(Mutex::new(json!({})));
+    let issuer_url = start_mock_provider(user_claims.clone())?;
+    let settings = Settings {
+        oidc: OidcSettings {
+            issuer_url,
+            client_id: String::from(CLIENT_ID),
+            client_secret: String::from(CLIENT_SECRET),
+            redirect_url: String::from("http://localhost/api/login/oidc/callback"),
+            username_claim: String::from("preferred_username"),
+            role_claim: String::from("groups"),
+            role_mapping: HashMap::from([(String::from("dfr-viewers"), UserRole::Viewer)]),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(SharedSettings::from(settings)))
+            .app_data(web::Data::new(SharedLoginLimiter::new()))
+            .wrap(SessionMiddleware::new(
+                CookieSessionStore::default(),
+                Key::generate(),
+            ))
+            .service(login_oidc)
+            .service(login_oidc_callback),
+    )
+    .await;
+
+    // login at the mock provider, which redirects back with the nonce as authorization code
+    let login = |user_name: &str| {
+        *user_claims.lock().unwrap() = json!({
+            "sub": format!("sub-{}", user_name),
+            "preferred_username": user_name,
+            "groups": ["dfr-viewers"],
+        });
+        async {
+            let req = TestRequest::get().uri("/api/login/oidc").to_request();
+            let resp = call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::FOUND);
+            let cookie = resp.response().cookies().next().unwrap().into_owned();
+            let location = resp.headers().get(header::LOCATION).unwrap();
+            let authorize_url = Url::parse(location.to_str().unwrap()).unwrap();
+            let query = authorize_url
+                .query_pairs()
+                .into_owned()
+                .collect::<HashMap<_, _>>();
+            let callback_url = Url::parse_with_params(
+                "http://localhost/api/login/oidc/callback",
+                [("code", &query["nonce"]), ("state", &query["state"])],
+            )
+            .unwrap();
+            let req = TestRequest::get()
+                .uri(&format!(
+                    "{}?{}",
+                    callback_url.path(),
+                    callback_url.query().unwrap()
+                ))
+                .cookie(Cookie::new(
+                    cookie.name().to_string(),
+                    cookie.value().to_string(),
+                ))
+                .to_request();
+            let resp = call_service(&app, req).await;
+            let status = resp.status();
+            let body = String::from_utf8(read_body(resp).await.to_vec()).unwrap();
+            (status, body)
+        }
+    };
+
+    // unknown users are created without local password and with the mapped role
+    let (status, _) = login("alice").await;
+    assert_eq!(status, StatusCode::FOUND);
+    let user_info = db.get_user_by_name("alice")?.unwrap();
+    assert!(user_info.password_hash.is_empty());
+    assert_eq!(user_info.role, UserRole::Viewer);
+
+    // the provider must not take over a local user with the same name
+    let (status, body) = login("admin").await;
+    assert_ne!(status, StatusCode::FOUND);
+    assert!(body.contains("has a local password"), "{}", body);
+    assert_eq!(db.get_user_by_name("admin")?.unwrap().role, UserRole::Admin);
+
+    fs::remove_dir_all(&test_path)?;
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use dup_file_remover::utils::{
+    error::DfrError,
+    password::{hash_password, is_password_hash, verify_password},
+};
+
+#[test]
+fn test_password_hash() -> Result<(), DfrError> {
+    let password_hash = hash_password("dfr_password")?;
+    assert!(password_hash.starts_with("$argon2id$"));
+    assert!(is_password_hash(password_hash.as_str()));
+    assert!(!is_password_hash("dfr_password"));
+    assert!(verify_password("dfr_password", password_hash.as_str()));
+    assert!(!verify_password("wrong_password", password_hash.as_str()));
+    assert!(!verify_password("dfr_password", "dfr_password"));
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use std::{env, fs, path::Path};
+
+use chrono::Local;
+use dup_file_remover::{
+    database::{
+        file_info::{file_md5, FileInfo},
+        sqlite::PoolDatabaseManager,
+    },
+    model::settings::{FileHashType, ListSettings},
+    utils::{
+        error::DfrError,
+        payload_hash::{is_payload_hash_file, payload_md5},
+    },
+};
+
+fn jpeg_segment(marker: u8, data: &[u8]) -> Vec<u8> {
+    let mut segment = vec![0xFF, marker];
+    segment.extend_from_slice(&(data.len() as u16 + 2).to_be_bytes());
+    segment.extend_from_slice(data);
+    segment
+}
+
+fn png_chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+    chunk.extend_from_slice(chunk_type);
+    chunk.extend_from_slice(data);
+    // the crc is not checked
+    chunk.extend_from_slice(&[0; 4]);
+    chunk
+}
+
+fn mp4_box(box_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut mp4_box = (data.len() as u32 + 8).to_be_bytes().to_vec();
+    mp4_box.extend_from_slice(box_type);
+    mp4_box.extend_from_slice(data);
+    mp4_box
+}
+
+/// Write the file with and without metadata, return the payload md5 of both
+fn payload_md5_pair(
+    test_path: &Path,
+    file_name: &str,
+    plain: Vec<Vec<u8>>,
+    tagged: Vec<Vec<u8>>,
+) -> Result<(Option<String>, Option<String>), DfrError> {
+    let plain_path = test_path.join(format!("plain_{}", file_name));
+    let tagged_path = test_path.join(format!("tagged_{}", file_name));
+    fs::write(&plain_path, plain.concat())?;
+    fs::write(&tagged_path, tagged.concat())?;
+    Ok((
+        payload_md5(plain_path.to_str().unwrap())?,
+        payload_md5(tagged_path.to_str().unwrap())?,
+    ))
+}
+
+#[tokio::test]
+async fn test_payload_md5() -> Result<(), DfrError> {
+    let test_path = env::temp_dir().join("dfr_test_payload_hash");
+    let _ = fs::remove_dir_all(&test_path);
+    fs::create_dir_all(&test_path)?;
+    assert!(is_payload_hash_file("IMG_0001.JPG"));
+    assert!(!is_payload_hash_file("notes.txt"));
+
+    // exif, xmp and a comment are added to a jpeg
+    let quantization = jpeg_segment(0xDB, &[1; 65]);
+    let scan = [
+        jpeg_segment(0xDA, &[3, 1, 0, 2, 17, 3, 17]),
+        vec![0x12, 0x34, 0xFF, 0xD9],
+    ]
+    .concat();
+    let (plain, tagged) = payload_md5_pair(
+        &test_path,
+        "photo.jpg",
+        vec![vec![0xFF, 0xD8], quantization.clone(), scan.clone()],
+        vec![
+            vec![0xFF, 0xD8],
+            jpeg_segment(0xE1, b"Exif\0\0MM"),
+            jpeg_segment(0xE1, b"http://ns.adobe.com/xap/1.0/\0<x:xmpmeta/>"),
+            quantization.clone(),
+            jpeg_segment(0xFE, b"edited"),
+            scan.clone(),
+        ],
+    )?;
+    assert!(plain.is_some());
+    assert_eq!(plain, tagged);
+    // a changed image is not the same payload
+    let changed_path = test_path.join("changed.jpg");
+    fs::write(
+        &changed_path,
+        [vec![0xFF, 0xD8], jpeg_segment(0xDB, &[2; 65]), scan].concat(),
+    )?;
+    assert_ne!(plain, payload_md5(changed_path.to_str().unwrap())?);
+
+    // text and exif chunks are added to a png
+    let header = png_chunk(b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0]);
+    let data = png_chunk(b"IDAT", &[0x78, 0x9C, 0x63, 0x60, 0, 0, 0, 2, 0, 1]);
+    let end = png_chunk(b"IEND", &[]);
+    let signature = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    let (plain, tagged) = payload_md5_pair(
+        &test_path,
+        "image.png",
+        vec![signature.clone(), header.clone(), data.clone(), end.clone()],
+        vec![
+            signature,
+            header,
+            png_chunk(b"tEXt", b"Software\0editor"),
+            png_chunk(b"eXIf", b"MM\0*"),
+            data,
+            end,
+        ],
+    )?;
+    assert!(plain.is_some());
+    assert_eq!(plain, tagged);
+
+    // ID3v2 and ID3v1 tags are added to an mp3
+    let frames = [0xFF, 0xFB, 0x90, 0x64].repeat(100);
+    let id3v2 = [b"ID3\x04\x00\x00\x00\x00\x00\x14".to_vec(), vec![0; 20]].concat();
+    let id3v1 = [b"TAG".to_vec(), vec![b' '; 125]].concat();
+    let (plain, tagged) = payload_md5_pair(
+        &test_path,
+        "song.mp3",
+        vec![frames.clone()],
+        vec![id3v2, frames, id3v1],
+    )?;
+    assert!(plain.is_some());
+    assert_eq!(plain, tagged);
+
+    // user data is added to an mp4, which moves the media data
+    let file_type = mp4_box(b"ftyp", b"isom\0\0\x02\0isom");
+    let media_data = mp4_box(b"mdat", &[7; 64]);
+    let (plain, tagged) = payload_md5_pair(
+        &test_path,
+        "video.mp4",
+        vec![
+            file_type.clone(),
+            mp4_box(b"moov", &mp4_box(b"mvhd", &[0; 20])),
+            media_data.clone(),
+        ],
+        vec![
+            file_type,
+            mp4_box(
+                b"moov",
+                &[
+                    mp4_box(b"mvhd", &[0; 20]),
+                    mp4_box(b"udta", b"\xA9nam title"),
+                ]
+                .concat(),
+            ),
+            media_data,
+        ],
+    )?;
+    assert!(plain.is_some());
+    assert_eq!(plain, tagged);
+
+    // other files have no payload md5, broken media files are an error
+    let text_path = test_path.join("notes.txt");
+    fs::write(&text_path, "notes")?;
+    assert_eq!(payload_md5(text_path.to_str().unwrap())?, None);
+    let broken_path = test_path.join("broken.png");
+    fs::write(&broken_path, "not a png")?;
+    assert!(payload_md5(broken_path.to_str().unwrap()).is_err());
+
+    // tagged copies are duplicates only if grouped by payload md5
+    let db = PoolDatabaseManager::new(test_path.join("db.sqlite").to_str().unwrap())?;
+    db.create_tables()?;
+    for file_name in [
+        "plain_photo.jpg",
+        "tagged_photo.jpg",
+        "plain_song.mp3",
+        "notes.txt",
+    ] {
+        let file_path = test_path.join(file_name).to_string_lossy().to_string();
+        let mut file_info = FileInfo::new(&file_path, 1, Local::now())?;
+        file_info.inode_info.md5 = Some(file_md5(&file_path).await?.0);
+        file_info.inode_info.payload_md5 = payload_md5(&file_path)?;
+        db.insert_file_info(&file_info)?;
+    }
+    let file_info_list = db.list_files(&ListSettings::default(), &[])?;
+    assert_eq!(file_info_list.total_count, 0);
+    let query_list_params = ListSettings {
+        group_by: Some(FileHashType::Payload),
+        ..Default::default()
+    };
+    let file_info_list = db.list_files(&query_list_params, &[])?;
+    assert_eq!(file_info_list.total_count, 2);
+    assert_eq!(file_info_list.file_info_list[0].md5_count, 2);
+    let mut file_names = file_info_list
+        .file_info_list
+        .iter()
+        .map(|item| item.file_info.file_name.as_str())
+        .collect::<Vec<_>>();
+    file_names.sort();
+    assert_eq!(file_names, vec!["plain_photo.jpg", "tagged_photo.jpg"]);
+
+    fs::remove_dir_all(&test_path)?;
+    Ok(())
+}
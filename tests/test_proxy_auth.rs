@@ -0,0 +1,26 @@
+use std::net::IpAddr;
+
+use dup_file_remover::{controller::user::is_trusted_proxy, model::settings::SystemSettings};
+
+#[test]
+fn test_trusted_proxy() {
+    let settings = SystemSettings {
+        proxy_auth_header: String::from("X-Forwarded-User"),
+        proxy_auth_trusted_ips: vec![
+            String::from("10.0.0.0/8"),
+            String::from("192.168.1.10"),
+            String::from("fd00::/8"),
+            String::from("invalid"),
+        ],
+        ..Default::default()
+    };
+    let is_trusted = |ip: &str| is_trusted_proxy(ip.parse::<IpAddr>().unwrap(), &settings);
+    assert!(is_trusted("10.1.2.3"));
+    assert!(is_trusted("192.168.1.10"));
+    assert!(is_trusted("fd00::1"));
+    // ipv4 client of a dual stack listener
+    assert!(is_trusted("::ffff:10.1.2.3"));
+    assert!(!is_trusted("192.168.1.11"));
+    assert!(!is_trusted("11.0.0.1"));
+    assert!(!is_trusted("::1"));
+}
@@ -0,0 +1,57 @@
+use chrono::Local;
+use dup_file_remover::{
+    model::{
+        export::{DuplicateFile, DuplicateGroup},
+        report::{DuplicateReport, ReportBucket, ReportSummary},
+    },
+    utils::report::{escape_html, format_size, render_html_report},
+};
+
+#[test]
+fn test_render_html_report() {
+    assert_eq!(format_size(512), "512 B");
+    assert_eq!(format_size(1536), "1.5 KiB");
+    assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    assert_eq!(
+        escape_html("<a href=\"x\">Tom & Jerry's</a>"),
+        "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+    );
+
+    let file = DuplicateFile {
+        file_path: String::from("/data/<script>.jpg"),
+        dir_path: String::from("/data"),
+        file_name: String::from("<script>.jpg"),
+        inode: 42,
+        dev_id: 1,
+        nlink: 1,
+        modified: Local::now(),
+    };
+    let report = DuplicateReport {
+        generated_time: Local::now(),
+        summary: ReportSummary {
+            group_count: 1,
+            file_count: 2,
+            duplicate_size: 2048,
+            reclaimable_size: 1024,
+        },
+        top_groups: vec![DuplicateGroup {
+            md5: String::from("d41d8cd98f00b204e9800998ecf8427e"),
+            size: 1024,
+            md5_count: 2,
+            reclaimable_size: 1024,
+            files: vec![file.clone(), file],
+        }],
+        directories: vec![ReportBucket {
+            name: String::from("/data"),
+            file_count: 2,
+            duplicate_size: 2048,
+        }],
+        extensions: Vec::new(),
+    };
+    let html = render_html_report(&report);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<details><summary><strong>1.0 KiB</strong> reclaimable"));
+    assert!(html.contains("/data/&lt;script&gt;.jpg"));
+    assert!(!html.contains("<script"));
+    assert!(html.contains("<h2>Duplicates by Extension</h2>\n<p>No duplicate files.</p>"));
+}
@@ -0,0 +1,43 @@
+use std::{env, fs};
+
+use dup_file_remover::{
+    model::settings::{Settings, SystemSettings},
+    utils::error::DfrError,
+};
+
+#[test]
+fn test_session_key() -> Result<(), DfrError> {
+    let config_dir = env::temp_dir().join("dfr_test_session_key");
+    fs::create_dir_all(&config_dir)?;
+    let mut settings = Settings::default();
+    settings.system.config_file_path = config_dir.join("config").display().to_string();
+
+    // a new key is generated and kept in settings
+    let key = settings.init_session_key()?;
+    assert_eq!(settings.session.secret_key.len(), 128);
+    assert!(config_dir.join("config.toml").exists());
+    assert_eq!(settings.init_session_key()?.master(), key.master());
+
+    // an invalid key is replaced
+    settings.session.secret_key = String::from("invalid");
+    let new_key = settings.init_session_key()?;
+    assert_ne!(new_key.master(), key.master());
+    assert_eq!(settings.session.secret_key.len(), 128);
+
+    fs::remove_dir_all(&config_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_validate_totp_with_proxy_auth() {
+    let mut settings = SystemSettings {
+        totp_required_for_delete: true,
+        ..Default::default()
+    };
+    assert!(settings.validate().is_ok());
+    // proxy logins are never verified with TOTP, they could never delete permanently
+    settings.proxy_auth_header = String::from("X-Forwarded-User");
+    assert!(settings.validate().is_err());
+    settings.totp_required_for_delete = false;
+    assert!(settings.validate().is_ok());
+}
@@ -1,8 +1,22 @@
-use chrono::Local;
+use std::{env, path::Path};
+
+use chrono::{Duration, Local};
 use dup_file_remover::{
-    database::{file_info::FileInfo, sqlite::PoolDatabaseManager},
-    model::settings::ListSettings,
-    utils::error::DfrError,
+    controller::audit::export_audit_logs_csv,
+    database::{
+        api_token::{ApiToken, TokenScope},
+        audit_log::{AuditAction, AuditLog},
+        file_info::FileInfo,
+        sqlite::PoolDatabaseManager,
+        user_info::{UserInfo, UserRole},
+        user_totp::UserTotp,
+    },
+    model::{audit::AuditLogListParams, settings::ListSettings},
+    utils::{
+        error::DfrError,
+        token::{generate_api_token, hash_api_token, API_TOKEN_PREFIX},
+        totp::{generate_totp_secret, hash_recovery_code},
+    },
 };
 
 #[test]
@@ -28,7 +42,7 @@ fn test_list_files() -> Result<(), DfrError> {
         max_file_size: Some(1000),
         ..Default::default()
     };
-    let result = database_manager.list_files(&query_list_params);
+    let result = database_manager.list_files(&query_list_params, &[]);
     assert!(result.is_ok());
     let files = result.unwrap();
     println!("Total file count: {}", files.total_count);
@@ -42,3 +56,243 @@ fn test_list_files() -> Result<(), DfrError> {
     //database_manager.drop_tables()?;
     Ok(())
 }
+
+#[test]
+fn test_users() -> Result<(), DfrError> {
+    let db_path = env::temp_dir().join("dfr_test_users.db");
+    let database_manager = PoolDatabaseManager::new(db_path.to_str().unwrap())?;
+    database_manager.drop_tables()?;
+    database_manager.create_tables()?;
+    let now = Local::now();
+    let mut user_info = UserInfo {
+        user_name: String::from("viewer"),
+        password_hash: String::from("hash"),
+        role: UserRole::Viewer,
+        allowed_paths: vec![String::from("/data/share")],
+        created_time: now,
+        modified_time: now,
+    };
+    database_manager.insert_user(&user_info)?;
+    assert!(database_manager.insert_user(&user_info).is_err());
+    assert_eq!(database_manager.count_users(None)?, 1);
+    assert_eq!(database_manager.count_users(Some(UserRole::Admin))?, 0);
+
+    user_info.user_name = String::from("operator");
+    user_info.role = UserRole::Operator;
+    assert_eq!(database_manager.update_user("viewer", &user_info)?, 1);
+    assert!(database_manager.get_user_by_name("viewer")?.is_none());
+    let db_user_info = database_manager.get_user_by_name("operator")?.unwrap();
+    assert_eq!(db_user_info.role, UserRole::Operator);
+    assert_eq!(
+        db_user_info.allowed_paths,
+        vec![String::from("/data/share")]
+    );
+    assert!(db_user_info.is_path_allowed(Path::new("/data/share")));
+    assert!(db_user_info.is_path_allowed(Path::new("/data/share/a/b.txt")));
+    assert!(!db_user_info.is_path_allowed(Path::new("/data/shared")));
+    assert!(!db_user_info.is_path_allowed(Path::new("/data/share/../other")));
+    assert!(UserRole::Operator > UserRole::Viewer);
+
+    assert_eq!(database_manager.list_users()?.total_count, 1);
+    assert_eq!(database_manager.remove_user("operator")?, 1);
+    assert_eq!(database_manager.count_users(None)?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_api_tokens() -> Result<(), DfrError> {
+    let db_path = env::temp_dir().join("dfr_test_api_tokens.db");
+    let database_manager = PoolDatabaseManager::new(db_path.to_str().unwrap())?;
+    database_manager.drop_tables()?;
+    database_manager.create_tables()?;
+    let token = generate_api_token();
+    assert!(token.starts_with(API_TOKEN_PREFIX));
+    assert_ne!(token, generate_api_token());
+    let now = Local::now();
+    let api_token = ApiToken {
+        id: 0,
+        user_name: String::from("admin"),
+        name: String::from("cron"),
+        token_hash: hash_api_token(token.as_str()),
+        scopes: vec![TokenScope::Scan],
+        created_time: now,
+        expire_time: Some(now + Duration::days(1)),
+        last_used_time: None,
+        revoked: false,
+    };
+    let id = database_manager.insert_api_token(&api_token)?;
+    let db_api_token = database_manager
+        .get_api_token_by_hash(hash_api_token(token.as_str()).as_str())?
+        .unwrap();
+    assert_eq!(db_api_token.id, id);
+    assert_eq!(db_api_token.scopes, vec![TokenScope::Scan]);
+    assert!(db_api_token.is_valid(&now));
+    assert!(!db_api_token.is_valid(&(now + Duration::days(2))));
+
+    database_manager.update_api_token_last_used_time(id, &now)?;
+    assert_eq!(database_manager.revoke_api_token("other", id)?, 0);
+    assert_eq!(database_manager.revoke_api_token("admin", id)?, 1);
+    let api_token_list = database_manager.list_api_tokens("admin")?;
+    assert_eq!(api_token_list.total_count, 1);
+    assert!(api_token_list.api_token_list[0].last_used_time.is_some());
+    assert!(!api_token_list.api_token_list[0].is_valid(&now));
+    Ok(())
+}
+
+#[test]
+fn test_user_totp() -> Result<(), DfrError> {
+    let db_path = env::temp_dir().join("dfr_test_user_totp.db");
+    let database_manager = PoolDatabaseManager::new(db_path.to_str().unwrap())?;
+    database_manager.drop_tables()?;
+    database_manager.create_tables()?;
+    assert!(database_manager.get_user_totp("admin")?.is_none());
+    let user_totp = UserTotp {
+        user_name: String::from("admin"),
+        secret: generate_totp_secret(),
+        recovery_code_hashes: vec![hash_recovery_code("abcde-12345")],
+        last_step: 10,
+        created_time: Local::now(),
+    };
+    database_manager.save_user_totp(&user_totp)?;
+
+    // codes can not be replayed
+    assert_eq!(database_manager.update_user_totp_last_step("admin", 10)?, 0);
+    assert_eq!(database_manager.update_user_totp_last_step("admin", 11)?, 1);
+
+    // recovery codes can be used once
+    let recovery_code_hash = hash_recovery_code("ABCDE12345");
+    assert_eq!(
+        database_manager.remove_user_totp_recovery_code("admin", recovery_code_hash.as_str())?,
+        1
+    );
+    assert_eq!(
+        database_manager.remove_user_totp_recovery_code("admin", recovery_code_hash.as_str())?,
+        0
+    );
+    let db_user_totp = database_manager.get_user_totp("admin")?.unwrap();
+    assert_eq!(db_user_totp.last_step, 11);
+    assert!(db_user_totp.recovery_code_hashes.is_empty());
+
+    assert_eq!(database_manager.remove_user_totp("admin")?, 1);
+    assert!(database_manager.get_user_totp("admin")?.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_audit_logs() -> Result<(), DfrError> {
+    let db_path = env::temp_dir().join("dfr_test_audit_logs.db");
+    let database_manager = PoolDatabaseManager::new(db_path.to_str().unwrap())?;
+    database_manager.drop_tables()?;
+    database_manager.create_tables()?;
+    let now = Local::now();
+    for (hours_ago, user_name, action, path, success) in [
+        (
+            3,
+            Some("alice"),
+            AuditAction::MoveToTrash,
+            "/data/a,b.txt",
+            true,
+        ),
+        (
+            2,
+            Some("bob"),
+            AuditAction::DeleteFile,
+            "/data/say \"hi\".txt",
+            false,
+        ),
+        (1, Some("alice"), AuditAction::Login, "alice", true),
+        (0, None, AuditAction::PurgeTrashFile, "/data/c.txt", true),
+    ] {
+        database_manager.insert_audit_log(&AuditLog {
+            time: now - Duration::hours(hours_ago),
+            user_name: user_name.map(String::from),
+            client_ip: user_name.map(|_| String::from("10.0.0.1")),
+            action,
+            path: Some(String::from(path)),
+            md5: None,
+            size: None,
+            success,
+            message: (!success).then(|| String::from("Permission denied")),
+        })?;
+    }
+    let list = |query_list_params: AuditLogListParams| {
+        database_manager
+            .list_audit_logs(&query_list_params)
+            .map(|audit_log_list| audit_log_list.audit_log_list)
+    };
+
+    // newest first
+    let audit_logs = list(AuditLogListParams::default())?;
+    assert_eq!(audit_logs.len(), 4);
+    assert_eq!(audit_logs[0].action, AuditAction::PurgeTrashFile);
+    assert_eq!(audit_logs[0].user_name, None);
+    assert_eq!(audit_logs[3].path.as_deref(), Some("/data/a,b.txt"));
+
+    let audit_logs = list(AuditLogListParams {
+        user_name: Some(String::from("alice")),
+        ..Default::default()
+    })?;
+    assert_eq!(audit_logs.len(), 2);
+    let audit_logs = list(AuditLogListParams {
+        action: Some(AuditAction::DeleteFile),
+        ..Default::default()
+    })?;
+    assert_eq!(audit_logs.len(), 1);
+    assert_eq!(audit_logs[0].user_name.as_deref(), Some("bob"));
+    let audit_logs = list(AuditLogListParams {
+        start_time: Some(now - Duration::minutes(150)),
+        end_time: Some(now - Duration::minutes(30)),
+        ..Default::default()
+    })?;
+    assert_eq!(audit_logs.len(), 2);
+    assert_eq!(audit_logs[0].action, AuditAction::Login);
+    let audit_logs = list(AuditLogListParams {
+        success: Some(false),
+        ..Default::default()
+    })?;
+    assert_eq!(audit_logs.len(), 1);
+    assert_eq!(audit_logs[0].message.as_deref(), Some("Permission denied"));
+
+    // pages of 3
+    let audit_log_list = database_manager.list_audit_logs(&AuditLogListParams {
+        page_count: 3,
+        ..Default::default()
+    })?;
+    assert_eq!(audit_log_list.total_count, 4);
+    assert_eq!(audit_log_list.audit_log_list.len(), 3);
+    let audit_logs = list(AuditLogListParams {
+        page_no: 2,
+        page_count: 3,
+        ..Default::default()
+    })?;
+    assert_eq!(audit_logs.len(), 1);
+    assert_eq!(audit_logs[0].action, AuditAction::MoveToTrash);
+
+    // paths with commas and quotes are quoted in csv
+    let (data, export_count) = export_audit_logs_csv(&database_manager, Default::default())?;
+    assert_eq!(export_count, 4);
+    let csv = String::from_utf8(data).unwrap();
+    assert!(csv.contains(",\"/data/a,b.txt\","));
+    assert!(csv.contains(",\"/data/say \"\"hi\"\".txt\","));
+    let mut reader = csv::Reader::from_reader(csv.as_bytes());
+    let path_index = reader
+        .headers()
+        .unwrap()
+        .iter()
+        .position(|header| header == "path")
+        .unwrap();
+    let paths = reader
+        .records()
+        .map(|record| record.unwrap()[path_index].to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec![
+            "/data/c.txt",
+            "alice",
+            "/data/say \"hi\".txt",
+            "/data/a,b.txt"
+        ]
+    );
+    Ok(())
+}
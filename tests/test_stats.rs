@@ -0,0 +1,60 @@
+mod commons;
+
+use std::fs;
+
+use chrono::{Duration, Local};
+use commons::TestFileDb;
+use dup_file_remover::{database::scan_history::ScanHistory, utils::error::DfrError};
+
+#[test]
+fn test_file_stats() -> Result<(), DfrError> {
+    let test_file_db = TestFileDb::new("dfr_test_stats")?;
+    let (test_path, db) = (&test_file_db.test_path, &test_file_db.db);
+    let insert_file = |file_path, content| test_file_db.insert_file(file_path, content);
+    insert_file("photos/a.jpg", "aaaa")?;
+    insert_file("photos/b.jpg", "aaaa")?;
+    insert_file("docs/a.jpg", "aaaa")?;
+    insert_file("docs/c.txt", "cc")?;
+
+    let file_stats = db.get_file_stats(&[])?;
+    assert_eq!(file_stats.scan_version, 0);
+    assert_eq!(file_stats.total_file_count, 4);
+    assert_eq!(file_stats.total_size, 14);
+    assert_eq!(file_stats.duplicate_group_count, 1);
+    assert_eq!(file_stats.duplicate_file_count, 3);
+    assert_eq!(file_stats.redundant_size, 8);
+    assert_eq!(file_stats.extensions[0].name, "jpg");
+    assert_eq!(file_stats.extensions[0].duplicate_size, 12);
+    assert_eq!(file_stats.extensions[1].duplicate_file_count, 0);
+    assert_eq!(file_stats.size_buckets.len(), 1);
+    assert_eq!(file_stats.size_buckets[0].file_count, 4);
+    assert!(file_stats.top_directories[0].name.ends_with("photos"));
+    assert!(file_stats.last_scan.is_none());
+
+    // statistics are cached until the next scan is recorded
+    insert_file("docs/d.txt", "cc")?;
+    assert_eq!(db.get_file_stats(&[])?.duplicate_group_count, 1);
+    let end_time = Local::now();
+    db.insert_scan_history(&ScanHistory {
+        version: 42,
+        scan_path: test_path.to_string_lossy().to_string(),
+        start_time: end_time - Duration::seconds(90),
+        end_time,
+        scanned_file_count: 5,
+        success: true,
+    })?;
+    let file_stats = db.get_file_stats(&[])?;
+    assert_eq!(file_stats.scan_version, 42);
+    assert_eq!(file_stats.duplicate_group_count, 2);
+    assert_eq!(file_stats.last_scan_duration_s, Some(90));
+
+    // only files under the allowed paths are counted
+    let allowed_paths = vec![test_path.join("docs").to_string_lossy().to_string()];
+    let file_stats = db.get_file_stats(&allowed_paths)?;
+    assert_eq!(file_stats.total_file_count, 3);
+    assert_eq!(file_stats.duplicate_group_count, 1);
+    assert_eq!(file_stats.redundant_size, 2);
+
+    fs::remove_dir_all(test_path)?;
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use dup_file_remover::utils::{
+    error::DfrError,
+    totp::{
+        generate_recovery_codes, generate_totp_secret, totp_code, totp_provisioning_uri, totp_step,
+        verify_totp, RECOVERY_CODE_COUNT,
+    },
+};
+
+#[test]
+fn test_totp() -> Result<(), DfrError> {
+    // test vectors of RFC 6238 with secret "12345678901234567890", truncated to 6 digits
+    let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    assert_eq!(totp_code(secret, totp_step(59))?, "287082");
+    assert_eq!(totp_code(secret, totp_step(1111111109))?, "081804");
+    assert_eq!(totp_code(secret, totp_step(2000000000))?, "279037");
+
+    // codes of the adjacent time steps are accepted
+    assert_eq!(verify_totp(secret, "081804", 1111111109)?, Some(37037036));
+    assert_eq!(
+        verify_totp(secret, "081804", 1111111109 + 30)?,
+        Some(37037036)
+    );
+    assert_eq!(verify_totp(secret, "081804", 1111111109 + 90)?, None);
+    assert_eq!(verify_totp(secret, "abcdef", 1111111109)?, None);
+
+    let secret = generate_totp_secret();
+    assert_eq!(secret.len(), 32);
+    assert!(totp_provisioning_uri(secret.as_str(), "admin")
+        .starts_with(format!("otpauth://totp/DupFileRemover:admin?secret={}", secret).as_str()));
+    let recovery_codes = generate_recovery_codes();
+    assert_eq!(recovery_codes.len(), RECOVERY_CODE_COUNT);
+    assert_eq!(recovery_codes[0].len(), 11);
+    Ok(())
+}
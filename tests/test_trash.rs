@@ -0,0 +1,268 @@
+use std::{
+    env,
+    fs::{self, FileTimes, Permissions},
+    os::unix::fs::PermissionsExt,
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Local};
+use dup_file_remover::{
+    controller::{
+        audit::AuditContext,
+        files::_delete_file,
+        scan::scan_all_files,
+        trash::{_restore_trash_file, check_trash_files, move_file_to_trash},
+    },
+    database::{file_info::FileInfo, sqlite::PoolDatabaseManager},
+    model::{
+        files::DeleteFileRequest,
+        scan::SharedScanStatus,
+        settings::{ScanSettings, Settings},
+    },
+    utils::{compress::decompressed_md5, error::DfrError},
+    SharedSettings,
+};
+use md5::{Digest, Md5};
+
+/// POSIX ACL in the `system.posix_acl_access` format, which grants read to user 1000
+fn posix_acl() -> Vec<u8> {
+    let entries: [(u16, u16, u32); 5] = [
+        // user owner, named user, group owner, mask and other
+        (0x01, 6, u32::MAX),
+        (0x02, 4, 1000),
+        (0x04, 4, u32::MAX),
+        (0x10, 4, u32::MAX),
+        (0x20, 0, u32::MAX),
+    ];
+    let mut acl = 2u32.to_le_bytes().to_vec();
+    for (tag, perm, id) in entries {
+        acl.extend_from_slice(&tag.to_le_bytes());
+        acl.extend_from_slice(&perm.to_le_bytes());
+        acl.extend_from_slice(&id.to_le_bytes());
+    }
+    acl
+}
+
+#[tokio::test]
+async fn test_restore_file_attributes() -> Result<(), DfrError> {
+    let test_path = env::temp_dir().join("dfr_test_restore_attributes");
+    let _ = fs::remove_dir_all(&test_path);
+    let data_path = test_path.join("data");
+    let trash_path = test_path.join("trash");
+    fs::create_dir_all(&data_path)?;
+    fs::create_dir_all(&trash_path)?;
+    let data_path = fs::canonicalize(&data_path)?;
+
+    // a file with extended attributes, an ACL and old timestamps, and a copy of it. Its access
+    // time is changed when the scan reads it.
+    let file_path = data_path.join("photo.jpg");
+    fs::write(&file_path, "photo content")?;
+    fs::write(data_path.join("photo copy.jpg"), "photo content")?;
+    xattr::set(&file_path, "user.tags", b"holiday")?;
+    xattr::set(&file_path, "system.posix_acl_access", &posix_acl())?;
+    fs::set_permissions(&file_path, Permissions::from_mode(0o640))?;
+    let accessed = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+    fs::File::options()
+        .write(true)
+        .open(&file_path)?
+        .set_times(
+            FileTimes::new()
+                .set_accessed(accessed)
+                .set_modified(modified),
+        )?;
+
+    let db = PoolDatabaseManager::new(test_path.join("db.sqlite").to_str().unwrap())?;
+    db.create_tables()?;
+    let scan_request = ScanSettings {
+        scan_path: data_path.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+    scan_all_files(
+        &scan_request,
+        &db,
+        &SharedScanStatus::new(),
+        trash_path.clone(),
+    )
+    .await?;
+    let mut settings = Settings::default();
+    settings.system.trash_path = trash_path.to_string_lossy().to_string();
+    let settings = SharedSettings::from(settings);
+    let delete_file_request = DeleteFileRequest {
+        dir_path: data_path.to_string_lossy().to_string(),
+        file_name: String::from("photo.jpg"),
+        delete_permanently: Some(false),
+        force_delete: Some(false),
+    };
+    _delete_file(delete_file_request, &db, &settings).await?;
+    assert!(!file_path.exists());
+
+    let trash_file_info = db.get_trash_file_by_path(&data_path.to_string_lossy(), "photo.jpg")?;
+    let result = _restore_trash_file(&trash_file_info, &db, &settings).await?;
+    assert_eq!(
+        xattr::get(&file_path, "user.tags")?,
+        Some(b"holiday".to_vec())
+    );
+    assert_eq!(
+        xattr::get(&file_path, "system.posix_acl_access")?,
+        Some(posix_acl())
+    );
+    let metadata = fs::metadata(&file_path)?;
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+    assert_eq!(metadata.modified()?, modified);
+    assert_eq!(
+        Some(DateTime::<Local>::from(metadata.accessed()?)),
+        trash_file_info.accessed
+    );
+    assert_eq!(fs::read_to_string(&file_path)?, "photo content");
+    // the create time can not be restored, everything else is restored
+    assert!(
+        result
+            .unrestored_attributes
+            .iter()
+            .all(|attribute| attribute == "created"),
+        "{:?}",
+        result.unrestored_attributes
+    );
+    assert!(db
+        .get_trash_file_by_path(&data_path.to_string_lossy(), "photo.jpg")
+        .is_err());
+
+    fs::remove_dir_all(&test_path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_check_trash_files() -> Result<(), DfrError> {
+    let test_path = env::temp_dir().join("dfr_test_check_trash");
+    let _ = fs::remove_dir_all(&test_path);
+    let data_path = test_path.join("data");
+    let trash_path = test_path.join("trash");
+    fs::create_dir_all(&data_path)?;
+    fs::create_dir_all(&trash_path)?;
+    let data_path = fs::canonicalize(&data_path)?;
+
+    // three files with copies are moved to trash
+    let contents = ["good", "corrupted", "missing"];
+    for content in contents {
+        fs::write(data_path.join(format!("{}.txt", content)), content)?;
+        fs::write(data_path.join(format!("{} copy.txt", content)), content)?;
+    }
+    let db = PoolDatabaseManager::new(test_path.join("db.sqlite").to_str().unwrap())?;
+    db.create_tables()?;
+    let scan_request = ScanSettings {
+        scan_path: data_path.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+    scan_all_files(
+        &scan_request,
+        &db,
+        &SharedScanStatus::new(),
+        trash_path.clone(),
+    )
+    .await?;
+    let mut settings = Settings::default();
+    settings.system.trash_path = trash_path.to_string_lossy().to_string();
+    let settings = SharedSettings::from(settings);
+    for content in contents {
+        let delete_file_request = DeleteFileRequest {
+            dir_path: data_path.to_string_lossy().to_string(),
+            file_name: format!("{}.txt", content),
+            delete_permanently: Some(false),
+            force_delete: Some(false),
+        };
+        _delete_file(delete_file_request, &db, &settings).await?;
+    }
+    let md5 = |content: &str| format!("{:x}", Md5::digest(content.as_bytes()));
+    fs::write(trash_path.join(md5("corrupted")), "changed")?;
+    fs::remove_file(trash_path.join(md5("missing")))?;
+    // a trash file without trash info and a file not named by md5
+    let orphan_md5 = md5("orphan");
+    fs::write(trash_path.join(orphan_md5.as_str()), "orphan")?;
+    fs::write(trash_path.join("notes.txt"), "notes")?;
+    // a partial file of a compression which was interrupted
+    let tmp_file_name = format!("{}.0123456789abcdef.zst.tmp", orphan_md5);
+    fs::write(trash_path.join(tmp_file_name.as_str()), "partial")?;
+
+    // only one check runs at a time, the first check waits for the settings lock held here
+    let audit_context = AuditContext::default();
+    let check = check_trash_files(&settings, &db, &audit_context);
+    tokio::pin!(check);
+    let settings_guard = settings.lock().await;
+    assert!(futures::poll!(&mut check).is_pending());
+    match check_trash_files(&settings, &db, &audit_context).await {
+        Err(DfrError::CustomError(e)) => assert!(e.message.contains("already in progress")),
+        _ => panic!("concurrent trash check must be rejected"),
+    }
+    drop(settings_guard);
+    let result = check.await?;
+    assert_eq!(result.checked_count, 2);
+    assert_eq!(result.corrupted_files, vec![md5("corrupted")]);
+    assert_eq!(result.missing_files, vec![md5("missing")]);
+    assert_eq!(result.unknown_files, vec![String::from("notes.txt")]);
+    // the orphan was just moved to trash, its trash info may not be inserted yet
+    assert!(result.orphan_files.is_empty());
+    assert!(trash_path.join(orphan_md5.as_str()).exists());
+    assert!(result.stale_tmp_files.is_empty());
+    assert!(trash_path.join(tmp_file_name.as_str()).exists());
+    let dir_path = data_path.to_string_lossy();
+    let trash_file_info = db.get_trash_file_by_path(&dir_path, "corrupted.txt")?;
+    assert!(trash_file_info.blob_corrupted && !trash_file_info.blob_missing);
+    let trash_file_info = db.get_trash_file_by_path(&dir_path, "missing.txt")?;
+    assert!(trash_file_info.blob_missing && !trash_file_info.blob_corrupted);
+    let trash_file_info = db.get_trash_file_by_path(&dir_path, "good.txt")?;
+    assert!(!trash_file_info.blob_missing && !trash_file_info.blob_corrupted);
+
+    // after the grace period the orphan and the partial file are removed, the unknown file is kept
+    settings.lock().await.system.orphan_trash_grace_period_s = 0;
+    let result = check_trash_files(&settings, &db, &audit_context).await?;
+    assert_eq!(result.orphan_files, vec![orphan_md5.clone()]);
+    assert!(!trash_path.join(orphan_md5.as_str()).exists());
+    assert_eq!(result.stale_tmp_files, vec![tmp_file_name.clone()]);
+    assert!(!trash_path.join(tmp_file_name.as_str()).exists());
+    assert!(trash_path.join("notes.txt").exists());
+
+    fs::remove_dir_all(&test_path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_concurrent_compressed_moves() -> Result<(), DfrError> {
+    let test_path = env::temp_dir().join("dfr_test_concurrent_compressed_moves");
+    let _ = fs::remove_dir_all(&test_path);
+    let trash_path = test_path.join("trash");
+    fs::create_dir_all(&trash_path)?;
+    let content = "duplicate file remover\n".repeat(10000);
+    let md5 = format!("{:x}", Md5::digest(content.as_bytes()));
+    let mut file_info_list = Vec::new();
+    for file_name in ["copy1.txt", "copy2.txt"] {
+        let file_path = test_path.join(file_name);
+        fs::write(&file_path, content.as_str())?;
+        let mut file_info = FileInfo::new(file_path.to_str().unwrap(), 1, Local::now())?;
+        file_info.inode_info.md5 = Some(md5.clone());
+        file_info_list.push(file_info);
+    }
+    let mut settings = Settings::default();
+    settings.system.trash_path = trash_path.to_string_lossy().to_string();
+    settings.system.trash_compression = true;
+    let settings = SharedSettings::from(settings);
+
+    // copies deleted at the same time are compressed to their own temporary files
+    let (result1, result2) = tokio::join!(
+        move_file_to_trash(&settings, &file_info_list[0]),
+        move_file_to_trash(&settings, &file_info_list[1]),
+    );
+    result1?;
+    result2?;
+    let trash_file_names = fs::read_dir(&trash_path)?
+        .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(trash_file_names, vec![format!("{}.zst", md5)]);
+    assert_eq!(
+        decompressed_md5(&trash_path.join(&trash_file_names[0]))?,
+        md5
+    );
+
+    fs::remove_dir_all(&test_path)?;
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use std::{env, fs};
+
+use dup_file_remover::utils::{
+    error::DfrError,
+    xdg_trash::{move_to_xdg_trash, restore_from_xdg_trash, trash_info_path},
+};
+
+#[test]
+fn test_xdg_trash_round_trip() -> Result<(), DfrError> {
+    let dir = env::temp_dir().join("dfr_test_xdg_trash");
+    let data_home = dir.join("data");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&data_home)?;
+    env::set_var("XDG_DATA_HOME", &data_home);
+
+    let file_path = dir.join("my photo.jpg");
+    fs::write(&file_path, "photo")?;
+    let trash_file_path = move_to_xdg_trash(&file_path)?;
+    assert!(!file_path.exists());
+    assert_eq!(trash_file_path, data_home.join("Trash/files/my photo.jpg"));
+    let trash_info = fs::read_to_string(trash_info_path(&trash_file_path).unwrap())?;
+    assert!(trash_info.starts_with("[Trash Info]\n"));
+    assert!(trash_info.contains("my%20photo.jpg\n"));
+
+    // same file name is trashed again
+    fs::write(&file_path, "photo")?;
+    let second_trash_file_path = move_to_xdg_trash(&file_path)?;
+    assert_eq!(
+        second_trash_file_path,
+        data_home.join("Trash/files/my photo.2.jpg")
+    );
+
+    restore_from_xdg_trash(&trash_file_path, &file_path)?;
+    assert!(file_path.exists());
+    assert!(!trash_info_path(&trash_file_path).unwrap().exists());
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}